@@ -0,0 +1,339 @@
+use std::fs;
+use std::time::{Duration, Instant};
+use chrono::Local;
+use crate::config::{render_filename, RuboxConfig, FILENAME_TIMESTAMP};
+use crate::llm_client::{ChatMessage, Usage};
+use crate::multi_model::{ask_model, elapsed_text, with_spinner};
+use crate::server_manager::ServerManager;
+
+// One prompt of a batch run and what the model made of it
+pub struct BatchResult {
+    pub prompt: String,
+    pub reply: Result<(String, Option<Usage>), String>,  // Reply, or the error on one line
+    pub elapsed: Duration,
+}
+
+// Prompts of a batch file: the blocks between lines holding only `---` when
+// the file has such a line, otherwise every non-empty line on its own
+pub fn split_prompts(content: &str) -> Vec<String> {
+    let blocks: Vec<String> = if content.lines().any(|line| line.trim() == "---") {
+        let mut blocks = vec![String::new()];
+        for line in content.lines() {
+            if line.trim() == "---" {
+                blocks.push(String::new());
+            } else if let Some(block) = blocks.last_mut() {
+                block.push_str(line);
+                block.push('\n');
+            }
+        }
+        blocks
+    } else {
+        content.lines().map(str::to_string).collect()
+    };
+    blocks
+        .into_iter()
+        .map(|prompt| prompt.trim().to_string())
+        .filter(|prompt| !prompt.is_empty())
+        .collect()
+}
+
+// Ask `model_name` every prompt in a fresh conversation, one after another.
+// Replies go to tmp_md (one file per prompt), the combined report to output.
+pub async fn run_batch(prompts: Vec<String>, model_name: &str, config: &RuboxConfig) -> anyhow::Result<()> {
+    let timestamp = Local::now().format(FILENAME_TIMESTAMP).to_string();
+    fs::create_dir_all(&config.directories.tmp_md)?;
+    fs::create_dir_all(&config.directories.output)?;
+
+    let name = config.display_name(model_name);
+    println!(
+        "{}Running {} prompt(s) against {}...{}",
+        config.ui.color_orange, prompts.len(), name, config.ui.color_reset
+    );
+
+    let started = Instant::now();
+    let results = run_prompts(config, model_name, &prompts, &timestamp, started).await?;
+    let total = started.elapsed();
+
+    let results_name = render_filename(&config.filenames.results, &[("timestamp", &timestamp)]);
+    let report_file = format!("{}/{}_batch.md", config.directories.output, results_name);
+    fs::write(&report_file, batch_report(&name, &results, total))?;
+
+    println!();
+    println!("{}Summary{}", config.ui.color_orange, config.ui.color_reset);
+    for line in summary_lines(&results, total) {
+        println!("  {}", line);
+    }
+    println!(
+        "{}Report saved to: {}{}",
+        config.ui.color_orange, report_file, config.ui.color_reset
+    );
+    println!();
+    Ok(())
+}
+
+// Every prompt against every model. Runs are grouped by model, so a managed
+// server loads each model once. `models` pairs each name with what
+// ensure_running starts for it. The grid goes to output as
+// <results name>_matrix.md and .json.
+pub async fn run_matrix(
+    prompts: Vec<String>,
+    models: &[(String, String)],
+    config: &RuboxConfig,
+    server: &mut ServerManager,
+) -> anyhow::Result<()> {
+    let timestamp = Local::now().format(FILENAME_TIMESTAMP).to_string();
+    fs::create_dir_all(&config.directories.tmp_md)?;
+    fs::create_dir_all(&config.directories.output)?;
+
+    println!(
+        "{}Running {} prompt(s) against {} model(s)...{}",
+        config.ui.color_orange, prompts.len(), models.len(), config.ui.color_reset
+    );
+
+    let mut runs = Vec::new();
+    let started = Instant::now();
+    for (index, (model_name, target)) in models.iter().enumerate() {
+        let name = config.display_name(model_name);
+        println!();
+        println!(
+            "{}[{}/{}] {} {}... (total {}){}",
+            config.ui.color_orange,
+            index + 1,
+            models.len(),
+            if config.llm.manage_server { "Loading" } else { "Asking" },
+            name,
+            elapsed_text(started.elapsed()),
+            config.ui.color_reset
+        );
+
+        // A model that won't load fails its row instead of ending the run
+        let loaded = if config.llm.manage_server {
+            server.stop()?;
+            server.ensure_running(config, Some(target)).await
+        } else {
+            Ok(())
+        };
+        println!("{}┌─ {} ─{}", config.ui.color_orange, name, config.ui.color_reset);
+        let results = match loaded {
+            Ok(()) => run_prompts(config, model_name, &prompts, &timestamp, started).await?,
+            Err(e) => {
+                let error = format!("llama-server did not start: {:#}", e).replace('\n', " ");
+                eprintln!("  {}✗ {}{}", config.ui.color_red, error, config.ui.color_reset);
+                prompts
+                    .iter()
+                    .map(|prompt| BatchResult { prompt: prompt.clone(), reply: Err(error.clone()), elapsed: Duration::ZERO })
+                    .collect()
+            }
+        };
+        runs.push((name, results));
+    }
+
+    let results_name = render_filename(&config.filenames.results, &[("timestamp", &timestamp)]);
+    let report_file = format!("{}/{}_matrix.md", config.directories.output, results_name);
+    let json_file = format!("{}/{}_matrix.json", config.directories.output, results_name);
+    fs::write(&report_file, matrix_table(&prompts, &runs))?;
+    fs::write(&json_file, serde_json::to_string_pretty(&matrix_json(&prompts, &runs, &timestamp))?)?;
+
+    println!();
+    println!("{}Summary{}", config.ui.color_orange, config.ui.color_reset);
+    for (name, results) in &runs {
+        println!("  {}: {}", name, model_totals(results));
+    }
+    println!(
+        "{}Results saved to: {} and {}{}",
+        config.ui.color_orange, report_file, json_file, config.ui.color_reset
+    );
+    println!();
+    Ok(())
+}
+
+// Markdown grid: a row per prompt, a column per model, each cell the timing
+// and token figures (or the error), and a last row of per-model totals
+pub fn matrix_table(prompts: &[String], runs: &[(String, Vec<BatchResult>)]) -> String {
+    let cell = |text: &str| text.replace('|', "\\|").replace('\n', " ");
+    let mut table = String::from("| # | Prompt |");
+    for (name, _) in runs {
+        table.push_str(&format!(" {} |", cell(name)));
+    }
+    table.push_str(&format!("\n|---|---|{}\n", "---|".repeat(runs.len())));
+
+    for (index, prompt) in prompts.iter().enumerate() {
+        table.push_str(&format!("| {} | {} |", index + 1, cell(&preview(prompt))));
+        for (_, results) in runs {
+            let text = match results.get(index).map(|r| (&r.reply, r.elapsed)) {
+                Some((Ok((_, usage)), elapsed)) => stats(usage.as_ref(), elapsed),
+                Some((Err(error), _)) => format!("✗ {}", error),
+                None => String::new(),
+            };
+            table.push_str(&format!(" {} |", cell(&text)));
+        }
+        table.push('\n');
+    }
+
+    table.push_str("| | **Total** |");
+    for (_, results) in runs {
+        table.push_str(&format!(" {} |", cell(&model_totals(results))));
+    }
+    table.push('\n');
+    table
+}
+
+// The same grid with the replies, for scripts: one entry per model holding
+// one result per prompt, in prompt order
+pub fn matrix_json(prompts: &[String], runs: &[(String, Vec<BatchResult>)], timestamp: &str) -> serde_json::Value {
+    let models: Vec<serde_json::Value> = runs
+        .iter()
+        .map(|(name, results)| {
+            let cells: Vec<serde_json::Value> = results
+                .iter()
+                .enumerate()
+                .map(|(index, result)| {
+                    let secs = result.elapsed.as_secs_f32();
+                    let (response, usage, error) = match &result.reply {
+                        Ok((response, usage)) => (Some(response.as_str()), usage.as_ref(), None),
+                        Err(error) => (None, None, Some(error.as_str())),
+                    };
+                    serde_json::json!({
+                        "prompt": index + 1,
+                        "elapsed_secs": secs,
+                        "prompt_tokens": usage.map(|u| u.prompt_tokens),
+                        "completion_tokens": usage.map(|u| u.completion_tokens),
+                        "tokens_per_sec": usage.filter(|_| secs > 0.0).map(|u| u.completion_tokens as f32 / secs),
+                        "response": response,
+                        "error": error,
+                    })
+                })
+                .collect();
+            serde_json::json!({ "model": name, "results": cells })
+        })
+        .collect();
+    serde_json::json!({ "timestamp": timestamp, "prompts": prompts, "models": models })
+}
+
+// One model's row of the grid in a line: answered count, time and speed
+fn model_totals(results: &[BatchResult]) -> String {
+    let answered: Vec<&BatchResult> = results.iter().filter(|r| r.reply.is_ok()).collect();
+    let secs: f32 = answered.iter().map(|r| r.elapsed.as_secs_f32()).sum();
+    let tokens: u32 = answered
+        .iter()
+        .filter_map(|r| r.reply.as_ref().ok()?.1.as_ref())
+        .map(|u| u.completion_tokens)
+        .sum();
+    let mut totals = format!("{}/{} answered, {:.2}s", answered.len(), results.len(), secs);
+    if tokens > 0 && secs > 0.0 {
+        totals.push_str(&format!(", {} tok, {:.1} tok/s", tokens, tokens as f32 / secs));
+    }
+    totals
+}
+
+// Ask every prompt in its own conversation, printing progress and saving each
+// reply to tmp_md as <response name>_p<n>.md. The spinner while a reply is
+// pending counts the whole run from `run_started`.
+async fn run_prompts(
+    config: &RuboxConfig,
+    model_name: &str,
+    prompts: &[String],
+    timestamp: &str,
+    run_started: Instant,
+) -> anyhow::Result<Vec<BatchResult>> {
+    let name = config.display_name(model_name);
+    let mut results = Vec::new();
+    for (index, prompt) in prompts.iter().enumerate() {
+        let number = index + 1;
+        let messages = vec![ChatMessage { role: "user".to_string(), content: prompt.clone() }];
+        let label = format!("[{}/{}] Waiting for {}", number, prompts.len(), name);
+        let (reply, elapsed) = with_spinner(config, &label, Instant::now(), run_started, ask_model(config, model_name, messages)).await;
+
+        match &reply {
+            Ok((response, usage)) => {
+                let response_name = render_filename(
+                    &config.filenames.response,
+                    &[("model", model_name), ("timestamp", timestamp)],
+                );
+                let response_file = format!("{}/{}_p{}.md", config.directories.tmp_md, response_name, number);
+                fs::write(&response_file, response)?;
+                println!(
+                    "  ✓ [{}] {} ({})",
+                    number,
+                    preview(prompt),
+                    stats(usage.as_ref(), elapsed)
+                );
+            }
+            Err(e) => eprintln!(
+                "  {}✗ [{}] {}: {}{}",
+                config.ui.color_red, number, preview(prompt), e, config.ui.color_reset
+            ),
+        }
+        // One line, so it fits the italic error section of the report
+        let reply = reply.map_err(|e| format!("{:#}", e).replace('\n', " "));
+        results.push(BatchResult { prompt: prompt.clone(), reply, elapsed });
+    }
+    Ok(results)
+}
+
+// The combined report: each prompt with its reply (or error) and timing, then
+// the aggregate figures
+pub fn batch_report(model: &str, results: &[BatchResult], total: Duration) -> String {
+    let mut report = format!("# Batch: {}\n", model);
+    for (index, result) in results.iter().enumerate() {
+        report.push_str(&format!("\n---\n\n## Prompt {}\n\n", index + 1));
+        for line in result.prompt.lines() {
+            report.push_str(&if line.is_empty() { ">\n".to_string() } else { format!("> {}\n", line) });
+        }
+        match &result.reply {
+            Ok((response, usage)) => {
+                report.push_str(&format!("\n{}\n\n_{}_\n", response, stats(usage.as_ref(), result.elapsed)))
+            }
+            Err(error) => report.push_str(&format!("\n_Error: {}_\n", error)),
+        }
+    }
+    report.push_str("\n---\n\n## Summary\n\n");
+    for line in summary_lines(results, total) {
+        report.push_str(&format!("- {}\n", line));
+    }
+    report
+}
+
+fn summary_lines(results: &[BatchResult], total: Duration) -> Vec<String> {
+    let answered: Vec<&BatchResult> = results.iter().filter(|r| r.reply.is_ok()).collect();
+    let failed = results.len() - answered.len();
+    let tokens: u32 = answered
+        .iter()
+        .filter_map(|r| r.reply.as_ref().ok()?.1.as_ref())
+        .map(|u| u.completion_tokens)
+        .sum();
+    let generating: f32 = answered.iter().map(|r| r.elapsed.as_secs_f32()).sum();
+
+    let mut lines = vec![format!("{} prompt(s): {} answered, {} failed", results.len(), answered.len(), failed)];
+    lines.push(format!("Total time: {:.2}s", total.as_secs_f32()));
+    if !answered.is_empty() {
+        lines.push(format!("Average per answered prompt: {:.2}s", generating / answered.len() as f32));
+    }
+    if tokens > 0 && generating > 0.0 {
+        lines.push(format!("Completion tokens: {} ({:.1} tok/s)", tokens, tokens as f32 / generating));
+    }
+    lines
+}
+
+fn stats(usage: Option<&Usage>, elapsed: Duration) -> String {
+    let secs = elapsed.as_secs_f32();
+    match usage {
+        Some(usage) if secs > 0.0 => format!(
+            "{:.2}s, {} tok, {:.1} tok/s",
+            secs,
+            usage.completion_tokens,
+            usage.completion_tokens as f32 / secs
+        ),
+        _ => format!("{:.2}s", secs),
+    }
+}
+
+// First line of a prompt, shortened for the progress output
+fn preview(prompt: &str) -> String {
+    let first = prompt.lines().next().unwrap_or_default();
+    if first.chars().count() > 60 {
+        format!("{}…", first.chars().take(59).collect::<String>())
+    } else {
+        first.to_string()
+    }
+}
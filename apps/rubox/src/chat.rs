@@ -6,18 +6,32 @@ use ratatui::{backend::CrosstermBackend, Terminal};
 use std::io;
 use std::time::Instant;
 
-use crate::config::RuboxConfig;
-use crate::llm_client::{LlmClient, ChatMessage as ApiChatMessage};
+use crate::command_log::CommandLog;
+use crate::config::{RuboxConfig, CHAT_PERCENT_RANGE, INPUT_MIN_ROWS_RANGE};
+use crate::llm_client::{LlmClient, OutputFormat, ChatMessage as ApiChatMessage};
 use crate::server_manager::ServerManager;
+use crate::state::AppState;
+use crate::prompts::PromptManager;
 use crate::commands::{ChatState, CommandResult};
-use crate::tui::{App, EventHandler, AppEvent, UIMode, ModalType};
+use crate::session::{RATING_BAD, RATING_GOOD};
+use crate::tui::{render_transcript, App, EventHandler, AppEvent, UIMode, ModalType, Theme};
+
+// How the session opens: a prompt from --prompt, --prompt-file or
+// prompt_input.txt to send right away, and a note for the status bar (e.g. why --model auto picked the model)
+#[derive(Debug, Default)]
+pub struct ChatStart {
+    pub prompt: Option<String>,
+    pub notice: Option<String>,
+}
 
 pub async fn run_chat_mode(
     client: &LlmClient,
     model_name: &str,
     config: &RuboxConfig,
-    _verbose: bool,
+    verbose: bool,
     server: &mut ServerManager,
+    start: ChatStart,
+    saved_state: AppState,
 ) -> anyhow::Result<()> {
     // Setup terminal
     enable_raw_mode()?;
@@ -27,51 +41,126 @@ pub async fn run_chat_mode(
     let mut terminal = Terminal::new(backend)?;
 
     // Create app state with model registry
+    let mut model_registry = config.models.registry.clone();
+    crate::config::merge_scanned_models(&mut model_registry);
+    // Pick up the last temperature if it's still within the configured range
+    let temperature = saved_state
+        .last_temperature
+        .filter(|t| (config.temperature.min..=config.temperature.max).contains(t))
+        .unwrap_or(config.temperature.default);
     let mut app = App::new(
         model_name.to_string(),
-        config.temperature.default,
-        config.models.registry.clone(),
+        temperature,
+        model_registry,
+        client.clone(),
     );
+    app.can_manage_server = config.llm.manage_server;
+    // Working copy: /connect can change the endpoint and whether rubox manages it
+    let mut config = config.clone();
+    let config = &mut config;
+    app.last_model = saved_state.last_model;
+    app.session.metadata.context_window = config.param_overrides.context_window;
+    app.request_timeout_secs = config.llm.request_timeout_secs;
+    app.verbose = verbose;
+    app.tick_ms = config.ui.tick_ms.max(10);
+
+    // An externally-managed server may serve models the local registry doesn't know
+    if !config.llm.manage_server {
+        app.server_models = app.client.list_server_models().await;
+    }
+
+    // Parse the theme once (again after /reload); malformed colors fall back to the default palette
+    let (mut theme, invalid_colors) = Theme::from_config(&config.theme);
+    if !invalid_colors.is_empty() {
+        app.set_error(format!("Invalid theme color(s): {} (using defaults)", invalid_colors.join(", ")));
+    }
+    apply_config(&mut app, config);
+    if let Some(notice) = start.notice {
+        app.set_status(notice);
+    }
 
     // Create event handler
-    let event_handler = EventHandler::new();
-    let event_tx = event_handler.sender();
+    let event_handler = EventHandler::new(app.tick_ms);
+
+    // In raw mode Ctrl-C arrives as a key, but an external SIGINT would kill the
+    // process mid-session; route it into the event loop for a clean shutdown
+    let signal_tx = event_handler.sender();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            let _ = signal_tx.send(AppEvent::Shutdown);
+        }
+    });
 
     // Create channel for LLM responses using tokio for async compatibility
     let (llm_tx, mut llm_rx) = tokio::sync::mpsc::unbounded_channel();
 
+    // A prompt from the command line or prompt_input.txt is sent as the first message
+    if let Some(prompt) = start.prompt {
+        app.session.add_message("user".to_string(), prompt, None);
+        let temperature = app.temperature;
+        spawn_generation(&mut app, config, &llm_tx, temperature);
+    }
+
+    // Responses received since the last auto-save
+    let mut unsaved_responses = 0;
+
     // Main event loop
     while !app.should_exit {
-        // Check for LLM response
-        if let Ok(event) = llm_rx.try_recv() {
+        // Drain pending LLM events (streamed deltas arrive in bursts)
+        while let Ok(event) = llm_rx.try_recv() {
+            if let AppEvent::ServerHealth(alive) = event {
+                on_server_health(&mut app, config, alive);
+                continue;
+            }
+            if let AppEvent::Suggestions(id, reply) = event {
+                app.set_suggestions(id, &reply);
+                continue;
+            }
+            if let AppEvent::ServerModel(model) = event {
+                crate::commands::fill_server_model(&mut app.output, &model);
+                continue;
+            }
+            // Events still queued from an aborted or superseded generation are stale
+            if app.generation_task.is_none() || event.generation() != Some(app.generation_id) {
+                continue;
+            }
             match event {
-                AppEvent::LlmResponse(text, usage, elapsed) => {
-                    app.add_assistant_message(text.clone(), usage.clone());
-                    app.last_response_time = elapsed;
-                    app.last_tps = if let Some(ref u) = usage {
-                        if elapsed > 0.0 {
-                            u.completion_tokens as f32 / elapsed
-                        } else {
-                            0.0
-                        }
+                AppEvent::LlmDelta(_, delta) => {
+                    app.on_stream_delta(&delta);
+                }
+                AppEvent::LlmResponse(_, text, usage, elapsed) => {
+                    record_timing(&mut app, usage.as_ref(), elapsed);
+                    accept_response(&mut app, config, &llm_tx, &mut unsaved_responses, text, usage);
+                }
+                AppEvent::LlmCandidates(_, mut candidates, usage, elapsed) => {
+                    record_timing(&mut app, usage.as_ref(), elapsed);
+                    if candidates.len() == 1 {
+                        // Server ignored `n`; nothing to choose between
+                        accept_response(&mut app, config, &llm_tx, &mut unsaved_responses, candidates.remove(0), usage);
                     } else {
-                        0.0
-                    };
-
-                    // Auto-save if enabled
-                    if config.session.auto_save {
-                        let _ = app.session.save(&config.directories.sessions);
+                        // Usage covers every candidate, so it isn't attributed to the accepted one
+                        app.show_candidates(candidates);
                     }
                 }
-                AppEvent::LlmError(err) => {
+                AppEvent::LlmError(_, err) => {
+                    app.clear_generation();
                     app.set_error(err);
                 }
+                AppEvent::BenchmarkProgress(_, done) => {
+                    if let Some((_, runs)) = app.benchmark_progress {
+                        app.benchmark_progress = Some((done, runs));
+                    }
+                }
+                AppEvent::BenchmarkDone(_, output) => {
+                    app.clear_generation();
+                    app.show_output(output);
+                }
                 _ => {}
             }
         }
 
         // Render
-        terminal.draw(|f| crate::tui::draw(f, &app))?;
+        terminal.draw(|f| crate::tui::draw(f, &app, &theme))?;
 
         // Handle events
         if let Some(event) = event_handler.next() {
@@ -80,11 +169,17 @@ pub async fn run_chat_mode(
                     use crossterm::event::{KeyCode, KeyModifiers};
 
                     match key.code {
+                        // The first key only skips the typewriter reveal; Ctrl combos still act
+                        _ if app.is_revealing() && !key.modifiers.contains(KeyModifiers::CONTROL) => app.finish_reveal(),
                         KeyCode::Char('/') if matches!(app.mode, UIMode::Chat) && app.input_buffer.is_empty() => {
                             // Open command palette when / is typed at start of input
                             app.open_command_palette();
                         }
                         KeyCode::Esc => match app.mode {
+                            // A running /benchmark stops instead of the chat ending
+                            UIMode::Chat if app.benchmark_progress.is_some() => cancel_generation(&mut app).await,
+                            UIMode::Chat if app.editing_message_id.is_some() => app.cancel_edit(),
+                            UIMode::Chat if app.focused_message_id.is_some() => app.focused_message_id = None,
                             UIMode::Chat => {
                                 app.should_exit = true;
                             }
@@ -94,31 +189,73 @@ pub async fn run_chat_mode(
                                 app.command_search.clear();
                                 app.selected_command_idx = 0;
                             }
+                            UIMode::Modal(ModalType::PickCandidate) => app.discard_candidates(),
                             UIMode::Modal(_) => {
                                 // Cancel modal and go back to chat
                                 app.mode = UIMode::Chat;
                                 app.modal_input.clear();
+                                app.pending_confirm = None;
                             }
                         },
+                        KeyCode::Enter if app.multiline && app.mode == UIMode::Chat => {
+                            app.input_buffer.push('\n');
+                        }
                         KeyCode::Enter => {
                             // Check if we're in a modal and handle modal submission
                             if let UIMode::Modal(ref modal_type) = app.mode.clone() {
                                 match modal_type {
                                     ModalType::ModelSelector => {
-                                        // Use arrow-key selected model
-                                        let mut models: Vec<_> = app.model_registry.keys().collect();
-                                        models.sort();
+                                        // Use arrow-key selected model, among those matching the filter
+                                        let models = app.filtered_model_names();
                                         if app.selected_model_idx < models.len() {
                                             let new_model = models[app.selected_model_idx].clone();
                                             app.mode = UIMode::Chat; // Close modal
-                                            app.is_loading = true;  // Show loading indicator
                                             app.error_message = None;  // Clear any prior errors
-
-                                            server.stop()?;
-                                            server.ensure_running(config, Some(&new_model)).await?;
-                                            app.current_model = new_model;
-
-                                            app.is_loading = false;  // Clear loading indicator
+                                            switch_model(&mut app, server, config, new_model, None).await;
+                                        }
+                                    }
+                                    ModalType::ErrorDetails | ModalType::DebugRequest | ModalType::Output => app.close_modal(),
+                                    ModalType::PickCandidate => {
+                                        let idx = app.selected_candidate_idx;
+                                        if let Some(text) = app.take_candidate(idx) {
+                                            accept_response(&mut app, config, &llm_tx, &mut unsaved_responses, text, None);
+                                        }
+                                    }
+                                    ModalType::Settings => {
+                                        let row = app.settings.get(app.selected_setting_idx).cloned();
+                                        let value = app.modal_input.trim().to_string();
+                                        app.modal_input.clear();
+                                        match row {
+                                            Some(row) if row.name == "model" => app.open_modal(ModalType::ModelSelector),
+                                            Some(row) if row.editable && !value.is_empty() => {
+                                                // Same path as typing the command, then show the new values
+                                                for command in [format!("/set {} {}", row.name, value), "/settings".to_string()] {
+                                                    handle_command(command, &mut app, server, config, llm_tx.clone()).await?;
+                                                }
+                                            }
+                                            _ => {}
+                                        }
+                                    }
+                                    ModalType::LoadPrompt => {
+                                        // A typed id/'list' wins; otherwise load the highlighted prompt
+                                        let typed = app.modal_input.trim().to_string();
+                                        let arg = if !typed.is_empty() {
+                                            Some(typed)
+                                        } else if app.selected_prompt_idx < app.prompt_list.len() {
+                                            Some((app.selected_prompt_idx + 1).to_string())
+                                        } else {
+                                            None
+                                        };
+                                        if let Some(arg) = arg {
+                                            app.close_modal();
+                                            handle_command(
+                                                format!("/prompt {}", arg),
+                                                &mut app,
+                                                server,
+                                                config,
+                                                llm_tx.clone(),
+                                            )
+                                            .await?;
                                         }
                                     }
                                     _ => {
@@ -127,10 +264,13 @@ pub async fn run_chat_mode(
                                             match modal_type {
                                                 ModalType::SetTemperature => {
                                                     if let Ok(temp) = input.parse::<f32>() {
-                                                        if temp >= 0.0 && temp <= 2.0 {
-                                                            app.temperature = temp;
+                                                        if (config.temperature.min..=config.temperature.max).contains(&temp) {
+                                                            app.set_temperature(temp);
                                                         } else {
-                                                            app.set_error(format!("❌ Temperature must be 0.0-2.0, got {}", temp));
+                                                            app.set_error(format!(
+                                                                "❌ Temperature must be {:.1}-{:.1}, got {}",
+                                                                config.temperature.min, config.temperature.max, temp
+                                                            ));
                                                             app.open_modal(ModalType::SetTemperature);
                                                         }
                                                     } else {
@@ -138,14 +278,19 @@ pub async fn run_chat_mode(
                                                         app.open_modal(ModalType::SetTemperature);
                                                     }
                                                 }
+                                                ModalType::DeleteMessage if input == "all" && config.session.confirm_destructive => {
+                                                    app.request_confirm(
+                                                        format!("Delete all {} messages?", app.session.messages.len()),
+                                                        "/delete all".to_string(),
+                                                    );
+                                                }
                                                 ModalType::DeleteMessage => {
                                                     handle_command(
                                                         format!("/delete {}", input),
                                                         &mut app,
                                                         server,
                                                         config,
-                                                        client,
-                                                        event_tx.clone(),
+                                                        llm_tx.clone(),
                                                     )
                                                     .await?;
                                                 }
@@ -155,8 +300,7 @@ pub async fn run_chat_mode(
                                                         &mut app,
                                                         server,
                                                         config,
-                                                        client,
-                                                        event_tx.clone(),
+                                                        llm_tx.clone(),
                                                     )
                                                     .await?;
                                                 }
@@ -166,19 +310,7 @@ pub async fn run_chat_mode(
                                                         &mut app,
                                                         server,
                                                         config,
-                                                        client,
-                                                        event_tx.clone(),
-                                                    )
-                                                    .await?;
-                                                }
-                                                ModalType::LoadPrompt => {
-                                                    handle_command(
-                                                        format!("/prompt {}", input),
-                                                        &mut app,
-                                                        server,
-                                                        config,
-                                                        client,
-                                                        event_tx.clone(),
+                                                        llm_tx.clone(),
                                                     )
                                                     .await?;
                                                 }
@@ -188,7 +320,25 @@ pub async fn run_chat_mode(
                                     }
                                 }
                             } else if let Some(input) = app.submit_input() {
-                                if input.starts_with('/') {
+                                if let Some(id) = app.editing_message_id.take() {
+                                    // Edits replace the text in place; nothing is sent. /undo
+                                    // restores the text from before the edit
+                                    let before = app.session.clone();
+                                    let saved = app.session.edit_message(id, input).and_then(|_| {
+                                        app.undo_snapshot = Some(before);
+                                        app.session.save(&config.directories.sessions)
+                                    });
+                                    if let Err(e) = saved {
+                                        app.set_error(format!("Failed to edit message {}: {}", id, e));
+                                    }
+                                } else if input.starts_with('/') {
+                                    if config.session.command_log {
+                                        let log = CommandLog::new(&config.directories.chat, config.session.command_log_max_kb);
+                                        if let Err(e) = log.append(&app.session.metadata.id, &input) {
+                                            app.set_error(format!("Command log: {}", e));
+                                        }
+                                    }
+
                                     // Check if command needs a modal form
                                     let cmd = input.trim_start_matches('/').split_whitespace().next().unwrap_or("");
 
@@ -199,17 +349,46 @@ pub async fn run_chat_mode(
                                         "temp" | "temperature" => {
                                             app.open_modal(ModalType::SetTemperature);
                                         }
-                                        "delete" | "del" | "rm" => {
+                                        // Wiping everything asks first, whatever is focused
+                                        "delete" | "del" | "rm"
+                                            if input.split_whitespace().nth(1) == Some("all") && config.session.confirm_destructive =>
+                                        {
+                                            app.request_confirm(
+                                                format!("Delete all {} messages?", app.session.messages.len()),
+                                                "/delete all".to_string(),
+                                            );
+                                        }
+                                        // A focused message or given ids are the target, so none needs asking for
+                                        "delete" | "del" | "rm" if !input.contains(' ') && app.focused_message_id.is_none() => {
                                             app.open_modal(ModalType::DeleteMessage);
                                         }
-                                        "save" | "export" => {
+                                        "save" | "export" if !input.contains(' ') && app.focused_message_id.is_none() => {
                                             app.open_modal(ModalType::SaveResponse);
                                         }
                                         "rename" => {
                                             app.open_modal(ModalType::RenameSession);
                                         }
-                                        "prompt" | "p" => {
+                                        "prompt" | "p" if !input.contains(' ') => {
+                                            // Read contents up front so the preview doesn't hit the disk per frame
+                                            let pm = PromptManager::new(config.directories.static_prompts.clone());
+                                            let mut unreadable = Vec::new();
+                                            app.prompt_list = pm
+                                                .list_prompts()
+                                                .unwrap_or_default()
+                                                .into_iter()
+                                                .filter_map(|name| match pm.load_prompt(&name) {
+                                                    Ok(content) => Some((name, content)),
+                                                    Err(e) => {
+                                                        unreadable.push(e.to_string());
+                                                        None
+                                                    }
+                                                })
+                                                .collect();
                                             app.open_modal(ModalType::LoadPrompt);
+                                            // Binary or unreadable files are left out of the list
+                                            if !unreadable.is_empty() {
+                                                app.set_error(unreadable.join("; "));
+                                            }
                                         }
                                         _ => {
                                             // Execute command directly for simple commands
@@ -218,107 +397,751 @@ pub async fn run_chat_mode(
                                                 &mut app,
                                                 server,
                                                 config,
-                                                client,
-                                                event_tx.clone(),
+                                                llm_tx.clone(),
                                             )
                                             .await?;
                                         }
                                     }
                                 } else if !input.is_empty() {
                                     app.session.add_message("user".to_string(), input, None);
-                                    app.is_loading = true;
-
-                                    // Spawn async LLM call
-                                    let llm_tx = llm_tx.clone();
-                                    let client = client.clone();
-                                    let messages = app.session.messages.clone();
-                                    let temp = app.temperature;
-
-                                    tokio::spawn(async move {
-                                        let api_messages: Vec<ApiChatMessage> = messages
-                                            .iter()
-                                            .map(|m| ApiChatMessage {
-                                                role: m.role.clone(),
-                                                content: m.content.clone(),
-                                            })
-                                            .collect();
-
-                                        let start = Instant::now();
-                                        match client.chat_completion_with_usage(api_messages, temp).await {
-                                            Ok((response, usage)) => {
-                                                let elapsed = start.elapsed().as_secs_f32();
-                                                let _ = llm_tx.send(AppEvent::LlmResponse(response, usage, elapsed));
-                                            }
-                                            Err(e) => {
-                                                let _ = llm_tx.send(AppEvent::LlmError(e.to_string()));
-                                            }
-                                        }
-                                    });
+                                    let temperature = app.temperature;
+                                    spawn_generation(&mut app, config, &llm_tx, temperature);
                                 }
                             }
                         }
+                        KeyCode::Char('?') if app.mode == UIMode::Chat && app.input_buffer.is_empty() && app.error_message.is_some() => {
+                            // Full text of an error the status bar had to truncate; only while
+                            // it is shown, so messages can still start with "?"
+                            app.open_modal(ModalType::ErrorDetails);
+                        }
+                        // Alt+digit, so a message can still start with a digit
+                        KeyCode::Char(c)
+                            if app.mode == UIMode::Chat
+                                && key.modifiers.contains(KeyModifiers::ALT)
+                                && app.input_buffer.is_empty()
+                                && app.suggestion(c).is_some() =>
+                        {
+                            // Sent as if typed
+                            if let Some(prompt) = app.suggestion(c) {
+                                app.session.add_message("user".to_string(), prompt, None);
+                                let temperature = app.temperature;
+                                spawn_generation(&mut app, config, &llm_tx, temperature);
+                            }
+                        }
+                        // Only with a focused message, so a message can still start with + or -
+                        KeyCode::Char(c @ ('+' | '-'))
+                            if app.mode == UIMode::Chat
+                                && app.input_buffer.is_empty()
+                                && !app.is_loading
+                                && app.focused_message_id.is_some() =>
+                        {
+                            app.rate_focused(if c == '+' { RATING_GOOD } else { RATING_BAD });
+                        }
+                        KeyCode::Tab | KeyCode::BackTab if app.mode == UIMode::Chat && app.editing_message_id.is_none() => {
+                            app.move_focus(key.code == KeyCode::Tab);
+                        }
+                        KeyCode::Char('y' | 'Y') if app.mode == UIMode::Modal(ModalType::Confirm) => {
+                            app.close_modal();
+                            if let Some((_, command)) = app.pending_confirm.take() {
+                                handle_command(command, &mut app, server, config, llm_tx.clone()).await?;
+                            }
+                        }
+                        KeyCode::Char('n' | 'N') if app.mode == UIMode::Modal(ModalType::Confirm) => {
+                            app.close_modal();
+                            app.pending_confirm = None;
+                        }
+                        KeyCode::Char(c @ '1'..='9') if app.mode == UIMode::Modal(ModalType::PickCandidate) => {
+                            let idx = c as usize - '1' as usize;
+                            if let Some(text) = app.take_candidate(idx) {
+                                accept_response(&mut app, config, &llm_tx, &mut unsaved_responses, text, None);
+                            }
+                        }
+                        // Plain keys type the selector's filter
+                        KeyCode::Char('r')
+                            if key.modifiers.contains(KeyModifiers::CONTROL)
+                                && matches!(app.mode, UIMode::Modal(ModalType::ModelSelector)) =>
+                        {
+                            app.refresh_models();
+                        }
                         KeyCode::Char(c) => {
                             if key.modifiers.contains(KeyModifiers::CONTROL) {
-                                match c {
-                                    'c' => {
-                                        app.should_exit = true;
+                                if c == 'c' {
+                                    app.should_exit = true;
+                                } else if c == 't' && app.mode == UIMode::Chat {
+                                    app.toggle_latest_thinking();
+                                } else if c == 'l' {
+                                    app.toggle_layout();
+                                } else if c == 'r' && app.mode == UIMode::Chat && app.server_alive == Some(false) {
+                                    // Offered by the status bar once the health check fails
+                                    cancel_generation(&mut app).await;
+                                    let target = model_target(&app, config, &app.current_model);
+                                    match restart_server(server, config, &app.client, &target).await {
+                                        Ok(()) => {
+                                            app.server_alive = Some(true);
+                                            app.error_message = None;
+                                        }
+                                        Err(e) => app.set_error(format!("Failed to restart server: {}", e)),
+                                    }
+                                } else if c == 'e' && app.mode == UIMode::Chat {
+                                    if app.multiline {
+                                        app.multiline = false;
+                                    } else {
+                                        match edit_in_editor(&mut terminal, &event_handler, &app.input_buffer) {
+                                            Ok(Some(text)) => app.input_buffer = text,
+                                            // No $EDITOR: compose in the input box instead
+                                            Ok(None) => app.multiline = true,
+                                            Err(e) => app.set_error(format!("Editor failed: {}", e)),
+                                        }
                                     }
-                                    _ => {}
                                 }
                             } else {
                                 app.handle_input_char(c);
                             }
                         }
                         KeyCode::Backspace => app.handle_backspace(),
+                        KeyCode::Left | KeyCode::Right if app.mode == UIMode::Chat && app.input_buffer.is_empty() => {
+                            app.cycle_latest_variant(key.code == KeyCode::Right);
+                        }
                         KeyCode::Up => app.scroll_up(),
                         KeyCode::Down => app.scroll_down(),
+                        KeyCode::End if app.mode == UIMode::Chat => app.scroll_to_bottom(),
                         _ => {}
                     }
                 }
-                AppEvent::Tick => app.tick(),
+                AppEvent::Tick => {
+                    app.tick();
+                    let due = app
+                        .last_health_check
+                        .is_none_or(|checked| checked.elapsed() >= HEALTH_CHECK_INTERVAL);
+                    // A server that dies mid-generation already fails the request
+                    if due && !app.health_check_pending && !app.is_loading {
+                        spawn_health_check(&mut app, &llm_tx);
+                    }
+                }
+                AppEvent::Shutdown => app.should_exit = true,
                 AppEvent::Render => {
                     // Render happens in the main loop
                 }
                 _ => {}
             }
         }
+
+        if std::mem::take(&mut app.theme_reload_requested) {
+            theme = Theme::from_config(&config.theme).0;
+        }
+        if std::mem::take(&mut app.transcript_requested) {
+            // Printed on the normal screen so it stays in the terminal's scrollback
+            event_handler.pause_input();
+            disable_raw_mode()?;
+            execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+            print!("{}", render_transcript(&app, &theme, config, crate::ui::color_enabled()));
+            enable_raw_mode()?;
+            execute!(terminal.backend_mut(), EnterAlternateScreen)?;
+            terminal.clear()?;
+            event_handler.resume_input();
+        }
     }
 
+    // Always persist on exit so batched auto-saves aren't lost
+    if !app.session.messages.is_empty() {
+        let _ = app.session.save(&config.directories.sessions);
+    }
+    let _ = AppState {
+        last_model: Some(app.current_model.clone()),
+        last_temperature: Some(app.temperature),
+    }
+    .save();
+
     // Cleanup
     disable_raw_mode()?;
     execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
     terminal.show_cursor()?;
+    if config.ui.transcript_on_exit && !app.session.messages.is_empty() {
+        print!("{}", render_transcript(&app, &theme, config, crate::ui::color_enabled()));
+    }
 
     Ok(())
 }
 
+// Copy the display settings the chat reads from the config into the app. Run
+// at startup and after /reload, which has already rejected invalid values.
+fn apply_config(app: &mut App, config: &RuboxConfig) {
+    app.typewriter_cps = config.ui.typewriter_cps;
+    app.assistant_name = config.ui.assistant_name.clone();
+    app.response_cleanup = config.response.clone();
+    app.default_temperature = config.temperature.default;
+    app.saved_message = format!("{}/{}", config.directories.saved_responses, config.filenames.saved_message);
+    app.temperature_range = (config.temperature.min, config.temperature.max);
+    app.display_names = config
+        .model_profiles
+        .iter()
+        .filter_map(|(key, params)| Some((key.clone(), params.display_name.clone()?)))
+        .collect();
+    app.context_windows = config
+        .model_profiles
+        .iter()
+        .map(|(key, params)| (key.clone(), params.context_window))
+        .collect();
+
+    match crate::tui::spinner_frames(&config.ui.spinner) {
+        Some(frames) => app.spinner = frames,
+        None => app.set_error(format!("Unknown spinner style '{}' (using braille)", config.ui.spinner)),
+    }
+    match crate::tui::layout_mode(&config.ui.layout) {
+        Some(layout) => app.layout = layout,
+        None => app.set_error(format!("Unknown layout '{}' (using auto)", config.ui.layout)),
+    }
+    if CHAT_PERCENT_RANGE.contains(&config.ui.chat_percent) {
+        app.chat_percent = config.ui.chat_percent;
+    } else {
+        app.set_error(format!(
+            "ui.chat_percent {} is outside {}-{} (using {})",
+            config.ui.chat_percent, CHAT_PERCENT_RANGE.start(), CHAT_PERCENT_RANGE.end(), app.chat_percent
+        ));
+    }
+    if INPUT_MIN_ROWS_RANGE.contains(&config.ui.input_min_rows) {
+        app.input_min_rows = config.ui.input_min_rows;
+    } else {
+        app.set_error(format!(
+            "ui.input_min_rows {} is outside {}-{} (using {})",
+            config.ui.input_min_rows, INPUT_MIN_ROWS_RANGE.start(), INPUT_MIN_ROWS_RANGE.end(), app.input_min_rows
+        ));
+    }
+}
+
 async fn handle_command(
     input: String,
     app: &mut App,
     server: &mut ServerManager,
-    config: &RuboxConfig,
-    client: &LlmClient,
-    _event_tx: std::sync::mpsc::Sender<AppEvent>,
+    config: &mut RuboxConfig,
+    llm_tx: LlmSender,
 ) -> anyhow::Result<()> {
+    // Identifies the conversation; unlike the id it survives /rename
+    let session_created = app.session.metadata.created_at;
     let mut state = ChatState {
         session: &mut app.session,
         current_model: &mut app.current_model,
-        verbose: &mut false,
+        verbose: &mut app.verbose,
         temperature: &mut app.temperature,
+        undo_snapshot: &mut app.undo_snapshot,
+        expanded_thinking: &mut app.expanded_thinking,
+        output_format: &mut app.output_format,
+        candidate_count: &mut app.candidate_count,
+        logit_bias: &mut app.logit_bias,
+        think: &mut app.think,
+        top_p: &mut app.top_p,
+        max_tokens: &mut app.max_tokens,
+        model_registry: &mut app.model_registry,
         server,
-        client,
+        client: &app.client,
         config,
+        is_loading: app.is_loading,
+        focused: app.focused_message_id,
     };
 
-    match app.command_registry.handle(&input, &mut state)? {
-        CommandResult::Exit => app.should_exit = true,
-        CommandResult::SwitchModel(new_model) => {
-            server.stop()?;
-            server.ensure_running(config, Some(&new_model)).await?;
-            app.current_model = new_model.clone();
+    // Command failures are shown in the status bar; they must never end the chat loop
+    match app.command_registry.handle(&input, &mut state) {
+        Ok(CommandResult::Exit) => app.should_exit = true,
+        Ok(CommandResult::SwitchModel(new_model, status)) => {
+            switch_model(app, server, config, new_model, status).await;
+        }
+        Ok(CommandResult::SetContext(context_window, status)) => {
+            let model = app.current_model.clone();
+            if switch_with_context(app, server, config, model, Some(context_window), Some(status)).await {
+                app.session.metadata.context_window = Some(context_window);
+            }
+        }
+        Ok(CommandResult::SwitchSession(model, context_window, status)) => {
+            switch_with_context(app, server, config, model, context_window, Some(status)).await;
+        }
+        Ok(CommandResult::Regenerate(temperature)) => {
+            let temperature = temperature.unwrap_or(app.temperature);
+            spawn_generation(app, config, &llm_tx, temperature);
+        }
+        Ok(CommandResult::Prefill(text)) => {
+            app.prefill = Some(text);
+            let temperature = app.temperature;
+            spawn_generation(app, config, &llm_tx, temperature);
+        }
+        Ok(CommandResult::Connect(url)) => {
+            cancel_generation(app).await;
+            match connect(app, config, &url).await {
+                Ok(()) => app.set_status(format!("Connected to {}", url)),
+                Err(e) => app.set_error(format!("Failed to connect to {}: {}", url, e)),
+            }
+        }
+        Ok(CommandResult::Edit(id)) => app.begin_edit(id),
+        Ok(CommandResult::Transcript) => app.transcript_requested = true,
+        Ok(CommandResult::Reload(new, report)) => {
+            // The endpoint in use may come from /connect; it only changes with a restart
+            let (api_url, manage_server) = (config.llm.api_url.clone(), config.llm.manage_server);
+            *config = *new;
+            config.llm.api_url = api_url;
+            config.llm.manage_server = manage_server;
+
+            apply_config(app, config);
+            app.model_registry = config.models.registry.clone();
+            crate::config::merge_scanned_models(&mut app.model_registry);
+            app.temperature = app.temperature.clamp(config.temperature.min, config.temperature.max);
+            app.theme_reload_requested = true;
+            app.show_output(report);
+        }
+        Ok(CommandResult::Output(output)) => app.show_output(output),
+        Ok(CommandResult::Info(output)) => {
+            app.show_output(output);
+            spawn_server_model(app, &llm_tx);
+        }
+        Ok(CommandResult::Benchmark(runs)) => spawn_benchmark(app, &llm_tx, runs),
+        Ok(CommandResult::DebugRequest) => {
+            let client = generation_client(app, config);
+            match client.request_preview(generation_messages(app), app.temperature, app.candidate_count) {
+                Ok(json) => app.show_debug_request(json),
+                Err(e) => app.set_error(format!("Failed to build the request: {}", e)),
+            }
+        }
+        Ok(CommandResult::Settings(rows)) => {
+            if app.mode != UIMode::Modal(ModalType::Settings) {
+                app.open_modal(ModalType::Settings);
+            }
+            app.settings = rows;
         }
-        CommandResult::Continue => {}
+        Ok(CommandResult::Error(msg)) => app.set_error(msg),
+        Ok(CommandResult::Status(msg)) => app.set_status(msg),
+        Ok(CommandResult::Continue) => {}
+        Err(e) => app.set_error(e.to_string()),
     }
+    app.drop_stale_focus();
+    if app.session.metadata.created_at != session_created {
+        // /load or /new: message ids start over, so per-message UI state is
+        // meaningless, and a reply still on its way belongs to the old chat
+        cancel_generation(app).await;
+        app.clear_suggestions();
+        app.finish_reveal();
+        app.focused_message_id = None;
+        app.editing_message_id = None;
+        app.scroll_to_bottom();
+    }
+
+    Ok(())
+}
+
+// Hand the terminal to $VISUAL/$EDITOR on a temp file seeded with `initial`
+// and return the edited text. None when neither variable is set.
+fn edit_in_editor(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    event_handler: &EventHandler,
+    initial: &str,
+) -> anyhow::Result<Option<String>> {
+    let Some(editor) = std::env::var("VISUAL")
+        .ok()
+        .or_else(|| std::env::var("EDITOR").ok())
+        .filter(|e| !e.trim().is_empty())
+    else {
+        return Ok(None);
+    };
+
+    let path = std::env::temp_dir().join(format!("rubox_input_{}.md", std::process::id()));
+    std::fs::write(&path, initial)?;
+
+    // The input thread would otherwise race the editor for keystrokes
+    event_handler.pause_input();
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+
+    // $EDITOR may carry arguments, e.g. "code --wait"
+    let mut parts = editor.split_whitespace();
+    let status = std::process::Command::new(parts.next().unwrap_or_default())
+        .args(parts)
+        .arg(&path)
+        .status();
+
+    enable_raw_mode()?;
+    execute!(terminal.backend_mut(), EnterAlternateScreen)?;
+    terminal.clear()?;
+    event_handler.resume_input();
+
+    let status = status?;
+    let edited = std::fs::read_to_string(&path);
+    let _ = std::fs::remove_file(&path);
+    if !status.success() {
+        anyhow::bail!("{} exited with {}", editor, status);
+    }
+    Ok(Some(edited?.trim_end().to_string()))
+}
+
+fn record_timing(app: &mut App, usage: Option<&crate::llm_client::Usage>, elapsed: f32) {
+    app.last_response_time = elapsed;
+    app.last_tps = match usage {
+        Some(u) if elapsed > 0.0 => u.completion_tokens as f32 / elapsed,
+        _ => 0.0,
+    };
+}
+
+// Add a finished reply to the session and auto-save if it's due
+fn accept_response(
+    app: &mut App,
+    config: &RuboxConfig,
+    llm_tx: &LlmSender,
+    unsaved_responses: &mut u32,
+    text: String,
+    usage: Option<crate::llm_client::Usage>,
+) {
+    // The server should enforce json_object, but not every model/build honors it
+    let invalid_json = app.output_format == OutputFormat::Json
+        && serde_json::from_str::<serde_json::Value>(text.trim()).is_err();
+    app.add_assistant_message(text, usage);
+    if invalid_json {
+        app.set_error("Warning: JSON mode is on but the response is not valid JSON".to_string());
+    }
+    if let Err(e) = app.session.archive_overflow(&config.directories.sessions, config.session.max_messages) {
+        app.set_error(format!("Failed to archive old messages: {}", e));
+    }
+    if config.ui.suggestions {
+        spawn_suggestions(app, config, llm_tx);
+    }
+
+    // Auto-save if enabled, batched every `auto_save_interval` responses
+    if config.session.auto_save {
+        *unsaved_responses += 1;
+        if *unsaved_responses >= config.session.auto_save_interval.max(1) {
+            let _ = app.session.save(&config.directories.sessions);
+            *unsaved_responses = 0;
+        }
+    }
+}
+
+// Run /benchmark as a generation: the spinner shows the run count, Esc
+// cancels it, and the table opens in the Output modal when it is done
+fn spawn_benchmark(app: &mut App, llm_tx: &LlmSender, runs: usize) {
+    let temperature = app.temperature;
+    app.start_generation(temperature);
+    app.benchmark_progress = Some((0, runs));
+
+    let llm_tx = llm_tx.clone();
+    let client = app.client.clone();
+    let model = app.current_model.clone();
+    let id = app.generation_id;
+    app.generation_task = Some(tokio::spawn(async move {
+        let progress_tx = llm_tx.clone();
+        let on_run = |done| {
+            let _ = progress_tx.send(AppEvent::BenchmarkProgress(id, done));
+        };
+        let event = match crate::commands::run_benchmark(&client, temperature, runs, on_run).await {
+            Ok(results) => AppEvent::BenchmarkDone(id, crate::commands::benchmark_report(&model, &results)),
+            Err(e) => AppEvent::LlmError(id, format!("Benchmark failed: {}", e)),
+        };
+        let _ = llm_tx.send(event);
+    }));
+}
+
+// Abort the in-flight completion and wait for the task to finish, so nothing
+// from it reaches the channel once this returns. Dropping the task drops the
+// half-read response, which closes its connection; llama-server notices the
+// closed stream and cancels the slot's task (see chat_completion_stream).
+// Anything the task queued before the abort carries the old generation id
+// and is discarded by the event loop.
+async fn cancel_generation(app: &mut App) {
+    if let Some(task) = app.generation_task.take() {
+        task.abort();
+        let _ = task.await;
+        tracing::debug!(generation = app.generation_id, "generation cancelled");
+        app.clear_generation();
+    }
+}
+
+// Switch to `new_model` from the selector or /model. An in-flight reply is
+// aborted and dropped first, so the server is never stopped under a request
+// and the reply can't be credited to the new model.
+// `status` is the command's confirmation, shown once the model is running.
+// Returns whether the switch succeeded.
+pub async fn switch_model(
+    app: &mut App,
+    server: &mut ServerManager,
+    config: &RuboxConfig,
+    new_model: String,
+    status: Option<String>,
+) -> bool {
+    cancel_generation(app).await;
+    let switched = if app.is_server_model(&new_model) {
+        // Already loaded remotely; requests just name it
+        Ok(())
+    } else {
+        let target = model_target(app, config, &new_model);
+        restart_server(server, config, &app.client, &target).await
+    };
+    match switched {
+        Ok(()) => {
+            app.set_model(new_model);
+            if let Some(status) = status {
+                app.set_status(status);
+            }
+            true
+        }
+        Err(e) => {
+            app.set_error(format!("Failed to switch model: {}", e));
+            false
+        }
+    }
+}
+
+// switch_model with `context_window` as the server's --ctx-size (None: the
+// model profile's). A size the server can't start with must not stick to
+// later restarts, so a failed switch puts the previous one back.
+pub async fn switch_with_context(
+    app: &mut App,
+    server: &mut ServerManager,
+    config: &RuboxConfig,
+    new_model: String,
+    context_window: Option<u32>,
+    status: Option<String>,
+) -> bool {
+    let previous = std::mem::replace(&mut server.context_override, context_window);
+    let switched = switch_model(app, server, config, new_model, status).await;
+    if !switched {
+        server.context_override = previous;
+    }
+    switched
+}
+
+async fn restart_server(
+    server: &mut ServerManager,
+    config: &RuboxConfig,
+    client: &LlmClient,
+    model: &str,
+) -> anyhow::Result<()> {
+    if !config.llm.manage_server {
+        anyhow::bail!("the server is externally managed (llm.manage_server is false); pick one of its models instead");
+    }
+    tracing::info!(%model, "restarting server for model switch");
+    server.stop()?;
+    server.ensure_running(config, Some(model)).await?;
+
+    // Don't hand control back until the new model answers, so the first real
+    // message after a switch doesn't hit a server that is still loading
+    const PROBE_ATTEMPTS: u32 = 30;
+    for _ in 0..PROBE_ATTEMPTS {
+        if client.probe().await.is_ok() {
+            return Ok(());
+        }
+        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+    }
+    anyhow::bail!("server started but did not answer a test completion after {} attempts", PROBE_ATTEMPTS)
+}
+
+// What restart_server needs to launch `name`. Scanned models aren't in the
+// config registry, so they are launched by path.
+fn model_target(app: &App, config: &RuboxConfig, name: &str) -> String {
+    if config.models.registry.contains_key(name) {
+        name.to_string()
+    } else {
+        app.model_registry.get(name).cloned().unwrap_or_else(|| name.to_string())
+    }
+}
+
+// How often the status bar's server dot is refreshed
+const HEALTH_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+
+fn spawn_health_check(app: &mut App, llm_tx: &LlmSender) {
+    app.health_check_pending = true;
+    app.last_health_check = Some(Instant::now());
+    let client = app.client.clone();
+    let llm_tx = llm_tx.clone();
+    tokio::spawn(async move {
+        let _ = llm_tx.send(AppEvent::ServerHealth(client.health().await));
+    });
+}
 
+// Fetch the loaded model for /info; the modal is already open, so a slow
+// server only delays that one row
+fn spawn_server_model(app: &App, llm_tx: &LlmSender) {
+    let client = app.client.clone();
+    let llm_tx = llm_tx.clone();
+    tokio::spawn(async move {
+        let _ = llm_tx.send(AppEvent::ServerModel(crate::commands::server_model(&client).await));
+    });
+}
+
+// Ask the model, in a separate short request, what the user might ask next.
+// Failures are silent: suggestions are a convenience, not part of the chat.
+fn spawn_suggestions(app: &App, config: &RuboxConfig, llm_tx: &LlmSender) {
+    let Some(id) = app.session.messages.last().map(|m| m.id) else {
+        return;
+    };
+    let mut client = generation_client(app, config);
+    client.output_format = OutputFormat::Text;
+    client.logit_bias.clear();
+    client.max_tokens = SUGGESTION_MAX_TOKENS;
+    let mut messages = generation_messages(app);
+    messages.push(ApiChatMessage {
+        role: "user".to_string(),
+        content: SUGGESTION_PROMPT.to_string(),
+    });
+    let temperature = app.temperature;
+    let llm_tx = llm_tx.clone();
+    tokio::spawn(async move {
+        match client.chat_completion_with_usage(messages, temperature).await {
+            Ok((reply, _)) => {
+                let _ = llm_tx.send(AppEvent::Suggestions(id, reply));
+            }
+            Err(e) => tracing::debug!(error = %e, "suggestion request failed"),
+        }
+    });
+}
+
+// Report a server that stopped answering right away, not on the next message
+fn on_server_health(app: &mut App, config: &RuboxConfig, alive: bool) {
+    let was_alive = app.server_alive;
+    app.health_check_pending = false;
+    app.server_alive = Some(alive);
+    if !alive && was_alive != Some(false) {
+        let message = if config.llm.manage_server {
+            "llama-server is not responding · Ctrl+R to restart".to_string()
+        } else {
+            format!("Server at {} is not responding", app.client.api_url())
+        };
+        app.set_error(message);
+    }
+}
+
+// Point the chat at another server. The new endpoint must answer a test
+// completion first; on failure the current client and settings stay as they were.
+async fn connect(app: &mut App, config: &mut RuboxConfig, url: &str) -> anyhow::Result<()> {
+    let mut candidate = config.clone();
+    candidate.llm.api_url = url.to_string();
+    let client = LlmClient::new(&candidate);
+    client.probe().await?;
+
+    // rubox can only start a server on this machine, and only if allowed to
+    config.llm.manage_server = app.can_manage_server && is_local_endpoint(url);
+    config.llm.api_url = url.to_string();
+    app.server_models = if config.llm.manage_server {
+        Vec::new()
+    } else {
+        client.list_server_models().await
+    };
+    tracing::info!(%url, managed = config.llm.manage_server, "connected to new endpoint");
+    app.client = client;
+    app.server_alive = Some(true);
     Ok(())
 }
+
+fn is_local_endpoint(url: &str) -> bool {
+    reqwest::Url::parse(url)
+        .ok()
+        .and_then(|u| u.host_str().map(|h| matches!(h, "localhost" | "127.0.0.1" | "[::1]")))
+        .unwrap_or(false)
+}
+
+type LlmSender = tokio::sync::mpsc::UnboundedSender<AppEvent>;
+
+// Follow-up request sent after each reply when `ui.suggestions` is on
+const SUGGESTION_PROMPT: &str = "Suggest 3 short follow-up questions I could ask next about this conversation. \
+Reply with the questions only, one per line, without numbering or any other text.";
+const SUGGESTION_MAX_TOKENS: u32 = 150;
+
+// The active client with the session's sampling settings applied
+fn generation_client(app: &App, config: &RuboxConfig) -> LlmClient {
+    let mut client = app.client.clone();
+    client.output_format = app.output_format.clone();
+    client.logit_bias = app.logit_bias.clone();
+    client.think = app.think;
+    client.top_p = app.top_p;
+    client.max_tokens = app.max_tokens;
+    client.stop = config.get_model_params(&app.current_model).stop.unwrap_or_default();
+    // A managed server runs whatever was last loaded; naming it keeps the
+    // response cache keyed by the model that actually answers
+    if config.llm.manage_server || app.is_server_model(&app.current_model) {
+        client.model_name = app.current_model.clone();
+    }
+    client
+}
+
+// The conversation as sent to the server, ending with the /continue prefill if any
+fn generation_messages(app: &App) -> Vec<ApiChatMessage> {
+    let mut api_messages: Vec<ApiChatMessage> = app
+        .session
+        .context_messages()
+        .map(|m| ApiChatMessage {
+            role: m.role.clone(),
+            content: m.content.clone(),
+        })
+        .collect();
+    // llama-server continues a trailing assistant turn instead of starting a new one
+    if let Some(prefill) = &app.prefill {
+        api_messages.push(ApiChatMessage {
+            role: "assistant".to_string(),
+            content: prefill.clone(),
+        });
+    }
+    api_messages
+}
+
+// reqwest's own deadline (non-streaming requests) or the per-read one the
+// stream applies with tokio
+fn is_timeout(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<reqwest::Error>().is_some_and(|e| e.is_timeout())
+        || err.is::<tokio::time::error::Elapsed>()
+}
+
+// Send the current conversation to the model in the background. `temperature`
+// applies to this generation only; it may differ from the session temperature.
+fn spawn_generation(app: &mut App, config: &RuboxConfig, llm_tx: &LlmSender, temperature: f32) {
+    // A message sent while a reply still streams replaces it; dropping the
+    // old request frees its llama-server slot
+    if let Some(task) = app.generation_task.take() {
+        task.abort();
+        tracing::debug!(generation = app.generation_id, "generation superseded");
+    }
+    app.start_generation(temperature);
+
+    let llm_tx = llm_tx.clone();
+    let client = generation_client(app, config);
+    let api_messages = generation_messages(app);
+    let candidate_count = app.candidate_count;
+    let id = app.generation_id;
+
+    app.generation_task = Some(tokio::spawn(async move {
+        let start = Instant::now();
+        if candidate_count > 1 {
+            // Candidates come back together; there is no stream to show
+            match client.chat_completion_choices(api_messages, temperature, candidate_count).await {
+                Ok((candidates, usage)) => {
+                    let elapsed = start.elapsed().as_secs_f32();
+                    let _ = llm_tx.send(AppEvent::LlmCandidates(id, candidates, usage, elapsed));
+                }
+                Err(e) if is_timeout(&e) => {
+                    let _ = llm_tx.send(AppEvent::LlmError(id, format!(
+                        "Generation timed out after {}s",
+                        client.request_timeout_secs
+                    )));
+                }
+                Err(e) => {
+                    let _ = llm_tx.send(AppEvent::LlmError(id, e.to_string()));
+                }
+            }
+            return;
+        }
+
+        let delta_tx = llm_tx.clone();
+        let result = client
+            .chat_completion_stream(api_messages, temperature, |delta| {
+                let _ = delta_tx.send(AppEvent::LlmDelta(id, delta.to_string()));
+            })
+            .await;
+        match result {
+            Ok((response, usage)) => {
+                let elapsed = start.elapsed().as_secs_f32();
+                let _ = llm_tx.send(AppEvent::LlmResponse(id, response, usage, elapsed));
+            }
+            Err(e) if is_timeout(&e) => {
+                let _ = llm_tx.send(AppEvent::LlmError(id, format!(
+                    "Generation timed out: no data from the server for {}s",
+                    client.request_timeout_secs
+                )));
+            }
+            Err(e) => {
+                let _ = llm_tx.send(AppEvent::LlmError(id, e.to_string()));
+            }
+        }
+    }));
+}
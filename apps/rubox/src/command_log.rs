@@ -0,0 +1,71 @@
+use chrono::{DateTime, Utc};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+// Append-only record of every slash-command, one tab-separated line per entry:
+// `<rfc3339 timestamp>\t<session id>\t<command>`. Opt-in via `session.command_log`.
+pub const LOG_FILE: &str = "command_log.tsv";
+
+#[derive(Debug, Clone)]
+pub struct LoggedCommand {
+    pub timestamp: DateTime<Utc>,
+    pub session_id: String,
+    pub command: String,
+}
+
+pub struct CommandLog {
+    path: PathBuf,
+    max_bytes: u64,
+}
+
+impl CommandLog {
+    pub fn new(chat_dir: &str, max_kb: u64) -> Self {
+        CommandLog {
+            path: Path::new(chat_dir).join(LOG_FILE),
+            max_bytes: max_kb * 1024,
+        }
+    }
+
+    // Previous generation after rotation; only one is kept
+    fn rotated_path(&self) -> PathBuf {
+        self.path.with_extension("tsv.1")
+    }
+
+    pub fn append(&self, session_id: &str, command: &str) -> anyhow::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        if fs::metadata(&self.path).map(|m| m.len() >= self.max_bytes).unwrap_or(false) {
+            fs::rename(&self.path, self.rotated_path())?;
+        }
+
+        // Tabs and newlines would break the line format
+        let command = command.replace(['\t', '\n', '\r'], " ");
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        writeln!(file, "{}\t{}\t{}", Utc::now().to_rfc3339(), session_id, command)?;
+        Ok(())
+    }
+
+    // Most recent `limit` entries, oldest first, reaching into the rotated file if needed
+    pub fn recent(&self, limit: usize) -> Vec<LoggedCommand> {
+        let mut entries: Vec<LoggedCommand> = [self.rotated_path(), self.path.clone()]
+            .iter()
+            .filter_map(|path| fs::read_to_string(path).ok())
+            .flat_map(|content| content.lines().filter_map(parse_line).collect::<Vec<_>>())
+            .collect();
+        let skip = entries.len().saturating_sub(limit);
+        entries.drain(..skip);
+        entries
+    }
+}
+
+fn parse_line(line: &str) -> Option<LoggedCommand> {
+    let mut fields = line.splitn(3, '\t');
+    let timestamp = DateTime::parse_from_rfc3339(fields.next()?).ok()?.with_timezone(&Utc);
+    Some(LoggedCommand {
+        timestamp,
+        session_id: fields.next()?.to_string(),
+        command: fields.next()?.to_string(),
+    })
+}
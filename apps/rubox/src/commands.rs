@@ -1,30 +1,163 @@
 use anyhow::Result;
 use colored::*;
-use crate::config::RuboxConfig;
-use crate::session::Session;
-use crate::server_manager::ServerManager;
-use crate::llm_client::LlmClient;
+use crate::command_log::CommandLog;
+use chrono::Local;
+use crate::config::{render_filename, RuboxConfig, FILENAME_TIMESTAMP};
+use crate::session::{MessageProvenance, Session, RATING_BAD, RATING_GOOD};
+use crate::server_manager::{ServerManager, SERVER_PATH};
+use crate::llm_client::{LlmClient, OutputFormat, ThinkLevel};
 use crate::prompts::PromptManager;
+use crate::response_cache::ResponseCache;
+use crate::ui::{ansi, ORANGE, EMERALD, RESET};
+use std::fmt;
 use std::fs;
 
 pub enum CommandResult {
     Continue,
     Exit,
-    SwitchModel(String),  // Signal to switch to a new model
+    SwitchModel(String, Option<String>),  // Signal to switch to a new model; the status shows once it runs
+    SetContext(u32, String),  // Restart with this --ctx-size; kept only if the server comes back up
+    SwitchSession(String, Option<u32>, String),  // Restart on a loaded session's model and --ctx-size (None: the profile's)
+    Error(String),        // User-facing error, shown in the status bar
+    Regenerate(Option<f32>),  // Resend the conversation, optionally at a one-off temperature
+    Prefill(String),          // Generate a reply that continues from this text
+    Connect(String),          // Switch to the server at this (validated) API URL
+    Edit(usize),              // Load this message into the input box for editing
+    DebugRequest,             // Show the request the next generation would send
+    Transcript,               // Print the conversation to the normal screen
+    Reload(Box<RuboxConfig>, CommandOutput), // Replace the running config with this re-read, validated one; show the report
+    Settings(Vec<SettingRow>),  // Show these rows in the Settings modal
+    Output(CommandOutput),      // Show this text in the scrollable Output modal
+    Benchmark(usize),           // Time this many runs of BENCHMARK_PROMPT in the background
+    Status(String),             // Confirmation shown in the status bar
+    Info(CommandOutput),        // Show /info now; the chat loop fills in the server's model when it answers
 }
 
+// What a command has to show. Printed output would be hidden by the TUI, so
+// it goes to a modal instead; the TUI maps each tone to a theme color.
+#[derive(Debug, Clone, Default)]
+pub struct CommandOutput {
+    pub title: String,
+    pub lines: Vec<Vec<(Tone, String)>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Tone {
+    Plain,
+    Dim,        // Metadata and hints
+    Label,      // Row names, roles
+    Added,      // Diff insertions
+    Removed,    // Diff deletions
+    Highlight,  // Search matches
+}
+
+impl CommandOutput {
+    pub fn new(title: impl Into<String>) -> Self {
+        CommandOutput { title: title.into(), lines: Vec::new() }
+    }
+
+    pub fn line(&mut self, tone: Tone, text: impl Into<String>) {
+        self.lines.push(vec![(tone, text.into())]);
+    }
+
+    pub fn spans(&mut self, spans: Vec<(Tone, String)>) {
+        self.lines.push(spans);
+    }
+
+    // Without the tones, one line per row
+    pub fn text(&self) -> String {
+        self.lines
+            .iter()
+            .map(|spans| spans.iter().map(|(_, text)| text.as_str()).collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+// One line of the Settings modal. Editable rows are applied with `/set <name> <value>`.
+#[derive(Debug, Clone)]
+pub struct SettingRow {
+    pub name: &'static str,
+    pub value: String,
+    pub range: String,
+    pub editable: bool,
+}
+
+// Recoverable user-input errors. Handlers return these with `?`; the registry
+// turns them into `CommandResult::Error` so bad input never aborts the chat loop.
+#[derive(Debug)]
+pub enum CommandError {
+    BadArgument(String),
+    NotFound(String),
+    OutOfRange(String),
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommandError::BadArgument(msg) => write!(f, "Invalid argument: {}", msg),
+            CommandError::NotFound(msg) => write!(f, "Not found: {}", msg),
+            CommandError::OutOfRange(msg) => write!(f, "Out of range: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for CommandError {}
+
 pub struct ChatState<'a> {
     pub session: &'a mut Session,
     pub current_model: &'a mut String,
     pub verbose: &'a mut bool,
     pub temperature: &'a mut f32,
-    #[allow(dead_code)]
+    pub undo_snapshot: &'a mut Option<Session>,
+    pub expanded_thinking: &'a mut std::collections::HashSet<usize>,
+    pub output_format: &'a mut OutputFormat,
+    pub candidate_count: &'a mut u32,  // Replies requested per generation (/set n)
+    pub logit_bias: &'a mut std::collections::HashMap<u32, f32>,  // Token id -> bias (/bias)
+    pub think: &'a mut ThinkLevel,  // Reasoning effort (/set think)
+    pub top_p: &'a mut Option<f32>,
+    pub max_tokens: &'a mut u32,
+    pub model_registry: &'a mut std::collections::HashMap<String, String>,
     pub server: &'a mut ServerManager,
-    #[allow(dead_code)]
     pub client: &'a LlmClient,
     pub config: &'a RuboxConfig,
+    pub is_loading: bool,  // A streamed generation is in flight
+    pub focused: Option<usize>,  // Message focused in the chat; used when a command gets no id
 }
 
+// Bounds for /set ctx; above the plausible maximum a warning is printed
+const MIN_CONTEXT_WINDOW: u32 = 512;
+const MAX_PLAUSIBLE_CONTEXT: u32 = 262_144;
+
+// Entries shown by `/history commands` without a count
+const COMMAND_LOG_DEFAULT_ENTRIES: usize = 20;
+
+// Upper bound for /set n; each candidate costs a full generation
+const MAX_CANDIDATES: u32 = 5;
+
+// Upper bound for /set max_tokens; no context window goes beyond it
+const MAX_REPLY_TOKENS: u32 = MAX_PLAUSIBLE_CONTEXT;
+
+// /bias values, as accepted by the OpenAI API; llama-server allows any float
+const MAX_LOGIT_BIAS: f32 = 100.0;
+
+// /search lists at most this many matches, newest last
+const SEARCH_MAX_RESULTS: usize = 30;
+
+// /diff shows at most this many changed lines
+const DIFF_MAX_LINES: usize = 200;
+
+// Fixed prompt so benchmark runs are comparable across models and settings
+const BENCHMARK_PROMPT: &str = "Write a Rust function that returns the n-th Fibonacci number iteratively, then explain its time complexity in two sentences.";
+const BENCHMARK_MAX_RUNS: usize = 20;
+
+// /info gives up on the server's /props after this long
+const INFO_PROPS_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2);
+
+// The /info row the server's answer goes into, and what it shows until then
+const INFO_SERVER_MODEL: &str = "server model";
+const INFO_SERVER_MODEL_PENDING: &str = "checking...";
+
 pub type CommandHandler = fn(&mut ChatState, &[&str]) -> Result<CommandResult>;
 
 pub struct Command {
@@ -66,29 +199,113 @@ impl CommandRegistry {
                     handler: cmd_model,
                     help: "List or switch models",
                 },
+                Command {
+                    name: "models",
+                    aliases: &[],
+                    handler: cmd_models,
+                    help: "Rescan the models directory: /models refresh",
+                },
+                Command {
+                    name: "connect",
+                    aliases: &[],
+                    handler: cmd_connect,
+                    help: "Switch to another server: /connect <url>",
+                },
+                Command {
+                    name: "info",
+                    aliases: &["i"],
+                    handler: cmd_info,
+                    help: "Show active model parameters",
+                },
+                Command {
+                    name: "benchmark",
+                    aliases: &["bench"],
+                    handler: cmd_benchmark,
+                    help: "Measure tok/s and latency: /benchmark [runs]",
+                },
                 Command {
                     name: "history",
                     aliases: &["hist"],
                     handler: cmd_history,
-                    help: "Show conversation history",
+                    help: "Show conversation history, or /history commands [n]",
+                },
+                Command {
+                    name: "search",
+                    aliases: &["find"],
+                    handler: cmd_search,
+                    help: "Search messages, including archived ones: /search [--user|--assistant] <text>",
                 },
                 Command {
                     name: "delete",
                     aliases: &["del", "rm"],
                     handler: cmd_delete,
-                    help: "Delete message(s): /delete <id|all>",
+                    help: "Delete message(s): /delete <id|3-7|3,5,8|all> (no id: focused)",
+                },
+                Command {
+                    name: "copy",
+                    aliases: &["cp"],
+                    handler: cmd_copy,
+                    help: "Copy a message to the clipboard: /copy [id] (no id: focused) | /copy code [id] [n]",
+                },
+                Command {
+                    name: "edit",
+                    aliases: &[],
+                    handler: cmd_edit,
+                    help: "Rewrite a message in the input box: /edit [id] (no id: focused)",
+                },
+                Command {
+                    name: "diff",
+                    aliases: &[],
+                    handler: cmd_diff,
+                    help: "Line diff between two messages: /diff <id1> <id2>",
+                },
+                Command {
+                    name: "undo",
+                    aliases: &[],
+                    handler: cmd_undo,
+                    help: "Undo the last delete, edit or other destructive command",
+                },
+                Command {
+                    name: "retry",
+                    aliases: &["regen"],
+                    handler: cmd_retry,
+                    help: "Regenerate last answer, keeping the old one (←/→ to cycle): /retry [temp <value>]",
+                },
+                Command {
+                    name: "continue",
+                    aliases: &["prefill"],
+                    handler: cmd_continue,
+                    help: "Answer the last message starting with text: /continue <text>",
+                },
+                Command {
+                    name: "think",
+                    aliases: &[],
+                    handler: cmd_think,
+                    help: "Expand/collapse a message's reasoning: /think <id> (Ctrl+T: latest)",
+                },
+                Command {
+                    name: "rate",
+                    aliases: &[],
+                    handler: cmd_rate,
+                    help: "Rate an answer for exports: /rate [id] <good|bad|clear> (+/-: focused message only)",
                 },
                 Command {
                     name: "save",
                     aliases: &["export"],
                     handler: cmd_save,
-                    help: "Save response: /save <id>",
+                    help: "Save response: /save [id] [--meta [json]] | /save all [--user] [--meta [json]] | /export html | /export jsonl [pairs] [--good] [--exclude <ids>]",
                 },
                 Command {
                     name: "set",
                     aliases: &[],
                     handler: cmd_set,
-                    help: "Set parameter: /set temp <value>",
+                    help: "Set parameter: /set <name> <value> (no args: list settings and current values)",
+                },
+                Command {
+                    name: "settings",
+                    aliases: &["config"],
+                    handler: cmd_settings,
+                    help: "Open the settings panel to review and change parameters in place",
                 },
                 Command {
                     name: "temp",
@@ -96,17 +313,53 @@ impl CommandRegistry {
                     handler: cmd_temp,
                     help: "Show current temperature",
                 },
+                Command {
+                    name: "bias",
+                    aliases: &[],
+                    handler: cmd_bias,
+                    help: "Token bias: /bias [<token_id> <value|off> | clear]",
+                },
+                Command {
+                    name: "reload",
+                    aliases: &[],
+                    handler: cmd_reload,
+                    help: "Re-read the config file and apply what can change without a restart",
+                },
+                Command {
+                    name: "transcript",
+                    aliases: &[],
+                    handler: cmd_transcript,
+                    help: "Print the conversation to the terminal's scrollback",
+                },
+                Command {
+                    name: "debug",
+                    aliases: &[],
+                    handler: cmd_debug,
+                    help: "Show the next request without sending it: /debug request",
+                },
+                Command {
+                    name: "cache",
+                    aliases: &[],
+                    handler: cmd_cache,
+                    help: "Response cache: /cache [clear]",
+                },
                 Command {
                     name: "sessions",
                     aliases: &["sess"],
                     handler: cmd_sessions,
                     help: "List all sessions",
                 },
+                Command {
+                    name: "new",
+                    aliases: &[],
+                    handler: cmd_new,
+                    help: "Save this chat and start a new session: /new [model]",
+                },
                 Command {
                     name: "load",
                     aliases: &[],
                     handler: cmd_load,
-                    help: "Load session: /load <id>",
+                    help: "Load session: /load <number|id>",
                 },
                 Command {
                     name: "rename",
@@ -118,7 +371,7 @@ impl CommandRegistry {
                     name: "prompt",
                     aliases: &["p"],
                     handler: cmd_prompt,
-                    help: "Load static prompt: /prompt <id|list>",
+                    help: "Load static prompt: /prompt <id|list> | /prompt show <id>",
                 },
             ],
         }
@@ -134,13 +387,18 @@ impl CommandRegistry {
 
         for cmd in &self.commands {
             if cmd.name == *command || cmd.aliases.contains(command) {
-                return (cmd.handler)(state, &parts[1..]);
+                return match (cmd.handler)(state, &parts[1..]) {
+                    Err(e) => match e.downcast::<CommandError>() {
+                        Ok(cmd_err) => Ok(CommandResult::Error(cmd_err.to_string())),
+                        Err(e) => Err(e),
+                    },
+                    result => result,
+                };
             }
         }
 
-        println!("  {}✗ Unknown command: /{}. Type / for help.",
-            "".bright_red(), command);
-        Ok(CommandResult::Continue)
+        // Shown in the status bar; printed output would be hidden by the TUI
+        Ok(CommandResult::Error(format!("Unknown command: /{}. Type / for the list", command)))
     }
 
     #[allow(dead_code)]
@@ -157,15 +415,21 @@ impl CommandRegistry {
     }
 }
 
+impl Default for CommandRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 // ============================================================================
 // COMMAND IMPLEMENTATIONS
 // ============================================================================
 
 fn cmd_help(_state: &mut ChatState, _args: &[&str]) -> Result<CommandResult> {
     println!();
-    let orange = "\x1b[38;5;208m";
-    let emerald = "\x1b[38;5;48m";
-    let reset = "\x1b[0m";
+    let orange = ansi(ORANGE);
+    let emerald = ansi(EMERALD);
+    let reset = ansi(RESET);
 
     let padding = "─".repeat(57);
     println!("{}┌─ {}Available Commands{}{}{}", orange, emerald, orange, padding, reset);
@@ -179,7 +443,7 @@ fn cmd_help(_state: &mut ChatState, _args: &[&str]) -> Result<CommandResult> {
             reset);
     }
 
-    println!("{}{}{}", orange, "└─────────────────────────────────────────────────────────┘", reset);
+    println!("{}└─────────────────────────────────────────────────────────┘{}", orange, reset);
     println!();
     Ok(CommandResult::Continue)
 }
@@ -187,13 +451,13 @@ fn cmd_help(_state: &mut ChatState, _args: &[&str]) -> Result<CommandResult> {
 fn cmd_exit(state: &mut ChatState, _args: &[&str]) -> Result<CommandResult> {
     state.session.save(&state.config.directories.sessions)?;
     println!();
-    let orange = "\x1b[38;5;208m";
-    let emerald = "\x1b[38;5;48m";
-    let reset = "\x1b[0m";
-    println!("{}{}{}", orange, "╔════════════════════════════════════════════════════╗", reset);
+    let orange = ansi(ORANGE);
+    let emerald = ansi(EMERALD);
+    let reset = ansi(RESET);
+    println!("{}╔════════════════════════════════════════════════════╗{}", orange, reset);
     println!("{}║ {}✓ Chat saved and session persisted{}  {}║{}",
         orange, emerald, orange, " ".repeat(20), reset);
-    println!("{}{}{}", orange, "╚════════════════════════════════════════════════════╝", reset);
+    println!("{}╚════════════════════════════════════════════════════╝{}", orange, reset);
     println!();
     Ok(CommandResult::Exit)
 }
@@ -215,8 +479,8 @@ fn cmd_model(state: &mut ChatState, args: &[&str]) -> Result<CommandResult> {
     let mut models: Vec<String> = state.config.models.registry.keys().cloned().collect();
     models.sort();
 
-    let emerald = "\x1b[38;5;48m";
-    let reset = "\x1b[0m";
+    let emerald = ansi(EMERALD);
+    let reset = ansi(RESET);
 
     if args.is_empty() {
         // List models
@@ -235,39 +499,204 @@ fn cmd_model(state: &mut ChatState, args: &[&str]) -> Result<CommandResult> {
         println!();
     } else {
         // Switch model
-        if let Ok(index) = args[0].parse::<usize>() {
-            if index > 0 && index <= models.len() {
-                let new_model = &models[index - 1];
-                println!("  {}⏳ Switching to {}...{}", emerald, new_model, reset);
+        let index: usize = parse_arg(args[0], "model number")?;
+        if index == 0 || index > models.len() {
+            return Err(CommandError::OutOfRange(format!("model {} (1-{})", index, models.len())).into());
+        }
 
-                // Return signal to switch model - chat loop will handle async restart
-                return Ok(CommandResult::SwitchModel(new_model.clone()));
-            } else {
-                println!(
-                    "  {}✗ Invalid model index{}",
-                    "".bright_red(),
-                    state.config.ui.color_reset
-                );
-                println!();
+        let new_model = &models[index - 1];
+        println!("  {}⏳ Switching to {}...{}", emerald, new_model, reset);
+
+        // Return signal to switch model - chat loop will handle async restart
+        return Ok(CommandResult::SwitchModel(new_model.clone(), None));
+    }
+
+    Ok(CommandResult::Continue)
+}
+
+fn cmd_models(state: &mut ChatState, args: &[&str]) -> Result<CommandResult> {
+    if args.first() != Some(&"refresh") {
+        return Err(CommandError::BadArgument("usage: /models refresh".to_string()).into());
+    }
+
+    let added = crate::config::merge_scanned_models(state.model_registry);
+    Ok(CommandResult::Status(format!(
+        "Rescanned {}/: {} new model(s), {} total",
+        crate::config::MODELS_DIR,
+        added,
+        state.model_registry.len()
+    )))
+}
+
+// Validate the URL here; the health check and client swap happen in the chat loop
+fn cmd_connect(_state: &mut ChatState, args: &[&str]) -> Result<CommandResult> {
+    let [url] = args else {
+        return Err(CommandError::BadArgument("usage: /connect <url> (e.g. http://192.168.1.20:8080/v1)".to_string()).into());
+    };
+    let mut parsed = reqwest::Url::parse(url)
+        .map_err(|e| CommandError::BadArgument(format!("url '{}': {}", url, e)))?;
+    if !matches!(parsed.scheme(), "http" | "https") || parsed.host_str().is_none() {
+        return Err(CommandError::BadArgument(format!("url '{}' (expected http(s)://host:port)", url)).into());
+    }
+    // A bare server address means its OpenAI-compatible API under /v1
+    if parsed.path() == "/" {
+        parsed.set_path("/v1");
+    }
+    let api_url = parsed.as_str().trim_end_matches('/').to_string();
+
+    Ok(CommandResult::Connect(api_url))
+}
+
+fn cmd_info(state: &mut ChatState, _args: &[&str]) -> Result<CommandResult> {
+    let params = state.config.get_model_params(state.current_model);
+    let model_path = state.config.models.registry
+        .get(state.current_model.as_str())
+        .cloned()
+        .unwrap_or_else(|| state.config.models.default.clone());
+    let mmproj = match &params.mmproj {
+        Some(path) if std::path::Path::new(path).exists() => format!("loaded ({})", path),
+        Some(path) => format!("not loaded (missing: {})", path),
+        None => "none".to_string(),
+    };
+
+    let mut output = CommandOutput::new("Model Info");
+    let rows = [
+        ("model", state.current_model.clone()),
+        ("model path", model_path),
+        ("context_window", params.context_window.to_string()),
+        ("gpu_layers", params.gpu_layers.to_string()),
+        ("batch_size", params.batch_size.to_string()),
+        ("ubatch_size", params.ubatch_size.to_string()),
+        ("parallel", params.parallel.to_string()),
+        ("cache_type_k", params.cache_type_k.clone().unwrap_or_else(|| "default".to_string())),
+        ("cache_type_v", params.cache_type_v.clone().unwrap_or_else(|| "default".to_string())),
+        ("mmproj", mmproj),
+        ("server binary", SERVER_PATH.to_string()),
+        ("api_url", state.client.api_url().to_string()),
+        ("server", if state.config.llm.manage_server { "managed by rubox" } else { "external" }.to_string()),
+        (INFO_SERVER_MODEL, INFO_SERVER_MODEL_PENDING.to_string()),
+    ];
+    for (key, value) in rows {
+        output.spans(vec![(Tone::Label, format!("{:<15} ", key)), (Tone::Plain, value)]);
+    }
+    let custom = custom_sampling(state.config.temperature.default, &Sampling {
+        temperature: *state.temperature,
+        top_p: *state.top_p,
+        max_tokens: *state.max_tokens,
+        candidate_count: *state.candidate_count,
+        output_format: state.output_format,
+        logit_bias: state.logit_bias,
+        think: *state.think,
+    });
+    if custom.is_empty() {
+        output.spans(vec![(Tone::Label, format!("{:<15} ", "sampling")), (Tone::Plain, "config defaults".to_string())]);
+    }
+    for (i, entry) in custom.into_iter().enumerate() {
+        output.spans(vec![(Tone::Label, format!("{:<15} ", if i == 0 { "custom" } else { "" })), (Tone::Plain, entry)]);
+    }
+    Ok(CommandResult::Info(output))
+}
+
+// Ask the running server what it actually loaded; "unavailable" if it is
+// unreachable or slow. Runs off the event loop, see CommandResult::Info.
+pub async fn server_model(client: &LlmClient) -> String {
+    tokio::time::timeout(INFO_PROPS_TIMEOUT, client.server_props())
+        .await
+        .ok()
+        .and_then(Result::ok)
+        .and_then(|props| {
+            props.get("model_path")
+                .or_else(|| props.pointer("/default_generation_settings/model"))
+                .and_then(|v| v.as_str())
+                .map(str::to_string)
+        })
+        .unwrap_or_else(|| "unavailable".to_string())
+}
+
+// Put the server's answer into an /info output still waiting for it
+pub fn fill_server_model(output: &mut CommandOutput, model: &str) {
+    for spans in &mut output.lines {
+        if let [(Tone::Label, label), (Tone::Plain, value)] = spans.as_mut_slice() {
+            if label.trim_end() == INFO_SERVER_MODEL && value == INFO_SERVER_MODEL_PENDING {
+                *value = model.to_string();
             }
-        } else {
-            println!(
-                "  {}Usage: /model <number>{}",
-                "".bright_red(),
-                state.config.ui.color_reset
-            );
-            println!();
         }
     }
+}
 
-    Ok(CommandResult::Continue)
+fn cmd_benchmark(state: &mut ChatState, args: &[&str]) -> Result<CommandResult> {
+    if state.is_loading {
+        return Ok(CommandResult::Error("Wait for the current generation to finish before benchmarking".to_string()));
+    }
+
+    let runs: usize = match args.first() {
+        Some(value) => parse_arg(value, "run count")?,
+        None => 3,
+    };
+    if runs == 0 || runs > BENCHMARK_MAX_RUNS {
+        return Err(CommandError::OutOfRange(format!("run count must be between 1 and {}", BENCHMARK_MAX_RUNS)).into());
+    }
+
+    // The runs take a while; the chat loop starts them in the background
+    Ok(CommandResult::Benchmark(runs))
+}
+
+// Latency, completion tokens and tok/s of one benchmark run
+pub type BenchmarkRun = (f32, u32, f32);
+
+// Send BENCHMARK_PROMPT `runs` times in a scratch conversation (the session
+// history is never touched), calling `on_run` after each run
+pub async fn run_benchmark(
+    client: &LlmClient,
+    temperature: f32,
+    runs: usize,
+    on_run: impl Fn(usize),
+) -> Result<Vec<BenchmarkRun>> {
+    let messages = vec![crate::llm_client::ChatMessage {
+        role: "user".to_string(),
+        content: BENCHMARK_PROMPT.to_string(),
+    }];
+
+    // Cached replies would make every run after the first look instant
+    let mut client = client.clone();
+    client.cache = None;
+    let mut results = Vec::new();
+    for run in 1..=runs {
+        let start = std::time::Instant::now();
+        let (_, usage) = client.chat_completion_with_usage(messages.clone(), temperature).await?;
+        let elapsed = start.elapsed().as_secs_f32();
+        let tokens = usage.map(|u| u.completion_tokens).unwrap_or(0);
+        let tps = if elapsed > 0.0 { tokens as f32 / elapsed } else { 0.0 };
+        results.push((elapsed, tokens, tps));
+        on_run(run);
+    }
+    Ok(results)
 }
 
-fn cmd_history(state: &mut ChatState, _args: &[&str]) -> Result<CommandResult> {
+// The benchmark table: one row per run, then the averages
+pub fn benchmark_report(model: &str, results: &[BenchmarkRun]) -> CommandOutput {
+    let mut output = CommandOutput::new(format!("Benchmark: {}", model));
+    output.line(Tone::Label, format!("{:<8} {:>10} {:>10} {:>10}", "run", "latency", "tokens", "tok/s"));
+    for (i, (elapsed, tokens, tps)) in results.iter().enumerate() {
+        output.line(Tone::Plain, format!("{:<8} {:>9.2}s {:>10} {:>10.1}", i + 1, elapsed, tokens, tps));
+    }
+
+    let count = results.len().max(1) as f32;
+    let avg_latency = results.iter().map(|r| r.0).sum::<f32>() / count;
+    let avg_tps = results.iter().map(|r| r.2).sum::<f32>() / count;
+    output.line(Tone::Label, format!("{:<8} {:>9.2}s {:>10} {:>10.1}", "average", avg_latency, "", avg_tps));
+    output
+}
+
+fn cmd_history(state: &mut ChatState, args: &[&str]) -> Result<CommandResult> {
+    if args.first() == Some(&"commands") {
+        return show_command_log(state, args.get(1).copied());
+    }
+
     println!();
-    let orange = "\x1b[38;5;208m";
-    let emerald = "\x1b[38;5;48m";
-    let reset = "\x1b[0m";
+    let orange = ansi(ORANGE);
+    let emerald = ansi(EMERALD);
+    let reset = ansi(RESET);
 
     let padding = "─".repeat(57_usize.saturating_sub("Conversation History".len()));
     println!("{}┌─ {}Conversation History{}{}{}", orange, emerald, orange, padding, reset);
@@ -287,89 +716,999 @@ fn cmd_history(state: &mut ChatState, _args: &[&str]) -> Result<CommandResult> {
             preview);
     }
 
-    println!("{}{}{}", orange, "└─────────────────────────────────────────────────────────┘", reset);
+    println!("{}└─────────────────────────────────────────────────────────┘{}", orange, reset);
     println!();
     Ok(CommandResult::Continue)
 }
 
+// `/history commands [n]`: the most recent entries of the global command log
+fn show_command_log(state: &mut ChatState, limit: Option<&str>) -> Result<CommandResult> {
+    if !state.config.session.command_log {
+        return Ok(CommandResult::Error(
+            "Command logging is off; enable session.command_log in the config".to_string(),
+        ));
+    }
+    let limit: usize = match limit {
+        Some(n) => parse_arg(n, "entry count")?,
+        None => COMMAND_LOG_DEFAULT_ENTRIES,
+    };
+
+    let log = CommandLog::new(&state.config.directories.chat, state.config.session.command_log_max_kb);
+    let entries = log.recent(limit);
+
+    let mut output = CommandOutput::new("Command History");
+    if entries.is_empty() {
+        output.line(Tone::Dim, "No commands logged yet");
+    }
+    for entry in &entries {
+        output.spans(vec![
+            (Tone::Dim, entry.timestamp.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M ").to_string()),
+            (Tone::Label, format!("{} ", entry.session_id)),
+            (Tone::Plain, entry.command.clone()),
+        ]);
+    }
+    Ok(CommandResult::Output(output))
+}
+
+// The message a per-message command acts on: its id argument, else the focused one
+fn target_message(state: &ChatState, arg: Option<&str>, usage: &str) -> Result<usize> {
+    let id = match arg {
+        Some(arg) => parse_arg(arg, "message id")?,
+        None => state.focused.ok_or_else(|| CommandError::BadArgument(format!("usage: {}", usage)))?,
+    };
+    if state.session.get_message(id).is_none() {
+        return Err(CommandError::NotFound(format!("message {}", id)).into());
+    }
+    Ok(id)
+}
+
 fn cmd_delete(state: &mut ChatState, args: &[&str]) -> Result<CommandResult> {
+    let focused = state.focused.map(|id| id.to_string());
+    let args: Vec<&str> = if args.is_empty() { focused.iter().map(String::as_str).collect() } else { args.to_vec() };
     if args.is_empty() {
-        println!("  {}Usage: /delete <id|all>", "".bright_red());
-        return Ok(CommandResult::Continue);
+        return Err(CommandError::BadArgument("usage: /delete <id|3-7|3,5,8|all>".to_string()).into());
     }
 
-    if args[0] == "all" {
+    let status = if args[0] == "all" {
+        *state.undo_snapshot = Some(state.session.clone());
         state.session.clear_all();
-        println!("  {}✓ All messages deleted", "".bright_green());
+        "All messages deleted".to_string()
     } else {
-        let id: usize = args[0].parse()?;
-        state.session.delete_message(id)?;
-        println!("  {}✓ Message {} deleted", "".bright_green(), id);
-    }
+        let spec = args.join("");
+        let ids = parse_id_selection(&spec, state.session).ok_or_else(|| {
+            CommandError::BadArgument(format!("{} (e.g. 5, 3-7 or 3,5,8)", spec))
+        })?;
+        // Nothing to remove: keep the undo snapshot the user may still need
+        if ids.is_empty() {
+            return Err(CommandError::NotFound(format!("messages {}", spec)).into());
+        }
+
+        // One-level undo: keep only the state from before this delete
+        *state.undo_snapshot = Some(state.session.clone());
+
+        // Remaining messages keep their ids (no renumbering), so ids shown
+        // in /history stay valid for follow-up /save or /delete commands.
+        for &id in &ids {
+            state.session.delete_message(id)?;
+        }
+        format!("{} message(s) deleted", ids.len())
+    };
 
     state.session.save(&state.config.directories.sessions)?;
+    Ok(CommandResult::Status(status))
+}
+
+fn cmd_search(state: &mut ChatState, args: &[&str]) -> Result<CommandResult> {
+    // Role flags may appear anywhere; everything else is the query
+    let mut role = None;
+    let mut words = Vec::new();
+    for arg in args {
+        match *arg {
+            "--user" => role = Some("user"),
+            "--assistant" => role = Some("assistant"),
+            word => words.push(word),
+        }
+    }
+    if words.is_empty() {
+        return Err(CommandError::BadArgument("usage: /search [--user|--assistant] <text>".to_string()).into());
+    }
+    let query = words.join(" ").to_lowercase();
+
+    // Archived messages first: they are always older than the ones in memory
+    let archived = state.session.load_archive(&state.config.directories.sessions);
+    let archived_count = archived.len();
+    let matches: Vec<(bool, crate::session::ChatMessage)> = archived
+        .into_iter()
+        .map(|m| (true, m))
+        .chain(state.session.messages.iter().cloned().map(|m| (false, m)))
+        .filter(|(_, m)| role.is_none_or(|r| m.role == r))
+        .filter(|(_, m)| m.content.to_lowercase().contains(&query))
+        .collect();
+
+    let mut output = CommandOutput::new(format!("Search: {}", query));
+    if matches.is_empty() {
+        output.line(Tone::Dim, format!("No matches in {} message(s)", state.session.messages.len() + archived_count));
+    }
+    let skipped = matches.len().saturating_sub(SEARCH_MAX_RESULTS);
+    if skipped > 0 {
+        output.line(Tone::Dim, format!("… {} older matches not shown", skipped));
+    }
+    for (is_archived, msg) in &matches[skipped..] {
+        let marker = if *is_archived { " (archived)" } else { "" };
+        let (before, matched, after) = search_snippet(&msg.content, &query);
+        output.spans(vec![
+            (Tone::Plain, format!("[{}] ", msg.id)),
+            (Tone::Label, msg.role.clone()),
+            (Tone::Dim, marker.to_string()),
+            (Tone::Plain, format!(": {}", before)),
+            (Tone::Highlight, matched),
+            (Tone::Plain, after),
+        ]);
+    }
+    Ok(CommandResult::Output(output))
+}
+
+// One line of context around the first match of `query` (already lowercase),
+// split into (before, match, after) so the match can be highlighted
+fn search_snippet(content: &str, query: &str) -> (String, String, String) {
+    const CONTEXT_CHARS: usize = 30;
+    let flat = content.replace('\n', " ");
+    let chars: Vec<char> = flat.chars().collect();
+    let lower: Vec<char> = flat.to_lowercase().chars().collect();
+    let needle: Vec<char> = query.chars().collect();
+    // Lowercasing can change the length of a few characters; fall back to the start
+    let found = if lower.len() == chars.len() {
+        lower.windows(needle.len().max(1)).position(|w| w == needle.as_slice())
+    } else {
+        None
+    };
+    let (position, match_len) = match found {
+        Some(position) => (position, needle.len()),
+        None => (0, 0),
+    };
+
+    let start = position.saturating_sub(CONTEXT_CHARS);
+    let match_end = position + match_len;
+    let end = (match_end + CONTEXT_CHARS).min(chars.len());
+    let mut before: String = chars[start..position].iter().collect();
+    let matched: String = chars[position..match_end].iter().collect();
+    let mut after: String = chars[match_end..end].iter().collect();
+    if start > 0 {
+        before.insert(0, '…');
+    }
+    if end < chars.len() {
+        after.push('…');
+    }
+    (before, matched, after)
+}
+
+fn cmd_diff(state: &mut ChatState, args: &[&str]) -> Result<CommandResult> {
+    let [first, second] = args else {
+        return Err(CommandError::BadArgument("usage: /diff <id1> <id2>".to_string()).into());
+    };
+    let first: usize = parse_arg(first, "message id")?;
+    let second: usize = parse_arg(second, "message id")?;
+    let old = state.session.get_message(first)
+        .ok_or_else(|| CommandError::NotFound(format!("message {}", first)))?;
+    let new = state.session.get_message(second)
+        .ok_or_else(|| CommandError::NotFound(format!("message {}", second)))?;
+
+    // Bound the diff time too; very large messages fall back to a coarser diff
+    let diff = similar::TextDiff::configure()
+        .timeout(std::time::Duration::from_secs(1))
+        .diff_lines(&old.content, &new.content);
+
+    let mut output = CommandOutput::new(format!("Diff #{} → #{}", first, second));
+    let mut shown = 0;
+    let mut hidden = 0;
+    for change in diff.iter_all_changes() {
+        let line = change.value().trim_end_matches('\n');
+        let (tone, sign) = match change.tag() {
+            similar::ChangeTag::Equal => continue,
+            similar::ChangeTag::Delete => (Tone::Removed, '-'),
+            similar::ChangeTag::Insert => (Tone::Added, '+'),
+        };
+        if shown < DIFF_MAX_LINES {
+            output.line(tone, format!("{} {}", sign, line));
+            shown += 1;
+        } else {
+            hidden += 1;
+        }
+    }
+
+    if shown == 0 {
+        output.line(Tone::Dim, "Messages are identical");
+    }
+    if hidden > 0 {
+        output.line(Tone::Dim, format!("… {} more changed lines not shown", hidden));
+    }
+    Ok(CommandResult::Output(output))
+}
+
+fn cmd_undo(state: &mut ChatState, _args: &[&str]) -> Result<CommandResult> {
+    match state.undo_snapshot.take() {
+        Some(snapshot) => {
+            *state.session = snapshot;
+            state.session.save(&state.config.directories.sessions)?;
+            Ok(CommandResult::Status(format!("Restored {} message(s)", state.session.messages.len())))
+        }
+        None => Ok(CommandResult::Error("Nothing to undo".to_string())),
+    }
+}
+
+fn cmd_retry(state: &mut ChatState, args: &[&str]) -> Result<CommandResult> {
+    let temperature = match args {
+        [] => None,
+        ["temp" | "temperature", value] => {
+            let value: f32 = parse_arg(value, "temperature value")?;
+            if value < state.config.temperature.min || value > state.config.temperature.max {
+                return Err(CommandError::OutOfRange(format!(
+                    "temperature must be between {} and {}",
+                    state.config.temperature.min,
+                    state.config.temperature.max
+                )).into());
+            }
+            Some(value)
+        }
+        _ => return Err(CommandError::BadArgument("usage: /retry [temp <value>]".to_string()).into()),
+    };
+
+    if !state.session.messages.iter().any(|m| m.role == "user") {
+        return Err(CommandError::NotFound("nothing to retry".to_string()).into());
+    }
+
+    // The last answer stays as a variant the new reply is filed next to (see
+    // Session::begin_regenerate); /undo drops the new variant again
+    if state.session.messages.last().is_some_and(|m| m.role == "assistant") {
+        *state.undo_snapshot = Some(state.session.clone());
+        state.session.begin_regenerate();
+    }
+
+    Ok(CommandResult::Regenerate(temperature))
+}
+
+fn cmd_continue(state: &mut ChatState, args: &[&str]) -> Result<CommandResult> {
+    if args.is_empty() {
+        return Err(CommandError::BadArgument("usage: /continue <text>".to_string()).into());
+    }
+    if state.is_loading {
+        return Ok(CommandResult::Error("Wait for the current generation to finish first".to_string()));
+    }
+    if state.session.messages.last().map(|m| m.role.as_str()) != Some("user") {
+        return Err(CommandError::NotFound("no user message awaiting a reply".to_string()).into());
+    }
+
+    Ok(CommandResult::Prefill(args.join(" ")))
+}
+
+fn cmd_think(state: &mut ChatState, args: &[&str]) -> Result<CommandResult> {
+    let Some(id) = args.first() else {
+        return Err(CommandError::BadArgument("usage: /think <id>".to_string()).into());
+    };
+    let id: usize = parse_arg(id, "message id")?;
+    let msg = state
+        .session
+        .get_message(id)
+        .ok_or_else(|| CommandError::NotFound(format!("message {}", id)))?;
+    if crate::session::split_thinking(&msg.content).0.is_none() {
+        return Err(CommandError::NotFound(format!("reasoning in message {}", id)).into());
+    }
+
+    if !state.expanded_thinking.remove(&id) {
+        state.expanded_thinking.insert(id);
+    }
     Ok(CommandResult::Continue)
 }
 
+fn cmd_rate(state: &mut ChatState, args: &[&str]) -> Result<CommandResult> {
+    const USAGE: &str = "/rate [id] <good|bad|clear>";
+    let (id, rating) = match args {
+        [id, rating] => (target_message(state, Some(id), USAGE)?, *rating),
+        [rating] => (target_message(state, None, USAGE)?, *rating),
+        _ => return Err(CommandError::BadArgument(format!("usage: {}", USAGE)).into()),
+    };
+    let rating = match rating {
+        "good" | "+" => Some(RATING_GOOD),
+        "bad" | "-" => Some(RATING_BAD),
+        "clear" | "off" => None,
+        other => return Err(CommandError::BadArgument(format!("{} (expected good, bad or clear)", other)).into()),
+    };
+    if state.session.get_message(id).is_some_and(|msg| msg.role != "assistant") {
+        return Err(CommandError::BadArgument(format!("message {} is not an answer", id)).into());
+    }
+    state.session.rate(id, rating)?;
+    Ok(CommandResult::Status(format!("Message {} {}", id, match rating {
+        Some(RATING_GOOD) => "rated good",
+        Some(_) => "rated bad",
+        None => "unrated",
+    })))
+}
+
+// OSC 52 asks the terminal itself to set the clipboard, which also works over SSH
+fn cmd_copy(state: &mut ChatState, args: &[&str]) -> Result<CommandResult> {
+    if args.first() == Some(&"code") {
+        return copy_code(state, &args[1..]);
+    }
+    let id = target_message(state, args.first().copied(), "/copy [id]")?;
+    let Some(msg) = state.session.get_message(id) else {
+        return Err(CommandError::NotFound(format!("message {}", id)).into());
+    };
+    // Copy what the chat shows: the answer without its reasoning
+    let text = crate::session::split_thinking(&msg.content).1;
+    copy_to_clipboard(&text)?;
+    Ok(CommandResult::Status(format!("Copied message {} ({} chars)", id, text.chars().count())))
+}
+
+// /copy code [id] [n]: one fenced block of the focused or latest reply, the
+// last one unless `n` picks another
+fn copy_code(state: &mut ChatState, args: &[&str]) -> Result<CommandResult> {
+    let usage = "/copy code [id] [n]";
+    let id = match args.first() {
+        Some(arg) => target_message(state, Some(arg), usage)?,
+        None => state
+            .focused
+            .or_else(|| state.session.messages.iter().rev().find(|m| m.role == "assistant").map(|m| m.id))
+            .ok_or_else(|| CommandError::NotFound("a reply to copy code from".to_string()))?,
+    };
+    let Some(msg) = state.session.get_message(id) else {
+        return Err(CommandError::NotFound(format!("message {}", id)).into());
+    };
+    let blocks = crate::tui::code_blocks(&crate::session::split_thinking(&msg.content).1);
+    if blocks.is_empty() {
+        return Err(CommandError::NotFound(format!("code block in message {}", id)).into());
+    }
+
+    let number = match args.get(1) {
+        Some(arg) => parse_arg(arg, "code block number")?,
+        None => blocks.len(),
+    };
+    if number == 0 || number > blocks.len() {
+        return Err(CommandError::OutOfRange(format!(
+            "message {} has {} code block(s)",
+            id,
+            blocks.len()
+        )).into());
+    }
+    let code = &blocks[number - 1];
+    copy_to_clipboard(code)?;
+    let which = if blocks.len() > 1 {
+        format!(" (block {} of {}; /copy code {} <n> for another)", number, blocks.len(), id)
+    } else {
+        String::new()
+    };
+    Ok(CommandResult::Status(format!("Copied code from message {}{}", id, which)))
+}
+
+// OSC 52: the terminal puts the text on the clipboard, also over SSH
+fn copy_to_clipboard(text: &str) -> Result<()> {
+    use base64::Engine;
+    use std::io::Write;
+
+    let mut stdout = std::io::stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", base64::engine::general_purpose::STANDARD.encode(text))?;
+    stdout.flush()?;
+    Ok(())
+}
+
+fn cmd_edit(state: &mut ChatState, args: &[&str]) -> Result<CommandResult> {
+    let id = target_message(state, args.first().copied(), "/edit [id]")?;
+    Ok(CommandResult::Edit(id))
+}
+
 fn cmd_save(state: &mut ChatState, args: &[&str]) -> Result<CommandResult> {
+    let (args, meta) = take_meta_flag(args);
+    let focused = state.focused.map(|id| id.to_string());
+    let args: Vec<&str> = if args.is_empty() { focused.iter().map(String::as_str).collect() } else { args };
     if args.is_empty() {
-        println!("  {}Usage: /save <id>", "".bright_red());
-        return Ok(CommandResult::Continue);
+        return Err(CommandError::BadArgument(
+            "usage: /save [id] [--meta [json]] | /save all [--user] [--meta [json]] | /export html | /export jsonl [pairs] [--good] [--exclude <ids>]".to_string(),
+        ).into());
+    }
+    if meta.is_some() && matches!(args[0], "html" | "jsonl") {
+        return Err(CommandError::BadArgument("--meta (only for saved messages)".to_string()).into());
+    }
+
+    if args[0] == "html" {
+        let save_dir = &state.config.directories.saved_responses;
+        fs::create_dir_all(save_dir)?;
+
+        let timestamp = Local::now().format(FILENAME_TIMESTAMP).to_string();
+        let name = render_filename(
+            &state.config.filenames.export,
+            &[
+                ("model", &state.session.metadata.model_name),
+                ("timestamp", &timestamp),
+                ("session", &state.session.metadata.id),
+            ],
+        );
+        let filename = format!("{}/{}.html", save_dir, name);
+        let html = crate::export::session_to_html(
+            state.session,
+            &state.config.user.name,
+            state.config.session.strip_thinking,
+        );
+        fs::write(&filename, html)?;
+        return Ok(CommandResult::Status(format!("Exported session to: {}", filename)));
+    }
+
+    if args[0] == "jsonl" {
+        return export_jsonl(state, &args[1..]);
+    }
+
+    if args[0] == "all" {
+        let include_user = match args.get(1) {
+            None => false,
+            Some(&"--user") => true,
+            Some(other) => return Err(CommandError::BadArgument(format!("{} (expected --user)", other)).into()),
+        };
+
+        // One file per message, grouped in a directory per session
+        let save_dir = format!("{}/{}", state.config.directories.saved_responses, state.session.metadata.id);
+        fs::create_dir_all(&save_dir)?;
+
+        let mut written = 0;
+        for msg in &state.session.messages {
+            if msg.role == "assistant" || (include_user && msg.role == "user") {
+                let provenance = meta.map(|format| (format, state.session.provenance(msg)));
+                write_message_file(state.config, &format!("{}/msg_{}", save_dir, msg.id), &msg.content, provenance.as_ref())?;
+                written += 1;
+            }
+        }
+        return Ok(CommandResult::Status(format!("Saved {} message(s) to: {}", written, save_dir)));
     }
 
-    let id: usize = args[0].parse()?;
+    let id: usize = parse_arg(args[0], "message id")?;
 
     if let Some(msg) = state.session.get_message(id) {
         let save_dir = &state.config.directories.saved_responses;
         fs::create_dir_all(save_dir)?;
 
-        let filename = format!("{}/{}_{}.txt",
-            save_dir,
-            state.session.metadata.id,
-            id);
+        let name = render_filename(
+            &state.config.filenames.saved_message,
+            &[
+                ("model", &state.session.metadata.model_name),
+                ("timestamp", &Local::now().format(FILENAME_TIMESTAMP).to_string()),
+                ("session", &state.session.metadata.id),
+                ("id", &id.to_string()),
+            ],
+        );
+        let stem = format!("{}/{}", save_dir, name);
+        let provenance = meta.map(|format| (format, state.session.provenance(msg)));
+        let saved = write_message_file(state.config, &stem, &msg.content, provenance.as_ref())?;
+        // Sidecar files sit next to the text, so their names are enough
+        let mut status = format!("Saved to: {}", saved.text);
+        for extra in [saved.thinking, saved.meta].into_iter().flatten() {
+            let name = std::path::Path::new(&extra).file_name().map_or(extra.clone(), |n| n.to_string_lossy().into_owned());
+            status.push_str(&format!(" + {}", name));
+        }
+        Ok(CommandResult::Status(status))
+    } else {
+        Err(CommandError::NotFound(format!("message {}", id)).into())
+    }
+}
+
+// /export jsonl [pairs] [--good] [--exclude <ids>]: the session as fine-tuning
+// data. Answers rated bad are always left out; --good keeps only those rated good.
+fn export_jsonl(state: &mut ChatState, args: &[&str]) -> Result<CommandResult> {
+    let mut layout = crate::export::JsonlLayout::Conversation;
+    let mut only_good = false;
+    let mut excluded = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match *arg {
+            "pairs" => layout = crate::export::JsonlLayout::Pairs,
+            "--good" => only_good = true,
+            "--exclude" => {
+                let spec = args
+                    .next()
+                    .ok_or_else(|| CommandError::BadArgument("--exclude needs message ids".to_string()))?;
+                excluded = parse_id_selection(spec, state.session)
+                    .ok_or_else(|| CommandError::BadArgument(format!("message ids '{}'", spec)))?;
+            }
+            other => return Err(CommandError::BadArgument(format!("{} (expected pairs, --good or --exclude <ids>)", other)).into()),
+        }
+    }
+
+    let jsonl = crate::export::session_to_jsonl(
+        state.session,
+        layout,
+        state.config.session.strip_thinking,
+        |msg| {
+            !excluded.contains(&msg.id)
+                && msg.rating != Some(RATING_BAD)
+                && (!only_good || msg.role != "assistant" || msg.rating == Some(RATING_GOOD))
+        },
+    );
+    if jsonl.is_empty() {
+        return Err(CommandError::NotFound("complete user/assistant exchanges to export".to_string()).into());
+    }
+
+    let save_dir = &state.config.directories.saved_responses;
+    fs::create_dir_all(save_dir)?;
+    let timestamp = Local::now().format(FILENAME_TIMESTAMP).to_string();
+    let name = render_filename(
+        &state.config.filenames.export,
+        &[
+            ("model", &state.session.metadata.model_name),
+            ("timestamp", &timestamp),
+            ("session", &state.session.metadata.id),
+        ],
+    );
+    let filename = format!("{}/{}.jsonl", save_dir, name);
+    fs::write(&filename, &jsonl)?;
+    Ok(CommandResult::Status(format!("Exported {} record(s) to: {}", jsonl.lines().count(), filename)))
+}
+
+// Where /save --meta puts a message's provenance: YAML front-matter at the
+// top of the file, or with `--meta json` a `<file>.meta.json` next to it
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum MetaFormat {
+    FrontMatter,
+    Json,
+}
+
+// Pull `--meta [json]` out of /save's arguments
+fn take_meta_flag<'a>(args: &[&'a str]) -> (Vec<&'a str>, Option<MetaFormat>) {
+    let Some(pos) = args.iter().position(|a| *a == "--meta") else {
+        return (args.to_vec(), None);
+    };
+    let mut rest = args.to_vec();
+    rest.remove(pos);
+    let format = if rest.get(pos) == Some(&"json") {
+        rest.remove(pos);
+        MetaFormat::Json
+    } else {
+        MetaFormat::FrontMatter
+    };
+    (rest, Some(format))
+}
+
+// Paths written by write_message_file
+struct SavedFiles {
+    text: String,
+    thinking: Option<String>,  // Reasoning sidecar
+    meta: Option<String>,      // Provenance sidecar
+}
 
-        fs::write(&filename, &msg.content)?;
-        println!("  {}✓ Saved to: {}", "".bright_green(), filename);
+// Write `content` to `<stem>.txt`, with the reasoning and provenance sidecars
+// when they apply. The session JSON always keeps the raw content; only the
+// saved file is split.
+fn write_message_file(
+    config: &RuboxConfig,
+    stem: &str,
+    content: &str,
+    provenance: Option<&(MetaFormat, MessageProvenance)>,
+) -> Result<SavedFiles> {
+    let (thinking, answer) = if config.session.strip_thinking {
+        crate::session::split_thinking(content)
     } else {
-        println!("  {}✗ Message {} not found", "".bright_red(), id);
+        (None, content.to_string())
+    };
+
+    let mut saved = SavedFiles { text: format!("{}.txt", stem), thinking: None, meta: None };
+    match provenance {
+        Some((MetaFormat::FrontMatter, provenance)) => fs::write(&saved.text, provenance.front_matter() + &answer)?,
+        Some((MetaFormat::Json, provenance)) => {
+            fs::write(&saved.text, &answer)?;
+            let meta = format!("{}.meta.json", stem);
+            fs::write(&meta, serde_json::to_string_pretty(provenance)?)?;
+            saved.meta = Some(meta);
+        }
+        None => fs::write(&saved.text, &answer)?,
+    }
+    if let Some(thinking) = thinking.filter(|_| config.session.thinking_sidecar) {
+        let sidecar = format!("{}.thinking.txt", stem);
+        fs::write(&sidecar, thinking)?;
+        saved.thinking = Some(sidecar);
     }
+    Ok(saved)
+}
 
-    Ok(CommandResult::Continue)
+// Per-session logit bias by token id. Ids are model-specific; llama-server's
+// /tokenize endpoint maps text to them.
+fn cmd_bias(state: &mut ChatState, args: &[&str]) -> Result<CommandResult> {
+    let status = match args {
+        [] => {
+            if state.logit_bias.is_empty() {
+                return Ok(CommandResult::Status("No token biases set".to_string()));
+            }
+            let mut biases: Vec<_> = state.logit_bias.iter().collect();
+            biases.sort_by_key(|(id, _)| **id);
+            let mut output = CommandOutput::new("Token Biases");
+            for (id, value) in biases {
+                output.spans(vec![(Tone::Label, format!("{:>8}  ", id)), (Tone::Plain, format!("{:+.1}", value))]);
+            }
+            return Ok(CommandResult::Output(output));
+        }
+        ["clear"] => {
+            state.logit_bias.clear();
+            "Token biases cleared".to_string()
+        }
+        [id, "off"] => {
+            let id: u32 = parse_arg(id, "token id")?;
+            if state.logit_bias.remove(&id).is_none() {
+                return Err(CommandError::NotFound(format!("bias for token {}", id)).into());
+            }
+            format!("Bias for token {} removed", id)
+        }
+        [id, value] => {
+            let id: u32 = parse_arg(id, "token id")?;
+            let value: f32 = parse_arg(value, "bias")?;
+            if !(-MAX_LOGIT_BIAS..=MAX_LOGIT_BIAS).contains(&value) {
+                return Err(CommandError::OutOfRange(format!("bias must be between -{0} and {0}", MAX_LOGIT_BIAS)).into());
+            }
+            state.logit_bias.insert(id, value);
+            format!("Token {} biased by {:+.1}", id, value)
+        }
+        _ => {
+            return Err(CommandError::BadArgument("usage: /bias [<token_id> <value|off> | clear]".to_string()).into());
+        }
+    };
+    state.session.metadata.logit_bias = state.logit_bias.clone();
+    Ok(CommandResult::Status(status))
 }
 
-fn cmd_set(state: &mut ChatState, args: &[&str]) -> Result<CommandResult> {
-    if args.len() < 2 {
-        println!("  {}Usage: /set temp <value>", "".bright_red());
-        return Ok(CommandResult::Continue);
+// Read and check the file here; the frontend swaps the config in and
+// refreshes what it copied from the old one
+fn cmd_reload(state: &mut ChatState, args: &[&str]) -> Result<CommandResult> {
+    if !args.is_empty() {
+        return Err(CommandError::BadArgument("usage: /reload".to_string()).into());
+    }
+    let config = state.config.reload().and_then(|config| {
+        let (_, invalid_colors) = crate::tui::Theme::from_config(&config.theme);
+        if !invalid_colors.is_empty() {
+            anyhow::bail!("invalid theme color(s): {}", invalid_colors.join(", "));
+        }
+        if crate::tui::spinner_frames(&config.ui.spinner).is_none() {
+            anyhow::bail!("unknown spinner style '{}'", config.ui.spinner);
+        }
+        if crate::tui::layout_mode(&config.ui.layout).is_none() {
+            anyhow::bail!("unknown layout '{}'", config.ui.layout);
+        }
+        Ok(config)
+    });
+    let config = match config {
+        Ok(config) => config,
+        Err(e) => return Ok(CommandResult::Error(format!("Config not reloaded, keeping the current one: {}", e))),
+    };
+
+    let (applied, restart) = crate::config::config_changes(state.config, &config);
+    let mut report = CommandOutput::new("Config Reloaded");
+    if applied.is_empty() && restart.is_empty() {
+        report.line(Tone::Dim, "Nothing changed");
+    }
+    if !applied.is_empty() {
+        report.spans(vec![(Tone::Label, "Applied: ".to_string()), (Tone::Added, applied.join(", "))]);
     }
+    if !restart.is_empty() {
+        report.spans(vec![
+            (Tone::Label, "Needs a server restart (switch model or restart rubox): ".to_string()),
+            (Tone::Plain, restart.join(", ")),
+        ]);
+    }
+    Ok(CommandResult::Reload(Box::new(config), report))
+}
 
-    match args[0] {
-        "temp" | "temperature" => {
-            let value: f32 = args[1].parse()?;
+// Only the frontend can step out of the TUI to print
+fn cmd_transcript(state: &mut ChatState, _args: &[&str]) -> Result<CommandResult> {
+    if state.session.messages.is_empty() {
+        return Ok(CommandResult::Error("Nothing to print yet".to_string()));
+    }
+    Ok(CommandResult::Transcript)
+}
 
-            if value < state.config.temperature.min || value > state.config.temperature.max {
-                println!("  {}✗ Temperature must be between {} and {}",
-                    "".bright_red(),
-                    state.config.temperature.min,
-                    state.config.temperature.max);
-                return Ok(CommandResult::Continue);
-            }
+// The frontend assembles the request, so it matches what a generation sends
+fn cmd_debug(_state: &mut ChatState, args: &[&str]) -> Result<CommandResult> {
+    match args.first() {
+        Some(&"request") => Ok(CommandResult::DebugRequest),
+        Some(other) => Err(CommandError::BadArgument(format!("{} (expected request)", other)).into()),
+        None => Err(CommandError::BadArgument("missing topic (expected request)".to_string()).into()),
+    }
+}
 
-            *state.temperature = value;
-            state.session.metadata.temperature = value;
-            println!("  {}✓ Temperature set to {}", "".bright_green(), value);
+fn cmd_cache(state: &mut ChatState, args: &[&str]) -> Result<CommandResult> {
+    let cache = ResponseCache::new(ResponseCache::dir_for(&state.config.directories.output));
+    let status = match args.first() {
+        None => {
+            let status = if state.config.llm.response_cache { "on" } else { "off" };
+            format!("Response cache: {} ({} entries, temperature 0 only)", status, cache.len())
         }
-        _ => {
-            println!("  {}✗ Unknown parameter: {}", "".bright_red(), args[0]);
+        Some(&"clear") => {
+            let removed = cache.clear()?;
+            format!("Cleared {} cached response(s)", removed)
         }
+        Some(other) => return Err(CommandError::BadArgument(format!("{} (expected clear)", other)).into()),
+    };
+    Ok(CommandResult::Status(status))
+}
+
+// One row of the /set table. Adding a setting here is enough for it to be
+// accepted by /set and shown in its listing.
+struct SetParam {
+    name: &'static str,
+    aliases: &'static [&'static str],
+    range: fn(&ChatState) -> String,    // Accepted values, for the listing
+    current: fn(&ChatState) -> String,
+    apply: fn(&mut ChatState, &str) -> Result<CommandResult>,
+}
+
+const SET_PARAMS: &[SetParam] = &[
+    SetParam {
+        name: "temp",
+        aliases: &["temperature"],
+        range: |state| format!("{} - {}", state.config.temperature.min, state.config.temperature.max),
+        current: |state| state.temperature.to_string(),
+        apply: set_temperature,
+    },
+    SetParam {
+        name: "top_p",
+        aliases: &["top-p"],
+        range: |_| "0 - 1 | off".to_string(),
+        current: |state| state.top_p.map_or("server default".to_string(), |p| p.to_string()),
+        apply: set_top_p,
+    },
+    SetParam {
+        name: "max_tokens",
+        aliases: &["max-tokens"],
+        range: |_| format!("1 - {} tokens", MAX_REPLY_TOKENS),
+        current: |state| state.max_tokens.to_string(),
+        apply: set_max_tokens,
+    },
+    SetParam {
+        name: "ctx",
+        aliases: &["context"],
+        range: |_| format!(">= {} tokens, before the first reply", MIN_CONTEXT_WINDOW),
+        current: |state| {
+            let ctx = state.server.context_override;
+            ctx.unwrap_or_else(|| state.config.get_model_params(state.current_model).context_window).to_string()
+        },
+        apply: set_context,
+    },
+    SetParam {
+        name: "think",
+        aliases: &["reasoning"],
+        range: |_| "off | low | medium | high | default".to_string(),
+        current: |state| state.think.name().to_string(),
+        apply: set_think,
+    },
+    SetParam {
+        name: "n",
+        aliases: &[],
+        range: |_| format!("1 - {} candidates", MAX_CANDIDATES),
+        current: |state| state.candidate_count.to_string(),
+        apply: set_candidates,
+    },
+    SetParam {
+        name: "format",
+        aliases: &[],
+        range: |_| "json | text".to_string(),
+        current: |state| match state.output_format {
+            OutputFormat::Text => "text",
+            OutputFormat::Json => "json",
+            OutputFormat::Grammar(_) => "grammar",
+        }
+        .to_string(),
+        apply: set_format,
+    },
+    SetParam {
+        name: "grammar",
+        aliases: &[],
+        range: |_| "<path> | off".to_string(),
+        current: |state| match state.output_format {
+            OutputFormat::Grammar(ref gbnf) => format!("{} lines", gbnf.lines().count()),
+            _ => "off".to_string(),
+        },
+        apply: set_grammar,
+    },
+];
+
+fn cmd_set(state: &mut ChatState, args: &[&str]) -> Result<CommandResult> {
+    let param = args
+        .first()
+        .and_then(|key| SET_PARAMS.iter().find(|p| p.name == *key || p.aliases.contains(key)));
+
+    match (param, args) {
+        (Some(param), [_, value]) => (param.apply)(state, value),
+        (None, [key, ..]) => Err(CommandError::BadArgument(format!("unknown parameter {} (/set alone lists them)", key)).into()),
+        // The full list, in the Settings panel where each row can be edited
+        _ => Ok(CommandResult::Settings(setting_rows(state))),
     }
+}
 
-    Ok(CommandResult::Continue)
+// Everything the Settings modal lists: the /set parameters between the model
+// and whether the conversation carries a system prompt, which are read-only here
+pub fn setting_rows(state: &ChatState) -> Vec<SettingRow> {
+    let mut rows = vec![SettingRow {
+        name: "model",
+        value: state.current_model.clone(),
+        range: "enter: choose from the list".to_string(),
+        editable: false,
+    }];
+    rows.extend(SET_PARAMS.iter().map(|param| SettingRow {
+        name: param.name,
+        value: (param.current)(state),
+        range: (param.range)(state),
+        editable: true,
+    }));
+    let system = state.session.messages.iter().find(|m| m.role == "system");
+    rows.push(SettingRow {
+        name: "system",
+        value: system.map_or("none".to_string(), |m| format!("{} chars", m.content.chars().count())),
+        range: "comes from the conversation".to_string(),
+        editable: false,
+    });
+    rows
+}
+
+// Sampling settings that differ from what a new session starts with, one
+// "name value (default ...)" entry each. Shown by /info and, as a [custom]
+// tag, in the status bar so a forgotten /set doesn't go unnoticed.
+pub fn custom_sampling(default_temperature: f32, sampling: &Sampling) -> Vec<String> {
+    let mut custom = Vec::new();
+    if (sampling.temperature - default_temperature).abs() > f32::EPSILON {
+        custom.push(format!("temp {} (default {})", sampling.temperature, default_temperature));
+    }
+    if let Some(p) = sampling.top_p {
+        custom.push(format!("top_p {} (default: server)", p));
+    }
+    if sampling.max_tokens != crate::llm_client::DEFAULT_MAX_TOKENS {
+        custom.push(format!("max_tokens {} (default {})", sampling.max_tokens, crate::llm_client::DEFAULT_MAX_TOKENS));
+    }
+    if sampling.candidate_count != 1 {
+        custom.push(format!("n {} (default 1)", sampling.candidate_count));
+    }
+    match sampling.output_format {
+        OutputFormat::Text => {}
+        OutputFormat::Json => custom.push("format json (default text)".to_string()),
+        OutputFormat::Grammar(_) => custom.push("grammar set (default none)".to_string()),
+    }
+    if !sampling.logit_bias.is_empty() {
+        custom.push(format!("{} token bias(es) (default none)", sampling.logit_bias.len()));
+    }
+    if !sampling.think.is_default() {
+        custom.push(format!("think {} (default: model)", sampling.think.name()));
+    }
+    custom
+}
+
+// The live sampling settings, wherever they are kept
+pub struct Sampling<'a> {
+    pub temperature: f32,
+    pub top_p: Option<f32>,
+    pub max_tokens: u32,
+    pub candidate_count: u32,
+    pub output_format: &'a OutputFormat,
+    pub logit_bias: &'a std::collections::HashMap<u32, f32>,
+    pub think: ThinkLevel,
+}
+
+fn cmd_settings(state: &mut ChatState, _args: &[&str]) -> Result<CommandResult> {
+    Ok(CommandResult::Settings(setting_rows(state)))
+}
+
+fn set_temperature(state: &mut ChatState, value: &str) -> Result<CommandResult> {
+    let value: f32 = parse_arg(value, "temperature value")?;
+
+    if value < state.config.temperature.min || value > state.config.temperature.max {
+        return Err(CommandError::OutOfRange(format!(
+            "temperature must be between {} and {}",
+            state.config.temperature.min,
+            state.config.temperature.max
+        )).into());
+    }
+
+    *state.temperature = value;
+    state.session.metadata.temperature = value;
+    Ok(CommandResult::Status(format!("Temperature set to {}", value)))
+}
+
+fn set_top_p(state: &mut ChatState, value: &str) -> Result<CommandResult> {
+    if value == "off" {
+        *state.top_p = None;
+        state.session.metadata.top_p = None;
+        return Ok(CommandResult::Status("top_p left to the server".to_string()));
+    }
+    let value: f32 = parse_arg(value, "top_p value")?;
+    if !(value > 0.0 && value <= 1.0) {
+        return Err(CommandError::OutOfRange("top_p must be above 0 and at most 1".to_string()).into());
+    }
+    *state.top_p = Some(value);
+    state.session.metadata.top_p = Some(value);
+    Ok(CommandResult::Status(format!("top_p set to {}", value)))
+}
+
+fn set_think(state: &mut ChatState, value: &str) -> Result<CommandResult> {
+    let Some(level) = ThinkLevel::parse(value) else {
+        return Err(CommandError::BadArgument(format!("{} (expected off, low, medium, high or default)", value)).into());
+    };
+    *state.think = level;
+    state.session.metadata.think = level;
+    Ok(CommandResult::Status(match level {
+        ThinkLevel::Default => "Reasoning left to the model".to_string(),
+        _ => format!("Reasoning effort set to {}", level.name()),
+    }))
+}
+
+fn set_max_tokens(state: &mut ChatState, value: &str) -> Result<CommandResult> {
+    let value: u32 = parse_arg(value, "token count")?;
+    if !(1..=MAX_REPLY_TOKENS).contains(&value) {
+        return Err(CommandError::OutOfRange(format!("max_tokens must be between 1 and {}", MAX_REPLY_TOKENS)).into());
+    }
+    *state.max_tokens = value;
+    state.session.metadata.max_tokens = Some(value);
+    Ok(CommandResult::Status(format!("Replies capped at {} tokens", value)))
+}
+
+fn set_context(state: &mut ChatState, value: &str) -> Result<CommandResult> {
+    let value: u32 = parse_arg(value, "context size")?;
+    if value < MIN_CONTEXT_WINDOW {
+        return Err(CommandError::OutOfRange(format!("context size must be at least {}", MIN_CONTEXT_WINDOW)).into());
+    }
+    if !state.config.llm.manage_server {
+        return Ok(CommandResult::Error("The server is externally managed; set its context size there".to_string()));
+    }
+    // Changing it means a server restart, which would drop the KV cache of a running chat
+    if state.session.messages.iter().any(|m| m.role == "assistant") {
+        return Ok(CommandResult::Error("The context size can only be changed before the first generation".to_string()));
+    }
+
+    // No model metadata to check against, so compare with the profile's own size
+    let profile_ctx = state.config.get_model_params(state.current_model).context_window;
+    let mut status = format!("Context size set to {}", value);
+    if value > MAX_PLAUSIBLE_CONTEXT || value > profile_ctx.saturating_mul(4) {
+        status.push_str(&format!(
+            " · ⚠ far above this profile's {}; the KV cache may not fit in memory",
+            profile_ctx
+        ));
+    }
+
+    Ok(CommandResult::SetContext(value, status))
+}
+
+fn set_candidates(state: &mut ChatState, value: &str) -> Result<CommandResult> {
+    let value: u32 = parse_arg(value, "candidate count")?;
+    if !(1..=MAX_CANDIDATES).contains(&value) {
+        return Err(CommandError::OutOfRange(format!("candidate count must be between 1 and {}", MAX_CANDIDATES)).into());
+    }
+    *state.candidate_count = value;
+    state.session.metadata.candidate_count = Some(value);
+    Ok(CommandResult::Status(if value > 1 {
+        format!("Generating {} candidates per reply (streaming off)", value)
+    } else {
+        "Generating a single streamed reply".to_string()
+    }))
+}
+
+fn set_format(state: &mut ChatState, value: &str) -> Result<CommandResult> {
+    *state.output_format = match value {
+        "json" => OutputFormat::Json,
+        "text" | "off" => OutputFormat::Text,
+        other => return Err(CommandError::BadArgument(format!("format '{}' (expected json or text)", other)).into()),
+    };
+    state.session.metadata.output_format = state.output_format.clone();
+    Ok(CommandResult::Status(format!("Output format set to {}", value)))
+}
+
+fn set_grammar(state: &mut ChatState, value: &str) -> Result<CommandResult> {
+    let status = if value == "off" {
+        *state.output_format = OutputFormat::Text;
+        "Grammar cleared".to_string()
+    } else {
+        let path = std::path::Path::new(value);
+        if !path.is_file() {
+            return Err(CommandError::NotFound(format!("grammar file {}", value)).into());
+        }
+        let gbnf = crate::prompts::read_text_file(path, "grammar file")?;
+        *state.output_format = OutputFormat::Grammar(gbnf);
+        format!("Grammar loaded from {}", value)
+    };
+    state.session.metadata.output_format = state.output_format.clone();
+    Ok(CommandResult::Status(status))
 }
 
 fn cmd_temp(state: &mut ChatState, _args: &[&str]) -> Result<CommandResult> {
-    let emerald = "\x1b[38;5;48m";
-    let reset = "\x1b[0m";
+    let emerald = ansi(EMERALD);
+    let reset = ansi(RESET);
     println!("  {}Current temperature: {:.1}{}", emerald, state.temperature, reset);
     println!();
     Ok(CommandResult::Continue)
@@ -379,9 +1718,9 @@ fn cmd_sessions(state: &mut ChatState, _args: &[&str]) -> Result<CommandResult>
     let sessions = Session::list_sessions(&state.config.directories.sessions)?;
 
     println!();
-    let orange = "\x1b[38;5;208m";
-    let emerald = "\x1b[38;5;48m";
-    let reset = "\x1b[0m";
+    let orange = ansi(ORANGE);
+    let emerald = ansi(EMERALD);
+    let reset = ansi(RESET);
 
     let padding = "─".repeat(57_usize.saturating_sub("Available Sessions".len()));
     println!("{}┌─ {}Available Sessions{}{}{}", orange, emerald, orange, padding, reset);
@@ -400,31 +1739,174 @@ fn cmd_sessions(state: &mut ChatState, _args: &[&str]) -> Result<CommandResult>
             session.total_tokens);
     }
 
-    println!("{}{}{}", orange, "└─────────────────────────────────────────────────────────┘", reset);
+    println!("{}└─────────────────────────────────────────────────────────┘{}", orange, reset);
     println!();
     Ok(CommandResult::Continue)
 }
 
-fn cmd_load(_state: &mut ChatState, _args: &[&str]) -> Result<CommandResult> {
-    println!("  {}Note: To load a session, restart the application with that session in the Chat/sessions directory", "".bright_green());
-    Ok(CommandResult::Continue)
+// Save the current chat and start an empty session with the same settings,
+// optionally on another model. Unlike `/delete all`, the old conversation stays
+// on disk as its own session.
+fn cmd_new(state: &mut ChatState, args: &[&str]) -> Result<CommandResult> {
+    let model = match args.first() {
+        Some(name) if !state.model_registry.contains_key(*name) => {
+            return Err(CommandError::NotFound(format!("model {}", name)).into());
+        }
+        Some(name) => name.to_string(),
+        None => state.current_model.clone(),
+    };
+
+    let dir = &state.config.directories.sessions;
+    if !state.session.messages.is_empty() {
+        state.session.save(dir)?;
+    }
+
+    let mut fresh = Session::new(state.current_model.clone(), *state.temperature);
+    // Ids have one-second resolution; a label keeps a quick /new from reusing one
+    let mut suffix = 1;
+    while fresh.metadata.id == state.session.metadata.id || std::path::Path::new(dir).join(&fresh.metadata.id).exists() {
+        suffix += 1;
+        fresh.rename(suffix.to_string());
+    }
+    let old = &state.session.metadata;
+    let metadata = &mut fresh.metadata;
+    metadata.context_window = old.context_window;
+    metadata.top_p = old.top_p;
+    metadata.max_tokens = old.max_tokens;
+    metadata.candidate_count = old.candidate_count;
+    metadata.output_format = old.output_format.clone();
+    metadata.logit_bias = old.logit_bias.clone();
+    metadata.think = old.think;
+
+    let saved = (!state.session.messages.is_empty()).then(|| state.session.metadata.id.clone());
+    *state.session = fresh;
+    *state.undo_snapshot = None;
+    state.expanded_thinking.clear();
+    let status = match saved {
+        Some(old_id) => format!("Saved {}; new session {}", old_id, state.session.metadata.id),
+        None => format!("New session {}", state.session.metadata.id),
+    };
+
+    if model != *state.current_model {
+        return Ok(CommandResult::SwitchModel(model, Some(status)));
+    }
+    Ok(CommandResult::Status(status))
+}
+
+// Replace the chat with a saved session, picked by its /sessions number or id,
+// and restore the settings it was using. The current chat is saved first.
+fn cmd_load(state: &mut ChatState, args: &[&str]) -> Result<CommandResult> {
+    let Some(target) = args.first() else {
+        return Err(CommandError::BadArgument("missing session (see /sessions)".to_string()).into());
+    };
+    if state.is_loading {
+        return Ok(CommandResult::Error("Wait for the reply to finish before loading a session".to_string()));
+    }
+
+    let dir = &state.config.directories.sessions;
+    let id = match target.parse::<usize>() {
+        Ok(number) => {
+            let sessions = Session::list_sessions(dir)?;
+            let session = number.checked_sub(1).and_then(|i| sessions.get(i));
+            session
+                .map(|s| s.id.clone())
+                .ok_or_else(|| CommandError::OutOfRange(format!("session {} (there are {})", number, sessions.len())))?
+        }
+        Err(_) => target.to_string(),
+    };
+    if !std::path::Path::new(dir).join(&id).is_dir() {
+        return Err(CommandError::NotFound(format!("session {}", id)).into());
+    }
+    let loaded = Session::load(dir, &id)?;
+
+    if !state.session.messages.is_empty() {
+        state.session.save(dir)?;
+    }
+    *state.session = loaded;
+    *state.undo_snapshot = None;
+    state.expanded_thinking.clear();
+    let restored = restore_session_settings(state);
+    let mut status = format!("Loaded {} ({} messages): {}", id, state.session.messages.len(), restored.join(" · "));
+
+    // The session's model and context size need a server restart; the model
+    // is recorded on the session once that succeeds. A session saved without
+    // a size goes back to the profile's rather than keeping the last /set ctx.
+    let model = std::mem::replace(&mut state.session.metadata.model_name, state.current_model.clone());
+    let context_window = if state.config.llm.manage_server {
+        state.session.metadata.context_window
+    } else {
+        state.server.context_override
+    };
+    let new_context = state.server.context_override != context_window;
+    let known = state.model_registry.contains_key(&model);
+    if model != *state.current_model && !known {
+        status.push_str(&format!(" · model {} is no longer available, staying on {}", model, state.current_model));
+    }
+    if (model != *state.current_model && known) || new_context {
+        let target = if known { model } else { state.current_model.clone() };
+        return Ok(CommandResult::SwitchSession(target, context_window, status));
+    }
+    Ok(CommandResult::Status(status))
+}
+
+// Apply the sampling settings recorded on the (just loaded) session. Returns a
+// short description of each, for the confirmation line.
+fn restore_session_settings(state: &mut ChatState) -> Vec<String> {
+    let metadata = &mut state.session.metadata;
+    let range = state.config.temperature.min..=state.config.temperature.max;
+    if range.contains(&metadata.temperature) {
+        *state.temperature = metadata.temperature;
+    } else {
+        metadata.temperature = *state.temperature;
+    }
+    *state.top_p = metadata.top_p;
+    *state.max_tokens = metadata.max_tokens.unwrap_or(crate::llm_client::DEFAULT_MAX_TOKENS);
+    *state.candidate_count = metadata.candidate_count.unwrap_or(1);
+    *state.output_format = metadata.output_format.clone();
+    *state.logit_bias = metadata.logit_bias.clone();
+    *state.think = metadata.think;
+
+    let mut restored = vec![format!("temp {}", state.temperature)];
+    if let Some(top_p) = state.top_p {
+        restored.push(format!("top_p {}", top_p));
+    }
+    restored.push(format!("max_tokens {}", state.max_tokens));
+    if let Some(ctx) = metadata.context_window {
+        restored.push(format!("ctx {}", ctx));
+    }
+    if *state.candidate_count > 1 {
+        restored.push(format!("n {}", state.candidate_count));
+    }
+    match state.output_format {
+        OutputFormat::Text => {}
+        OutputFormat::Json => restored.push("format json".to_string()),
+        OutputFormat::Grammar(_) => restored.push("grammar".to_string()),
+    }
+    if !state.logit_bias.is_empty() {
+        restored.push(format!("{} token bias(es)", state.logit_bias.len()));
+    }
+    if !state.think.is_default() {
+        restored.push(format!("think {}", state.think.name()));
+    }
+    restored
 }
 
 fn cmd_rename(state: &mut ChatState, args: &[&str]) -> Result<CommandResult> {
     if args.is_empty() {
-        println!("  {}Usage: /rename <label>", "".bright_red());
-        return Ok(CommandResult::Continue);
+        return Err(CommandError::BadArgument("usage: /rename <label>".to_string()).into());
     }
 
     let label = args.join("_");
-    state.session.rename(label.clone());
+    let new_id = state.session.id_for_label(&label);
+    if new_id != state.session.metadata.id
+        && std::path::Path::new(&state.config.directories.sessions).join(&new_id).exists()
+    {
+        return Err(CommandError::BadArgument(format!("'{}': a session with that name already exists", label)).into());
+    }
+    state.session.rename(label);
     state.session.save(&state.config.directories.sessions)?;
 
-    println!("  {}✓ Session renamed to: {}",
-        "".bright_green(),
-        state.session.metadata.id);
-
-    Ok(CommandResult::Continue)
+    Ok(CommandResult::Status(format!("Session renamed to: {}", state.session.metadata.id)))
 }
 
 fn cmd_prompt(state: &mut ChatState, args: &[&str]) -> Result<CommandResult> {
@@ -433,51 +1915,86 @@ fn cmd_prompt(state: &mut ChatState, args: &[&str]) -> Result<CommandResult> {
     if args.is_empty() || args[0] == "list" {
         let prompts = pm.list_prompts()?;
 
-        println!();
-        let orange = "\x1b[38;5;208m";
-        let emerald = "\x1b[38;5;48m";
-        let reset = "\x1b[0m";
-
-        let padding = "─".repeat(57_usize.saturating_sub("Static Prompts".len()));
-        println!("{}┌─ {}Static Prompts{}{}{}", orange, emerald, orange, padding, reset);
-
+        let mut output = CommandOutput::new("Static Prompts");
         if prompts.is_empty() {
-            println!("{}  No prompts available", "  ".bright_green());
-        } else {
-            for (i, prompt) in prompts.iter().enumerate() {
-                println!("{}  [{}] {}",
-                    "  ".bright_green(),
-                    i + 1,
-                    prompt.bright_green());
-            }
+            output.line(Tone::Dim, "No prompts available");
         }
-
-        println!("{}{}{}", orange, "└─────────────────────────────────────────────────────────┘", reset);
-        println!();
-    } else {
-        let prompts = pm.list_prompts()?;
-        let idx: usize = args[0].parse::<usize>()? - 1;
-
-        if idx >= prompts.len() {
-            println!("  {}✗ Invalid prompt index", "".bright_red());
-            return Ok(CommandResult::Continue);
+        for (i, prompt) in prompts.iter().enumerate() {
+            output.spans(vec![(Tone::Dim, format!("[{}] ", i + 1)), (Tone::Label, prompt.clone())]);
         }
+        output.line(Tone::Dim, "");
+        output.line(Tone::Dim, "/prompt <n> to send · /prompt show <n> to preview");
+        Ok(CommandResult::Output(output))
+    } else if args[0] == "show" {
+        // Preview only; nothing is added to the session
+        let Some(number) = args.get(1) else {
+            return Err(CommandError::BadArgument("usage: /prompt show <id>".to_string()).into());
+        };
+        let (name, content) = select_prompt(&pm, number)?;
 
-        let content = pm.load_prompt(&prompts[idx])?;
-
-        println!();
-        println!("{}Loaded prompt: {}", "  ".bright_green(), prompts[idx]);
-        println!("{}{}", "  ".bright_green(), content);
-        println!();
+        let mut output = CommandOutput::new(format!("Prompt: {}", name));
+        for line in content.lines() {
+            output.line(Tone::Plain, line);
+        }
+        Ok(CommandResult::Output(output))
+    } else {
+        let (name, content) = select_prompt(&pm, args[0])?;
 
-        // Add to session as user message
+        // Add to session as user message; the chat shows its content
         state.session.add_message("user".to_string(), content, None);
+        Ok(CommandResult::Status(format!("Loaded prompt: {}", name)))
     }
+}
 
-    Ok(CommandResult::Continue)
+// Resolve a 1-based prompt number to its name and content
+fn select_prompt(pm: &PromptManager, number: &str) -> Result<(String, String)> {
+    let prompts = pm.list_prompts()?;
+    let number: usize = parse_arg(number, "prompt number")?;
+    if number == 0 || number > prompts.len() {
+        return Err(CommandError::OutOfRange(format!("prompt {} (1-{})", number, prompts.len())).into());
+    }
+    let name = prompts[number - 1].clone();
+    let content = pm.load_prompt(&name)?;
+    Ok((name, content))
 }
 
 // ============================================================================
 // HELPER FUNCTIONS
 // ============================================================================
 
+fn parse_arg<T: std::str::FromStr>(value: &str, what: &str) -> std::result::Result<T, CommandError> {
+    value
+        .parse()
+        .map_err(|_| CommandError::BadArgument(format!("{} '{}'", what, value)))
+}
+
+/// Parse a message id selection such as `5`, `3-7`, `3,5,8` or `1,4-6`.
+/// Only ids present in `session` are returned (so `1-999999999` stays cheap),
+/// sorted and de-duplicated; `None` if any part is malformed.
+fn parse_id_selection(spec: &str, session: &Session) -> Option<Vec<usize>> {
+    let mut ids = Vec::new();
+    let mut parts = 0;
+
+    for part in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        parts += 1;
+        let (start, end) = match part.split_once('-') {
+            Some((start, end)) => (start.trim().parse().ok()?, end.trim().parse().ok()?),
+            None => {
+                let id: usize = part.parse().ok()?;
+                (id, id)
+            }
+        };
+        if start > end {
+            return None;
+        }
+        ids.extend(session.messages.iter().map(|m| m.id).filter(|id| (start..=end).contains(id)));
+    }
+
+    if parts == 0 {
+        return None;
+    }
+
+    ids.sort_unstable();
+    ids.dedup();
+    Some(ids)
+}
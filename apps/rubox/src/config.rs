@@ -1,6 +1,47 @@
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+
+pub const MODELS_DIR: &str = "models";
+pub const CONFIG_FILE: &str = "rubox_config.json";
+pub const CONFIG_ENV: &str = "RUBOX_CONFIG";
+
+// Scan MODELS_DIR for .gguf files as (name, path) pairs, name being the file stem
+pub fn scan_models_dir() -> Vec<(String, String)> {
+    scan_models_in(Path::new(MODELS_DIR))
+}
+
+pub fn scan_models_in(dir: &Path) -> Vec<(String, String)> {
+    let mut models = Vec::new();
+
+    if let Ok(entries) = fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().and_then(|s| s.to_str()) == Some("gguf") {
+                if let Some(name) = path.file_stem().and_then(|n| n.to_str()) {
+                    models.push((name.to_string(), path.to_string_lossy().to_string()));
+                }
+            }
+        }
+    }
+
+    models.sort();
+    models
+}
+
+// Add scanned models to a registry without overriding configured entries.
+// Returns the number of models added.
+pub fn merge_scanned_models(registry: &mut std::collections::HashMap<String, String>) -> usize {
+    let mut added = 0;
+    for (name, path) in scan_models_dir() {
+        let is_configured = registry.contains_key(&name) || registry.values().any(|p| Path::new(p) == Path::new(&path));
+        if !is_configured {
+            registry.insert(name, path);
+            added += 1;
+        }
+    }
+    added
+}
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct RuboxConfig {
@@ -10,10 +51,20 @@ pub struct RuboxConfig {
     pub directories: DirectoriesConfig,
     pub cleanup: CleanupConfig,
     pub ui: UiConfig,
+    #[serde(default)]
+    pub theme: ThemeConfig,
+    #[serde(default)]
+    pub response: ResponseConfig,
     pub temperature: TemperatureConfig,
     pub session: SessionConfig,
+    #[serde(default)]
+    pub filenames: FilenamesConfig,
     #[serde(default = "ModelProfiles::default_profiles")]
     pub model_profiles: std::collections::HashMap<String, ModelParams>,
+    #[serde(skip)]
+    pub param_overrides: ParamOverrides,
+    #[serde(skip)]
+    pub source_path: Option<PathBuf>,  // File this config was loaded from (None: built-in defaults)
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -23,12 +74,54 @@ pub struct LlmConfig {
     pub base_temp: f32,
     pub max_temp: f32,
     pub context_window: u32,
+    #[serde(default = "LlmConfig::default_request_timeout")]
+    pub request_timeout_secs: u64,
+    #[serde(default = "LlmConfig::default_manage_server")]
+    pub manage_server: bool,  // false: api_url points at a server rubox doesn't start or stop
+    #[serde(default = "LlmConfig::default_server_start_timeout")]
+    pub server_start_timeout_secs: u64,  // How long a freshly spawned llama-server may take to load
+    #[serde(default)]
+    pub response_cache: bool,  // Reuse temperature 0 replies to identical requests from disk
+    #[serde(default)]
+    pub endpoints: std::collections::HashMap<String, String>,  // Model name -> API URL of a remote server serving it
+    #[serde(default = "LlmConfig::default_multi_model_concurrency")]
+    pub multi_model_concurrency: usize,  // Remote models asked at once in multi-model runs
+    #[serde(default = "LlmConfig::default_multi_model_retries")]
+    pub multi_model_retries: u32,  // Extra attempts for a model whose request failed
+    #[serde(default)]
+    pub keep_server: bool,  // Leave the spawned llama-server running on exit (--keep-server)
+}
+
+impl LlmConfig {
+    // Seconds a completion may wait on the server: the whole reply without
+    // streaming, each gap between chunks with it. 0 disables the timeout.
+    fn default_request_timeout() -> u64 {
+        300
+    }
+
+    fn default_manage_server() -> bool {
+        true
+    }
+
+    fn default_server_start_timeout() -> u64 {
+        180
+    }
+
+    fn default_multi_model_concurrency() -> usize {
+        4
+    }
+
+    fn default_multi_model_retries() -> u32 {
+        1
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ModelsConfig {
     pub default: String,
     pub registry: std::collections::HashMap<String, String>,
+    #[serde(default)]
+    pub models_root: Option<String>,  // Base for relative model paths (default: the config file's directory)
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -60,6 +153,141 @@ pub struct UiConfig {
     pub color_bright_red: String,
     pub color_white: String,
     pub color_reset: String,
+    #[serde(default = "UiConfig::default_spinner")]
+    pub spinner: String,  // braille, dots, bar or moon
+    #[serde(default = "UiConfig::default_tick_ms")]
+    pub tick_ms: u64,
+    #[serde(default = "UiConfig::default_layout")]
+    pub layout: String,  // auto, compact or full; Ctrl+L toggles at runtime
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub assistant_name: Option<String>,  // Persona name on every reply, whatever the model
+    #[serde(default)]
+    pub suggestions: bool,  // Ask for follow-up prompts after each reply (one extra request)
+    #[serde(default)]
+    pub typewriter_cps: u32,  // Reveal replies at this many characters per second; 0 shows them at once
+    #[serde(default)]
+    pub transcript_on_exit: bool,  // Print the conversation to the normal screen when the chat ends
+    #[serde(default = "UiConfig::default_chat_percent")]
+    pub chat_percent: u16,  // Share of the full layout given to the chat history (CHAT_PERCENT_RANGE)
+    #[serde(default = "UiConfig::default_input_min_rows")]
+    pub input_min_rows: u16,  // Rows the full layout keeps for the input at least (INPUT_MIN_ROWS_RANGE)
+}
+
+// Accepted ui.chat_percent and ui.input_min_rows; outside them the defaults apply
+pub const CHAT_PERCENT_RANGE: std::ops::RangeInclusive<u16> = 20..=85;
+pub const INPUT_MIN_ROWS_RANGE: std::ops::RangeInclusive<u16> = 3..=30;
+
+// Settings read only when llama-server, the HTTP client or the event loop
+// starts. A /reload can't apply them to the running chat.
+pub const RESTART_SETTINGS: [&str; 11] = [
+    "models",
+    "model_profiles",
+    "llm.api_url",
+    "llm.model_name",
+    "llm.context_window",
+    "llm.request_timeout_secs",
+    "llm.manage_server",
+    "llm.server_start_timeout_secs",
+    "llm.response_cache",
+    "llm.keep_server",
+    "ui.tick_ms",
+];
+
+// Settings that differ between two configs, named "section.key" ("models" and
+// "model_profiles" as a whole), split into (applied at once, needing a restart)
+pub fn config_changes(old: &RuboxConfig, new: &RuboxConfig) -> (Vec<String>, Vec<String>) {
+    use serde_json::Value;
+    let (Ok(Value::Object(old)), Ok(Value::Object(new))) = (serde_json::to_value(old), serde_json::to_value(new)) else {
+        return (Vec::new(), Vec::new());
+    };
+
+    let mut changed = Vec::new();
+    for (section, value) in &new {
+        match (old.get(section), value) {
+            (Some(before), after) if before == after => {}
+            (Some(Value::Object(before)), Value::Object(after)) if !matches!(section.as_str(), "models" | "model_profiles") => {
+                let mut keys: Vec<&String> = before.keys().chain(after.keys()).collect();
+                keys.sort();
+                keys.dedup();
+                changed.extend(
+                    keys.into_iter()
+                        .filter(|key| before.get(*key) != after.get(*key))
+                        .map(|key| format!("{}.{}", section, key)),
+                );
+            }
+            _ => changed.push(section.clone()),
+        }
+    }
+    changed.into_iter().partition(|name| !RESTART_SETTINGS.contains(&name.as_str()))
+}
+
+impl UiConfig {
+    fn default_spinner() -> String {
+        "braille".to_string()
+    }
+
+    fn default_tick_ms() -> u64 {
+        250
+    }
+
+    fn default_layout() -> String {
+        "auto".to_string()
+    }
+
+    pub fn default_chat_percent() -> u16 {
+        60
+    }
+
+    pub fn default_input_min_rows() -> u16 {
+        10
+    }
+}
+
+// TUI palette as hex strings ("#RRGGBB"), parsed into colors at startup
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct ThemeConfig {
+    pub user: String,
+    pub assistant: String,
+    pub accent: String,
+    pub dim: String,
+    pub border: String,
+    pub code: String,
+    pub error: String,
+}
+
+impl Default for ThemeConfig {
+    fn default() -> Self {
+        ThemeConfig {
+            user: "#FF8700".to_string(),
+            assistant: "#00FF87".to_string(),
+            accent: "#FF8700".to_string(),
+            dim: "#808080".to_string(),
+            border: "#3C3C3C".to_string(),
+            code: "#00FFFF".to_string(),
+            error: "#FF0000".to_string(),
+        }
+    }
+}
+
+// Cleanup of assistant replies before they are stored and shown (see
+// postprocess.rs); turn everything off to keep the model's raw output
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct ResponseConfig {
+    pub trim_trailing_whitespace: bool,  // Strip spaces at line ends and blank lines at the end
+    pub close_code_fences: bool,         // Close a ``` block the model left open
+    pub collapse_blank_lines: bool,      // Squeeze runs of blank lines outside code into one
+}
+
+impl Default for ResponseConfig {
+    fn default() -> Self {
+        ResponseConfig {
+            trim_trailing_whitespace: true,
+            close_code_fences: true,
+            collapse_blank_lines: false,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -74,6 +302,128 @@ pub struct TemperatureConfig {
 pub struct SessionConfig {
     pub auto_save: bool,
     pub format: String,
+    #[serde(default = "SessionConfig::default_auto_save_interval")]
+    pub auto_save_interval: u32,  // Save after every N assistant responses
+    #[serde(default = "SessionConfig::default_confirm_destructive")]
+    pub confirm_destructive: bool,  // Ask before wiping the whole conversation
+    #[serde(default)]
+    pub strip_thinking: bool,  // Drop <think> blocks from /save and /export output
+    #[serde(default = "SessionConfig::default_thinking_sidecar")]
+    pub thinking_sidecar: bool,  // With strip_thinking, write the reasoning to <file>.thinking.txt
+    #[serde(default)]
+    pub command_log: bool,  // Record every slash-command to <chat>/command_log.tsv
+    #[serde(default = "SessionConfig::default_command_log_max_kb")]
+    pub command_log_max_kb: u64,  // Rotate the command log once it grows past this
+    #[serde(default)]
+    pub max_messages: usize,  // Archive older messages past this many; 0 = unlimited
+}
+
+impl SessionConfig {
+    fn default_auto_save_interval() -> u32 {
+        1
+    }
+
+    fn default_confirm_destructive() -> bool {
+        true
+    }
+
+    fn default_thinking_sidecar() -> bool {
+        true
+    }
+
+    fn default_command_log_max_kb() -> u64 {
+        256
+    }
+}
+
+// Output file names as templates, without the extension. Tokens are replaced
+// with filename-safe values; the defaults reproduce the historical names.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct FilenamesConfig {
+    pub results: String,        // Multi-model results in directories.output
+    pub prompt: String,         // Multi-model prompt copy in directories.prompts
+    pub response: String,       // Per-model response in directories.tmp_md
+    pub saved_message: String,  // /save <id> in directories.saved_responses
+    pub export: String,         // /export html in directories.saved_responses
+}
+
+impl Default for FilenamesConfig {
+    fn default() -> Self {
+        FilenamesConfig {
+            results: "Results_{timestamp}".to_string(),
+            prompt: "Prompt_{timestamp}".to_string(),
+            response: "{model}_{timestamp}".to_string(),
+            saved_message: "{session}_{id}".to_string(),
+            export: "{session}".to_string(),
+        }
+    }
+}
+
+pub const FILENAME_TOKENS: [&str; 4] = ["model", "timestamp", "session", "id"];
+pub const FILENAME_TIMESTAMP: &str = "%d_%m_%Y_%H_%M_%S";  // Format of the {timestamp} token
+const ILLEGAL_FILENAME_CHARS: [char; 9] = ['/', '\\', ':', '*', '?', '"', '<', '>', '|'];
+
+impl FilenamesConfig {
+    // Each template may only use the tokens known where it is rendered, and
+    // must carry the ones that keep its files from overwriting one another
+    // (several models share one timestamp in a multi-model run)
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let templates: [(&str, &str, &[&str], &[&str]); 5] = [
+            ("results", &self.results, &["timestamp"], &["timestamp"]),
+            ("prompt", &self.prompt, &["timestamp"], &["timestamp"]),
+            ("response", &self.response, &["model", "timestamp"], &["model", "timestamp"]),
+            ("saved_message", &self.saved_message, &FILENAME_TOKENS, &["session", "id"]),
+            ("export", &self.export, &["model", "timestamp", "session"], &["session"]),
+        ];
+        for (name, template, allowed, required) in templates {
+            validate_filename_template(template, allowed, required)
+                .map_err(|e| anyhow::anyhow!("Invalid filenames.{} \"{}\": {}", name, template, e))?;
+        }
+        Ok(())
+    }
+}
+
+pub fn validate_filename_template(template: &str, allowed: &[&str], required: &[&str]) -> anyhow::Result<()> {
+    if template.trim().is_empty() {
+        anyhow::bail!("template is empty");
+    }
+    if let Some(c) = template.chars().find(|c| ILLEGAL_FILENAME_CHARS.contains(c) || c.is_control()) {
+        anyhow::bail!("illegal character {:?}", c);
+    }
+
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let Some(len) = rest[start..].find('}') else {
+            anyhow::bail!("unclosed '{{'");
+        };
+        let token = &rest[start + 1..start + len];
+        if !allowed.contains(&token) {
+            anyhow::bail!("unsupported token {{{}}} (expected one of {{{}}})", token, allowed.join("}, {"));
+        }
+        rest = &rest[start + len + 1..];
+    }
+
+    for token in required {
+        if !template.contains(&format!("{{{}}}", token)) {
+            anyhow::bail!("missing required token {{{}}}", token);
+        }
+    }
+    Ok(())
+}
+
+// Fill a validated template. Values are made filename-safe, so a model or
+// session name can't smuggle a path separator into the result.
+pub fn render_filename(template: &str, values: &[(&str, &str)]) -> String {
+    let mut name = template.to_string();
+    for (token, value) in values {
+        let safe: String = value
+            .chars()
+            .map(|c| if ILLEGAL_FILENAME_CHARS.contains(&c) || c.is_control() { '_' } else { c })
+            .collect();
+        name = name.replace(&format!("{{{}}}", token), &safe);
+    }
+    name
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -85,6 +435,32 @@ pub struct ModelParams {
     pub mmproj: Option<String>, // Vision model projection file
     pub cache_type_k: Option<String>,
     pub cache_type_v: Option<String>,
+    // llama-server slots (--parallel). The context window is split across
+    // slots, so each extra slot costs KV cache; a single user rarely needs more than 1-4.
+    #[serde(default = "ModelParams::default_parallel")]
+    pub parallel: u32,
+    // Advanced overrides, unset by default so the server decides. Use them when
+    // a GGUF ships a wrong or missing template or the model runs past its turn.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chat_template: Option<String>,  // Passed as --chat-template (e.g. "chatml", "llama3")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stop: Option<Vec<String>>,  // Extra stop strings sent with every completion
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,  // Shown in the chat and results instead of the registry key
+}
+
+impl ModelParams {
+    fn default_parallel() -> u32 {
+        4
+    }
+}
+
+// Launch-time overrides from CLI flags; applied on top of every model profile
+#[derive(Debug, Clone, Default)]
+pub struct ParamOverrides {
+    pub gpu_layers: Option<i32>,
+    pub context_window: Option<u32>,
+    pub batch_size: Option<u32>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -107,6 +483,10 @@ impl ModelProfiles {
                 mmproj: Some("../../models/mmproj-Qwen3VL-8B-Instruct-F16.gguf".to_string()),
                 cache_type_k: None,
                 cache_type_v: None,
+                parallel: ModelParams::default_parallel(),
+                chat_template: None,
+                stop: None,
+                display_name: None,
             },
         );
 
@@ -121,6 +501,10 @@ impl ModelProfiles {
                 mmproj: None,
                 cache_type_k: None,
                 cache_type_v: None,
+                parallel: ModelParams::default_parallel(),
+                chat_template: None,
+                stop: None,
+                display_name: None,
             },
         );
 
@@ -135,6 +519,10 @@ impl ModelProfiles {
                 mmproj: None,
                 cache_type_k: None,
                 cache_type_v: None,
+                parallel: ModelParams::default_parallel(),
+                chat_template: None,
+                stop: None,
+                display_name: None,
             },
         );
 
@@ -151,6 +539,10 @@ impl ModelProfiles {
                 mmproj: None,         // Text-only model
                 cache_type_k: None,
                 cache_type_v: None,
+                parallel: 1,          // Single user; keep the full context in one slot
+                chat_template: None,
+                stop: None,
+                display_name: None,
             },
         );
 
@@ -165,6 +557,10 @@ impl ModelProfiles {
                 mmproj: None,
                 cache_type_k: Some("q4_0".to_string()),
                 cache_type_v: Some("q4_0".to_string()),
+                parallel: ModelParams::default_parallel(),
+                chat_template: None,
+                stop: None,
+                display_name: None,
             },
         );
 
@@ -172,22 +568,140 @@ impl ModelProfiles {
     }
 }
 
+// Built-in defaults, as used when no config file is found
+impl Default for RuboxConfig {
+    fn default() -> Self {
+        Self::default_internal()
+    }
+}
+
 impl RuboxConfig {
-    pub fn load() -> Self {
-        let config_path = "rubox_config.json";
-        if Path::new(config_path).exists() {
-            let content = fs::read_to_string(config_path).expect("Failed to read config");
-            serde_json::from_str(&content).unwrap_or_else(|_| Self::default_internal())
-        } else {
-            Self::default_internal()
+    // Lookup order: --config, $RUBOX_CONFIG, ./rubox_config.json, then
+    // <platform config dir>/rubox/config.json. An explicitly given path must exist.
+    pub fn locate(cli_path: Option<&Path>) -> anyhow::Result<Option<PathBuf>> {
+        let explicit = cli_path
+            .map(|p| (p.to_path_buf(), "--config"))
+            .or_else(|| std::env::var_os(CONFIG_ENV).map(|p| (PathBuf::from(p), CONFIG_ENV)));
+        if let Some((path, source)) = explicit {
+            if !path.is_file() {
+                anyhow::bail!("Config file from {} not found: {}", source, path.display());
+            }
+            return Ok(Some(path));
+        }
+
+        let local = PathBuf::from(CONFIG_FILE);
+        if local.is_file() {
+            return Ok(Some(local));
         }
+        Ok(dirs::config_dir()
+            .map(|dir| dir.join("rubox").join("config.json"))
+            .filter(|path| path.is_file()))
     }
 
-    pub fn get_model_params(&self, model_name: &str) -> ModelParams {
+    pub fn load(cli_path: Option<&Path>) -> anyhow::Result<Self> {
+        let Some(config_path) = Self::locate(cli_path)? else {
+            return Ok(Self::default_internal());
+        };
+        let mut config = Self::read_file(&config_path)?;
+        config.source_path = Some(config_path);
+        config.filenames.validate()?;
+        config.resolve_model_paths()?;
+        Ok(config)
+    }
+
+    // Re-read the file this config came from for /reload. A file that can't be
+    // read, parsed or validated is an error, so the running config is kept.
+    pub fn reload(&self) -> anyhow::Result<Self> {
+        let Some(config_path) = self.source_path.clone().or(Self::locate(None)?) else {
+            anyhow::bail!("no config file to reload (running on built-in defaults)");
+        };
+        let mut config = Self::read_file(&config_path)?;
+        config.source_path = Some(config_path);
+        config.param_overrides = self.param_overrides.clone();
+        config.filenames.validate()?;
+        config.resolve_model_paths()?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    // A file that can't be read or parsed is an error naming it; falling back
+    // to defaults would leave `source_path` pointing at a config never loaded
+    fn read_file(path: &Path) -> anyhow::Result<Self> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read config {}: {}", path.display(), e))?;
+        serde_json::from_str(&content)
+            .map_err(|e| anyhow::anyhow!("Invalid config {}: {}", path.display(), e))
+    }
+
+    // Value checks for a config replacing a running one; at startup the UI
+    // falls back to defaults instead
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let t = &self.temperature;
+        if !(0.0 <= t.min && t.min <= t.default && t.default <= t.max) {
+            anyhow::bail!(
+                "temperature needs 0 <= min <= default <= max (got min {}, default {}, max {})",
+                t.min, t.default, t.max
+            );
+        }
+        if !CHAT_PERCENT_RANGE.contains(&self.ui.chat_percent) {
+            anyhow::bail!(
+                "ui.chat_percent {} is outside {}-{}",
+                self.ui.chat_percent, CHAT_PERCENT_RANGE.start(), CHAT_PERCENT_RANGE.end()
+            );
+        }
+        if !INPUT_MIN_ROWS_RANGE.contains(&self.ui.input_min_rows) {
+            anyhow::bail!(
+                "ui.input_min_rows {} is outside {}-{}",
+                self.ui.input_min_rows, INPUT_MIN_ROWS_RANGE.start(), INPUT_MIN_ROWS_RANGE.end()
+            );
+        }
+        Ok(())
+    }
+
+    // Make relative model and mmproj paths absolute, anchored at models_root or
+    // the config file's directory, so launching from another CWD still works
+    fn resolve_model_paths(&mut self) -> anyhow::Result<()> {
+        let Some(config_path) = &self.source_path else {
+            return Ok(());
+        };
+        let config_dir = match config_path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let config_dir = std::path::absolute(config_dir)?;
+        let base = match &self.models.models_root {
+            Some(root) => config_dir.join(root),
+            None => config_dir,
+        };
+
+        let resolve = |path: &mut String| {
+            if Path::new(path.as_str()).is_relative() {
+                *path = base.join(path.as_str()).to_string_lossy().into_owned();
+            }
+        };
+        resolve(&mut self.models.default);
+        self.models.registry.values_mut().for_each(resolve);
+        for params in self.model_profiles.values_mut() {
+            if let Some(mmproj) = params.mmproj.as_mut() {
+                resolve(mmproj);
+            }
+        }
+        Ok(())
+    }
+
+    // A profile's display_name, else the registry key itself
+    pub fn display_name(&self, model_name: &str) -> String {
         self.model_profiles
+            .get(model_name)
+            .and_then(|params| params.display_name.clone())
+            .unwrap_or_else(|| model_name.to_string())
+    }
+
+    pub fn get_model_params(&self, model_name: &str) -> ModelParams {
+        let mut params = self.model_profiles
             .get(model_name)
             .cloned()
-            .unwrap_or_else(|| ModelParams {
+            .unwrap_or(ModelParams {
                 batch_size: 512,
                 ubatch_size: 256,
                 gpu_layers: 35,
@@ -195,7 +709,62 @@ impl RuboxConfig {
                 mmproj: None,
                 cache_type_k: None,
                 cache_type_v: None,
-            })
+                parallel: ModelParams::default_parallel(),
+                chat_template: None,
+                stop: None,
+                display_name: None,
+            });
+
+        if let Some(gpu_layers) = self.param_overrides.gpu_layers {
+            params.gpu_layers = gpu_layers;
+        }
+        if let Some(context_window) = self.param_overrides.context_window {
+            params.context_window = context_window;
+        }
+        if let Some(batch_size) = self.param_overrides.batch_size {
+            params.batch_size = batch_size;
+        }
+
+        params
+    }
+
+    // Registry entries resolve to their configured path; anything else is
+    // looked up as models/<name>.gguf
+    pub fn model_path_exists(&self, model_name: &str) -> bool {
+        match self.models.registry.get(model_name) {
+            Some(path) => Path::new(path).exists(),
+            None => Path::new(MODELS_DIR).join(format!("{}.gguf", model_name)).exists(),
+        }
+    }
+
+    // Registry names, then .gguf files in `models_dir` the registry doesn't already name
+    pub fn available_models(&self, models_dir: &Path) -> Vec<String> {
+        let mut models: Vec<String> = self.models.registry.keys().cloned().collect();
+        models.sort();
+        for (name, _) in scan_models_in(models_dir) {
+            if !models.contains(&name) {
+                models.push(name);
+            }
+        }
+        models
+    }
+
+    // Startup check for a managed server: with no model anywhere there is
+    // nothing to launch, so say how to add one instead of failing later
+    pub fn require_models(&self, models_dir: &Path) -> anyhow::Result<Vec<String>> {
+        let models = self.available_models(models_dir);
+        if models.is_empty() {
+            let reason = if models_dir.is_dir() { "contains no .gguf files" } else { "does not exist" };
+            anyhow::bail!(
+                "No models available: models.registry is empty and {}/ {}. \
+                 Place a .gguf file in {}/ or add a \"name\": \"path/to/model.gguf\" entry to models.registry in {}.",
+                models_dir.display(),
+                reason,
+                models_dir.display(),
+                self.source_path.as_deref().map_or(CONFIG_FILE.into(), Path::to_string_lossy)
+            );
+        }
+        Ok(models)
     }
 
     fn default_internal() -> Self {
@@ -228,10 +797,19 @@ impl RuboxConfig {
                 base_temp: 0.7,
                 max_temp: 0.9,
                 context_window: 8192,
+                request_timeout_secs: LlmConfig::default_request_timeout(),
+                manage_server: LlmConfig::default_manage_server(),
+                server_start_timeout_secs: LlmConfig::default_server_start_timeout(),
+                response_cache: false,
+                endpoints: std::collections::HashMap::new(),
+                multi_model_concurrency: LlmConfig::default_multi_model_concurrency(),
+                multi_model_retries: LlmConfig::default_multi_model_retries(),
+                keep_server: false,
             },
             models: ModelsConfig {
                 default: "../../models/Qwen3-VL-8B-Instruct-UD-Q6_K_XL.gguf".to_string(),
                 registry,
+                models_root: None,
             },
             user: UserConfig {
                 name: "Arto".to_string(),
@@ -253,7 +831,18 @@ impl RuboxConfig {
                 color_bright_red: "\x1b[38;5;9m".to_string(),
                 color_white: "\x1b[37m".to_string(),
                 color_reset: "\x1b[0m".to_string(),
+                spinner: UiConfig::default_spinner(),
+                tick_ms: UiConfig::default_tick_ms(),
+                layout: UiConfig::default_layout(),
+                assistant_name: None,
+                suggestions: false,
+                typewriter_cps: 0,
+                transcript_on_exit: false,
+                chat_percent: UiConfig::default_chat_percent(),
+                input_min_rows: UiConfig::default_input_min_rows(),
             },
+            theme: ThemeConfig::default(),
+            response: ResponseConfig::default(),
             temperature: TemperatureConfig {
                 default: 0.7,
                 min: 0.0,
@@ -263,8 +852,18 @@ impl RuboxConfig {
             session: SessionConfig {
                 auto_save: true,
                 format: "json".to_string(),
+                auto_save_interval: SessionConfig::default_auto_save_interval(),
+                confirm_destructive: SessionConfig::default_confirm_destructive(),
+                strip_thinking: false,
+                thinking_sidecar: SessionConfig::default_thinking_sidecar(),
+                command_log: false,
+                command_log_max_kb: SessionConfig::default_command_log_max_kb(),
+                max_messages: 0,
             },
+            filenames: FilenamesConfig::default(),
             model_profiles: ModelProfiles::default_profiles(),
+            param_overrides: ParamOverrides::default(),
+            source_path: None,
         }
     }
 }
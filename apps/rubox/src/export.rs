@@ -0,0 +1,132 @@
+use pulldown_cmark::{html, Options, Parser};
+use crate::session::{ChatMessage, Session};
+
+const HTML_STYLE: &str = r#"
+body { font-family: -apple-system, "Segoe UI", Helvetica, Arial, sans-serif; background: #1e1e1e; color: #e0e0e0; max-width: 860px; margin: 2rem auto; padding: 0 1rem; line-height: 1.55; }
+header { border-bottom: 1px solid #3c3c3c; margin-bottom: 1.5rem; }
+header h1 { color: #ff8700; margin-bottom: 0.25rem; }
+header p { color: #808080; margin-top: 0; }
+section.message { border-left: 3px solid #3c3c3c; padding: 0.25rem 1rem; margin: 1.25rem 0; }
+section.user { border-color: #ff8700; }
+section.assistant { border-color: #00ff87; }
+.role { font-weight: bold; }
+section.user .role { color: #ff8700; }
+section.assistant .role { color: #00ff87; }
+.meta { color: #808080; font-size: 0.85em; margin-left: 0.5rem; }
+pre { background: #111; border: 1px solid #3c3c3c; border-radius: 4px; padding: 0.75rem; overflow-x: auto; }
+code { font-family: "JetBrains Mono", Menlo, Consolas, monospace; color: #00ffff; }
+a { color: #00ffff; }
+blockquote { border-left: 3px solid #808080; margin-left: 0; padding-left: 1rem; color: #b0b0b0; }
+"#;
+
+// Render a session as a self-contained HTML page (inline CSS, no external assets).
+// With `strip_thinking`, assistant <think> blocks are left out.
+pub fn session_to_html(session: &Session, user_name: &str, strip_thinking: bool) -> String {
+    let title = session.metadata.label.as_deref().unwrap_or(&session.metadata.id);
+
+    let mut out = String::new();
+    out.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+    out.push_str(&format!("<title>{}</title>\n", escape_html(title)));
+    out.push_str(&format!("<style>{}</style>\n</head>\n<body>\n", HTML_STYLE));
+
+    out.push_str(&format!(
+        "<header>\n<h1>{}</h1>\n<p>{} · {} messages · created {}</p>\n</header>\n",
+        escape_html(title),
+        escape_html(&session.metadata.model_name),
+        session.messages.len(),
+        session.metadata.created_at.format("%Y-%m-%d %H:%M UTC"),
+    ));
+
+    for msg in &session.messages {
+        let role_label = if msg.role == "user" {
+            user_name
+        } else {
+            msg.model.as_deref().unwrap_or(&session.metadata.model_name)
+        };
+
+        out.push_str(&format!("<section class=\"message {}\">\n", escape_html(&msg.role)));
+        out.push_str(&format!(
+            "<div><span class=\"role\">{}</span><span class=\"meta\">#{} · {}</span></div>\n",
+            escape_html(role_label),
+            msg.id,
+            msg.timestamp.format("%H:%M:%S"),
+        ));
+        let content = if strip_thinking && msg.role == "assistant" {
+            crate::session::split_thinking(&msg.content).1
+        } else {
+            msg.content.clone()
+        };
+        out.push_str(&markdown_to_html(&content));
+        out.push_str("</section>\n");
+    }
+
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+// How a session is split into JSONL records
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JsonlLayout {
+    Conversation,  // One line holding the whole conversation
+    Pairs,         // One line per user/assistant exchange
+}
+
+// Render a session as fine-tuning data: JSONL lines of OpenAI-style
+// `{"messages": [...]}` objects. Only complete exchanges are written; an
+// exchange is dropped when `keep` rejects either of its messages, so a
+// conversation never ends up with two user turns in a row. System messages
+// lead every record.
+pub fn session_to_jsonl(
+    session: &Session,
+    layout: JsonlLayout,
+    strip_thinking: bool,
+    keep: impl Fn(&ChatMessage) -> bool,
+) -> String {
+    let entry = |msg: &ChatMessage| {
+        let content = if strip_thinking && msg.role == "assistant" {
+            crate::session::split_thinking(&msg.content).1
+        } else {
+            msg.content.clone()
+        };
+        serde_json::json!({ "role": msg.role, "content": content })
+    };
+
+    let system: Vec<_> = session.messages.iter().filter(|m| m.role == "system").map(entry).collect();
+    let exchanges: Vec<[serde_json::Value; 2]> = session
+        .messages
+        .iter()
+        .filter(|m| m.role != "system")
+        .collect::<Vec<_>>()
+        .windows(2)
+        .filter(|pair| pair[0].role == "user" && pair[1].role == "assistant")
+        .filter(|pair| keep(pair[0]) && keep(pair[1]))
+        .map(|pair| [entry(pair[0]), entry(pair[1])])
+        .collect();
+
+    let record = |turns: Vec<serde_json::Value>| {
+        let messages: Vec<_> = system.iter().cloned().chain(turns).collect();
+        serde_json::json!({ "messages": messages }).to_string() + "\n"
+    };
+    match layout {
+        JsonlLayout::Conversation if exchanges.is_empty() => String::new(),
+        JsonlLayout::Conversation => record(exchanges.into_iter().flatten().collect()),
+        JsonlLayout::Pairs => exchanges.into_iter().map(|pair| record(pair.into())).collect(),
+    }
+}
+
+fn markdown_to_html(text: &str) -> String {
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_TABLES);
+    options.insert(Options::ENABLE_STRIKETHROUGH);
+
+    let mut rendered = String::new();
+    html::push_html(&mut rendered, Parser::new_ext(text, options));
+    rendered
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
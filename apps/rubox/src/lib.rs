@@ -0,0 +1,142 @@
+// Core chat logic, usable without the TUI. The `rubox` binary is a thin
+// frontend over this crate; `Rubox` is the entry point for other programs.
+
+pub mod command_log;
+pub mod commands;
+pub mod config;
+pub mod export;
+pub mod llm_client;
+pub mod logging;
+pub mod postprocess;
+pub mod prompts;
+pub mod response_cache;
+pub mod router;
+pub mod server_manager;
+pub mod session;
+pub mod state;
+
+// Frontends used by the binary
+pub mod batch;
+pub mod chat;
+pub mod multi_model;
+pub mod tui;
+pub mod ui;
+
+use std::time::Instant;
+
+pub use commands::CommandRegistry;
+pub use config::RuboxConfig;
+pub use llm_client::{LlmClient, OutputFormat, Usage};
+pub use server_manager::ServerManager;
+pub use session::Session;
+
+// A completed assistant turn
+#[derive(Debug, Clone)]
+pub struct Response {
+    pub content: String,
+    pub usage: Option<Usage>,
+    pub elapsed_secs: f32,
+}
+
+// One conversation against one server: owns the session, the HTTP client and,
+// when `llm.manage_server` is set, the llama-server process.
+pub struct Rubox {
+    config: RuboxConfig,
+    client: LlmClient,
+    server: ServerManager,
+    session: Session,
+}
+
+impl Rubox {
+    pub fn new(config: RuboxConfig) -> Self {
+        let client = LlmClient::new(&config);
+        let session = Session::new(config.llm.model_name.clone(), config.temperature.default);
+        Rubox {
+            config,
+            client,
+            server: ServerManager::new(),
+            session,
+        }
+    }
+
+    pub fn config(&self) -> &RuboxConfig {
+        &self.config
+    }
+
+    pub fn session(&self) -> &Session {
+        &self.session
+    }
+
+    pub fn session_mut(&mut self) -> &mut Session {
+        &mut self.session
+    }
+
+    pub fn set_temperature(&mut self, temperature: f32) {
+        self.session.metadata.temperature = temperature;
+    }
+
+    pub fn set_output_format(&mut self, format: OutputFormat) {
+        self.client.output_format = format;
+    }
+
+    // Start (or restart) the server on `name`, a registry name or a .gguf path.
+    // Externally-managed servers are left alone; only the session is updated.
+    // Either way requests name the new model, which keeps cached replies from
+    // one model from answering another.
+    pub async fn switch_model(&mut self, name: &str) -> anyhow::Result<()> {
+        if self.config.llm.manage_server {
+            self.server.stop()?;
+            self.server.ensure_running(&self.config, Some(name)).await?;
+        }
+        self.client.model_name = name.to_string();
+        self.session.metadata.model_name = name.to_string();
+        Ok(())
+    }
+
+    // Append `prompt` to the session, wait for the full reply and record it.
+    // When the request fails the prompt is taken back out, so a retry doesn't
+    // leave it in the history twice.
+    pub async fn send(&mut self, prompt: &str) -> anyhow::Result<Response> {
+        self.session.add_message("user".to_string(), prompt.to_string(), None);
+        let prompt_id = self.session.metadata.next_id - 1;
+
+        let messages = self
+            .session
+            .messages
+            .iter()
+            .map(|m| llm_client::ChatMessage {
+                role: m.role.clone(),
+                content: m.content.clone(),
+            })
+            .collect();
+
+        self.client.stop = self
+            .config
+            .get_model_params(&self.session.metadata.model_name)
+            .stop
+            .unwrap_or_default();
+
+        let start = Instant::now();
+        let reply = self
+            .client
+            .chat_completion_with_usage(messages, self.session.metadata.temperature)
+            .await;
+        let (content, usage) = match reply {
+            Ok(reply) => reply,
+            Err(e) => {
+                let _ = self.session.delete_message(prompt_id);
+                return Err(e);
+            }
+        };
+        let elapsed_secs = start.elapsed().as_secs_f32();
+
+        let tokens = usage.as_ref().map(|u| u.completion_tokens);
+        self.session.add_message("assistant".to_string(), content.clone(), tokens);
+
+        Ok(Response {
+            content,
+            usage,
+            elapsed_secs,
+        })
+    }
+}
@@ -1,6 +1,9 @@
 use reqwest::Client;
+use std::collections::HashMap;
+use std::time::Duration;
 use serde::{Deserialize, Serialize};
 use crate::config::RuboxConfig;
+use crate::response_cache::ResponseCache;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ChatMessage {
@@ -8,22 +11,132 @@ pub struct ChatMessage {
     pub content: String,
 }
 
+// Constraint on the shape of generated output
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,             // response_format: { type: "json_object" }
+    Grammar(String),  // GBNF grammar source
+}
+
+impl OutputFormat {
+    pub fn is_text(&self) -> bool {
+        *self == OutputFormat::Text
+    }
+}
+
+// How much a reasoning model should think before answering (/set think).
+// Sent as `reasoning_effort` and, since most chat templates ignore that, also
+// approximated with an instruction on the last user message: Qwen3's
+// `/no_think` switch for off, a request to keep it short or be thorough for
+// low and high. Off also asks the template to skip the think block.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThinkLevel {
+    #[default]
+    Default,  // Nothing sent; the model decides
+    Off,
+    Low,
+    Medium,
+    High,
+}
+
+impl ThinkLevel {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "default" => Some(ThinkLevel::Default),
+            "off" | "none" => Some(ThinkLevel::Off),
+            "low" => Some(ThinkLevel::Low),
+            "medium" => Some(ThinkLevel::Medium),
+            "high" => Some(ThinkLevel::High),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ThinkLevel::Default => "default",
+            ThinkLevel::Off => "off",
+            ThinkLevel::Low => "low",
+            ThinkLevel::Medium => "medium",
+            ThinkLevel::High => "high",
+        }
+    }
+
+    pub fn is_default(&self) -> bool {
+        *self == ThinkLevel::Default
+    }
+
+    fn instruction(self) -> Option<&'static str> {
+        match self {
+            ThinkLevel::Off => Some("/no_think"),
+            ThinkLevel::Low => Some("Think briefly before answering; keep the reasoning short."),
+            ThinkLevel::High => Some("Think the problem through carefully before answering."),
+            ThinkLevel::Default | ThinkLevel::Medium => None,
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct LlmClient {
     api_url: String,
-    model_name: String,
+    pub model_name: String,
     client: Client,
     #[allow(dead_code)]
     pub context_window: u32,
     pub temperature: f32,
+    pub request_timeout_secs: u64,
+    pub output_format: OutputFormat,
+    pub stop: Vec<String>,  // From the model profile's `stop`; empty leaves it to the server
+    pub cache: Option<ResponseCache>,  // Set when `llm.response_cache` is on; consulted at temperature 0 only
+    pub logit_bias: HashMap<u32, f32>,  // Token id -> bias, set with /bias
+    pub top_p: Option<f32>,  // None leaves nucleus sampling to the server
+    pub max_tokens: u32,     // Reply length cap, set with /set max_tokens
+    pub think: ThinkLevel,   // Reasoning effort, set with /set think
 }
 
+// Reply length cap until /set max_tokens changes it
+pub const DEFAULT_MAX_TOKENS: u32 = 4096;
+
 #[derive(Serialize)]
 struct CompletionRequest {
     model: String,
     messages: Vec<ChatMessage>,
     temperature: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
     max_tokens: u32,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream_options: Option<StreamOptions>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<ResponseFormat>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    grammar: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    n: Option<u32>,  // Number of candidate completions; server default is 1
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    stop: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    logit_bias: Option<HashMap<u32, f32>>,  // Serialized as {"<token id>": bias}
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reasoning_effort: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    chat_template_kwargs: Option<serde_json::Value>,  // Jinja template variables (llama-server --jinja)
+}
+
+#[derive(Serialize)]
+struct StreamOptions {
+    include_usage: bool,
+}
+
+#[derive(Serialize)]
+struct ResponseFormat {
+    #[serde(rename = "type")]
+    kind: &'static str,
 }
 
 #[derive(Deserialize, Debug)]
@@ -32,7 +145,7 @@ struct CompletionResponse {
     usage: Option<Usage>,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[allow(dead_code)]
 pub struct Usage {
     pub prompt_tokens: u32,
@@ -50,39 +163,335 @@ struct MessageContent {
     content: String,
 }
 
+#[derive(Deserialize, Debug)]
+struct ModelList {
+    data: Vec<ModelEntry>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ModelEntry {
+    id: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct StreamChunk {
+    #[serde(default)]
+    choices: Vec<StreamChoice>,
+    usage: Option<Usage>,
+}
+
+#[derive(Deserialize, Debug)]
+struct StreamChoice {
+    delta: StreamDelta,
+}
+
+#[derive(Deserialize, Debug)]
+struct StreamDelta {
+    content: Option<String>,
+}
+
 impl LlmClient {
     pub fn new(config: &RuboxConfig) -> Self {
         LlmClient {
             api_url: config.llm.api_url.clone(),
             model_name: config.llm.model_name.clone(),
-            client: Client::new(),
+            // No client-wide deadline: it would also cut off long streams.
+            // Completions apply request_timeout_secs themselves.
+            client: Client::builder().build().unwrap_or_default(),
             context_window: config.llm.context_window,
             temperature: config.temperature.default,
+            request_timeout_secs: config.llm.request_timeout_secs,
+            output_format: OutputFormat::Text,
+            stop: Vec::new(),
+            cache: config
+                .llm
+                .response_cache
+                .then(|| ResponseCache::new(ResponseCache::dir_for(&config.directories.output))),
+            logit_bias: HashMap::new(),
+            top_p: None,
+            max_tokens: DEFAULT_MAX_TOKENS,
+            think: ThinkLevel::Default,
+        }
+    }
+
+    fn completion_request(&self, mut messages: Vec<ChatMessage>, temperature: f32, stream: bool) -> CompletionRequest {
+        if let Some(instruction) = self.think.instruction() {
+            if let Some(last) = messages.iter_mut().rev().find(|m| m.role == "user") {
+                last.content = format!("{}\n\n{}", last.content, instruction);
+            }
+        }
+        let (response_format, grammar) = match &self.output_format {
+            OutputFormat::Text => (None, None),
+            OutputFormat::Json => (Some(ResponseFormat { kind: "json_object" }), None),
+            OutputFormat::Grammar(gbnf) => (None, Some(gbnf.clone())),
+        };
+        CompletionRequest {
+            model: self.model_name.clone(),
+            messages,
+            temperature,
+            top_p: self.top_p,
+            max_tokens: self.max_tokens,
+            stream,
+            stream_options: stream.then_some(StreamOptions { include_usage: true }),
+            response_format,
+            grammar,
+            n: None,
+            stop: self.stop.clone(),
+            logit_bias: (!self.logit_bias.is_empty()).then(|| self.logit_bias.clone()),
+            reasoning_effort: matches!(self.think, ThinkLevel::Low | ThinkLevel::Medium | ThinkLevel::High)
+                .then(|| self.think.name()),
+            chat_template_kwargs: (self.think == ThinkLevel::Off)
+                .then(|| serde_json::json!({ "enable_thinking": false })),
         }
     }
 
+    // The JSON body a chat generation would POST: streamed, or `n` candidates
+    // in one non-streamed call, the same choice the chat makes
+    pub fn request_preview(&self, messages: Vec<ChatMessage>, temperature: f32, n: u32) -> anyhow::Result<String> {
+        let mut request = self.completion_request(messages, temperature, n <= 1);
+        request.n = (n > 1).then_some(n);
+        Ok(serde_json::to_string_pretty(&request)?)
+    }
+
     #[allow(dead_code)]
-    pub async fn chat_completion(&self, messages: Vec<ChatMessage>) -> Result<String, reqwest::Error> {
+    pub async fn chat_completion(&self, messages: Vec<ChatMessage>) -> anyhow::Result<String> {
         self.chat_completion_with_usage(messages, self.temperature).await.map(|(content, _)| content)
     }
 
-    pub async fn chat_completion_with_usage(&self, messages: Vec<ChatMessage>, temperature: f32) -> Result<(String, Option<Usage>), reqwest::Error> {
-        let url = format!("{}/chat/completions", self.api_url);
+    // llm.request_timeout_secs as a deadline; 0 means wait indefinitely
+    fn request_timeout(&self) -> Option<Duration> {
+        (self.request_timeout_secs > 0).then(|| Duration::from_secs(self.request_timeout_secs))
+    }
 
-        let request = CompletionRequest {
-            model: self.model_name.clone(),
-            messages,
-            temperature,
-            max_tokens: 4096,
+    // Await `fut` for at most the request timeout
+    async fn within_timeout<T>(&self, fut: impl std::future::Future<Output = T>) -> Result<T, tokio::time::error::Elapsed> {
+        match self.request_timeout() {
+            Some(limit) => tokio::time::timeout(limit, fut).await,
+            None => Ok(fut.await),
+        }
+    }
+
+    pub fn api_url(&self) -> &str {
+        &self.api_url
+    }
+
+    // llama-server's /props endpoint lives at the server root, not under /v1
+    pub async fn server_props(&self) -> Result<serde_json::Value, reqwest::Error> {
+        let base = self.api_url.trim_end_matches('/').trim_end_matches("/v1");
+        let res = self.client.get(format!("{}/props", base))
+            .timeout(Duration::from_secs(2))
+            .send()
+            .await?;
+        res.json().await
+    }
+
+    // llama-server's /health answers 200 once the model is loaded (503 while
+    // loading). Servers without the endpoint (404) are taken to be up as long
+    // as they answer at all.
+    pub async fn health(&self) -> bool {
+        let base = self.api_url.trim_end_matches('/').trim_end_matches("/v1");
+        self.client.get(format!("{}/health", base))
+            .timeout(Duration::from_secs(2))
+            .send()
+            .await
+            .is_ok_and(|res| res.status().is_success() || res.status() == reqwest::StatusCode::NOT_FOUND)
+    }
+
+    // Model ids the server reports at /v1/models. Servers without the endpoint
+    // (or unreachable ones) yield an empty list rather than an error.
+    pub async fn list_server_models(&self) -> Vec<String> {
+        let res = match self.client.get(format!("{}/models", self.api_url))
+            .timeout(Duration::from_secs(5))
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+        {
+            Ok(res) => res,
+            Err(_) => return Vec::new(),
         };
+        match res.json::<ModelList>().await {
+            Ok(list) => list.data.into_iter().map(|m| m.id).collect(),
+            Err(_) => Vec::new(),
+        }
+    }
 
-        let res = self.client.post(url)
+    // One-token generation used to confirm a freshly started server actually
+    // serves completions; /health can answer before the model is usable
+    pub async fn probe(&self) -> Result<(), reqwest::Error> {
+        let mut request = self.completion_request(
+            vec![ChatMessage { role: "user".to_string(), content: "hi".to_string() }],
+            0.0,
+            false,
+        );
+        request.max_tokens = 1;
+        self.client.post(format!("{}/chat/completions", self.api_url))
             .json(&request)
+            .timeout(Duration::from_secs(30))
             .send()
-            .await?;
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    // Non-streaming completion. Error statuses surface the server's response
+    // body, and a reply without choices is an error rather than a panic.
+    // With the response cache on, temperature 0 replies are served from disk
+    // when the exact same request was answered before; sampled ones never are.
+    pub async fn chat_completion_with_usage(&self, messages: Vec<ChatMessage>, temperature: f32) -> anyhow::Result<(String, Option<Usage>)> {
+        let cached = match &self.cache {
+            Some(cache) if temperature == 0.0 => {
+                let body = serde_json::to_string(&self.completion_request(messages.clone(), temperature, false))?;
+                Some((cache, ResponseCache::key(&body)))
+            }
+            _ => None,
+        };
+        if let Some((cache, key)) = &cached {
+            if let Some(hit) = cache.get(key) {
+                tracing::debug!(model = %self.model_name, key = %key, "completion served from cache");
+                return Ok(hit);
+            }
+        }
+
+        let (mut choices, usage) = self.chat_completion_choices(messages, temperature, 1).await?;
+        let content = choices.swap_remove(0);
+        if let Some((cache, key)) = &cached {
+            if let Err(e) = cache.put(key, &content, usage.as_ref()) {
+                tracing::warn!(error = %e, "failed to write response cache entry");
+            }
+        }
+        Ok((content, usage))
+    }
+
+    // Request `n` candidate completions in one call. The server may return
+    // fewer than asked for (some builds ignore `n`), but never zero on success.
+    // Usage covers all candidates together.
+    pub async fn chat_completion_choices(&self, messages: Vec<ChatMessage>, temperature: f32, n: u32) -> anyhow::Result<(Vec<String>, Option<Usage>)> {
+        let url = format!("{}/chat/completions", self.api_url);
+
+        let mut request = self.completion_request(messages, temperature, false);
+        request.n = (n > 1).then_some(n);
+
+        let start = std::time::Instant::now();
+        let mut builder = self.client.post(url).json(&request);
+        if let Some(limit) = self.request_timeout() {
+            builder = builder.timeout(limit);
+        }
+        let res = builder
+            .send()
+            .await
+            .inspect_err(|e| tracing::warn!(error = %e, "completion request failed"))?;
+
+        let status = res.status();
+        if !status.is_success() {
+            let body = res.text().await.unwrap_or_default();
+            tracing::warn!(%status, body = body.trim(), "completion returned an error status");
+            anyhow::bail!("server returned {}: {}", status, body.trim());
+        }
 
         let response_data: CompletionResponse = res.json().await?;
-        let content = response_data.choices[0].message.content.clone();
-        Ok((content, response_data.usage))
+        if response_data.choices.is_empty() {
+            anyhow::bail!("server returned no choices");
+        }
+        let choices: Vec<String> = response_data.choices.into_iter().map(|c| c.message.content).collect();
+        tracing::debug!(
+            model = %self.model_name,
+            n = choices.len(),
+            elapsed_ms = start.elapsed().as_millis() as u64,
+            completion_tokens = response_data.usage.as_ref().map(|u| u.completion_tokens),
+            "completion finished"
+        );
+        Ok((choices, response_data.usage))
+    }
+
+    // Streams the completion over SSE, calling `on_delta` for each content
+    // fragment as it arrives. Returns the full text and usage; when the server
+    // doesn't report usage, the number of deltas stands in for completion tokens.
+    //
+    // The request timeout bounds each wait for the server (the response
+    // headers, then every chunk) rather than the whole stream, so a stuck
+    // server still times out but a long healthy generation is never cut off.
+    // Hitting it returns tokio's `Elapsed` error.
+    //
+    // To cancel, drop the future. The unfinished body is never returned to the
+    // connection pool, so the socket closes; llama.cpp checks for a closed
+    // connection between tokens and cancels the task, freeing its slot. There is
+    // no separate abort endpoint for chat completions to call.
+    //
+    // Streams never read or fill the response cache; only the non-streamed
+    // calls above do.
+    pub async fn chat_completion_stream<F>(
+        &self,
+        messages: Vec<ChatMessage>,
+        temperature: f32,
+        mut on_delta: F,
+    ) -> anyhow::Result<(String, Option<Usage>)>
+    where
+        F: FnMut(&str),
+    {
+        let url = format!("{}/chat/completions", self.api_url);
+
+        let request = self.completion_request(messages, temperature, true);
+
+        let start = std::time::Instant::now();
+        let mut res = self.within_timeout(self.client.post(url).json(&request).send())
+            .await
+            .inspect_err(|_| tracing::warn!(timeout_secs = self.request_timeout_secs, "stream request timed out"))?
+            .and_then(|r| r.error_for_status())
+            .inspect_err(|e| tracing::warn!(error = %e, "stream request failed"))?;
+        let first_byte_ms = start.elapsed().as_millis() as u64;
+
+        let mut content = String::new();
+        let mut usage = None;
+        let mut deltas = 0u32;
+        // Raw bytes: a multi-byte character can be split across chunks, so
+        // only complete lines are decoded
+        let mut buffer: Vec<u8> = Vec::new();
+
+        'read: while let Some(chunk) = self.within_timeout(res.chunk())
+            .await
+            .inspect_err(|_| tracing::warn!(timeout_secs = self.request_timeout_secs, "stream stalled"))??
+        {
+            buffer.extend_from_slice(&chunk);
+
+            // SSE events are newline-delimited "data: {...}" lines
+            while let Some(newline) = buffer.iter().position(|&b| b == b'\n') {
+                let line: Vec<u8> = buffer.drain(..=newline).collect();
+                let line = String::from_utf8_lossy(&line);
+                let Some(data) = line.trim().strip_prefix("data:") else {
+                    continue;
+                };
+                let data = data.trim();
+                if data == "[DONE]" {
+                    break 'read;
+                }
+
+                if let Ok(parsed) = serde_json::from_str::<StreamChunk>(data) {
+                    if let Some(delta) = parsed.choices.first().and_then(|c| c.delta.content.as_deref()) {
+                        if !delta.is_empty() {
+                            content.push_str(delta);
+                            deltas += 1;
+                            on_delta(delta);
+                        }
+                    }
+                    if parsed.usage.is_some() {
+                        usage = parsed.usage;
+                    }
+                }
+            }
+        }
+
+        tracing::debug!(
+            model = %self.model_name,
+            first_byte_ms,
+            elapsed_ms = start.elapsed().as_millis() as u64,
+            deltas,
+            "stream finished"
+        );
+        let usage = usage.or(Some(Usage {
+            prompt_tokens: 0,
+            completion_tokens: deltas,
+            total_tokens: deltas,
+        }));
+        Ok((content, usage))
     }
 }
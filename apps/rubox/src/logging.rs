@@ -0,0 +1,36 @@
+use std::fs::OpenOptions;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Mutex;
+use tracing::Level;
+
+// Diagnostic log, kept apart from the terminal UI. Enabled by --verbose
+// (debug level) or RUBOX_LOG=<trace|debug|info|warn|error>.
+pub const LOG_ENV: &str = "RUBOX_LOG";
+pub const LOG_FILE: &str = "rubox.log";
+
+// Install the file subscriber under `output_dir`. Returns the log path, or
+// None when logging is off.
+pub fn init(output_dir: &str, verbose: bool) -> anyhow::Result<Option<PathBuf>> {
+    let level = match std::env::var(LOG_ENV) {
+        // Any other non-empty value ("1", "on") just means "log"
+        Ok(value) if !value.trim().is_empty() => Level::from_str(value.trim()).unwrap_or(Level::DEBUG),
+        _ if verbose => Level::DEBUG,
+        _ => return Ok(None),
+    };
+
+    std::fs::create_dir_all(output_dir)?;
+    let path = Path::new(output_dir).join(LOG_FILE);
+    let file = OpenOptions::new().create(true).append(true).open(&path)?;
+
+    tracing_subscriber::fmt()
+        .with_writer(Mutex::new(file))
+        .with_ansi(false)
+        .with_max_level(level)
+        .with_target(true)
+        .try_init()
+        .map_err(|e| anyhow::anyhow!("failed to install log subscriber: {}", e))?;
+
+    tracing::info!(version = env!("CARGO_PKG_VERSION"), %level, "rubox started");
+    Ok(Some(path))
+}
@@ -1,26 +1,22 @@
-mod config;
-mod llm_client;
-mod server_manager;
-mod chat;
-mod multi_model;
-mod ui;
-mod session;
-mod commands;
-mod prompts;
-mod tui;
-
 use clap::Parser;
 use std::fs;
-use std::path::Path;
-use config::RuboxConfig;
-use llm_client::LlmClient;
-use server_manager::ServerManager;
+use std::path::{Path, PathBuf};
+use rubox::{batch, chat, config, logging, multi_model, router, ui};
+use rubox::config::{ParamOverrides, RuboxConfig, MODELS_DIR};
+use rubox::llm_client::LlmClient;
+use rubox::prompts::{initial_prompt, read_text_file, PROMPT_INPUT_FILE};
+use rubox::server_manager::{stop_kept_server, ServerManager};
+use rubox::state::AppState;
 
 #[derive(Parser, Debug)]
 #[command(name = "rubox")]
 #[command(about = "Rust-based Chat Application with llama.cpp", long_about = None)]
 struct Args {
-    /// Model name or path override
+    /// Path to the config file (default: $RUBOX_CONFIG, ./rubox_config.json, then ~/.config/rubox/config.json)
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// Model to start with; "auto" picks one from the first prompt (images -> vision, quick questions -> small, involved -> reasoning)
     #[arg(short, long)]
     model: Option<String>,
 
@@ -28,23 +24,81 @@ struct Args {
     #[arg(short, long)]
     list: bool,
 
-    /// Prompt text (if not using prompt_input.txt)
+    /// Prompt text sent as the first message (takes precedence over --prompt-file)
     #[arg(short, long)]
     prompt: Option<String>,
 
+    /// Read the first message from a file instead of prompt_input.txt
+    #[arg(long, value_name = "PATH")]
+    prompt_file: Option<PathBuf>,
+
     /// Enable verbose mode
     #[arg(long)]
     verbose: bool,
+
+    /// Start with the model used last time instead of the default
+    #[arg(long)]
+    resume: bool,
+
+    /// Override the model profile's GPU layer count
+    #[arg(long)]
+    gpu_layers: Option<i32>,
+
+    /// Override the model profile's context size
+    #[arg(long)]
+    ctx_size: Option<u32>,
+
+    /// Override the model profile's batch size
+    #[arg(long)]
+    batch_size: Option<u32>,
+
+    /// Leave llama-server running on exit so the next launch skips loading the model
+    #[arg(long)]
+    keep_server: bool,
+
+    /// Stop a llama-server left running by --keep-server, then exit
+    #[arg(long)]
+    stop_server: bool,
+
+    /// Run each prompt in a file (one per line, or blocks separated by "---") against the model (--model or the default) and write a report instead of chatting
+    #[arg(long, value_name = "PATH")]
+    batch: Option<PathBuf>,
+
+    /// Compare these models ("1,3", "qwen*", "*") on one prompt (--prompt, --prompt-file, prompt_input.txt, else asked for), with follow-ups sent to all; with --batch, run every prompt against each and write a results grid
+    #[arg(long, value_name = "SELECTION")]
+    models: Option<String>,
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    // Load config
-    let config = RuboxConfig::load();
-
     // Parse CLI args
     let args = Args::parse();
 
+    // Load config
+    let mut config = RuboxConfig::load(args.config.as_deref())?;
+    ui::init_color_support(&mut config);
+    if args.verbose {
+        match &config.source_path {
+            Some(path) => println!("Config: {}", path.display()),
+            None => println!("Config: built-in defaults (no config file found)"),
+        }
+    }
+
+    // Diagnostics go to a file so they never mix with the TUI
+    if let Some(log_path) = logging::init(&config.directories.output, args.verbose)? {
+        if args.verbose {
+            println!("Log: {}", log_path.display());
+        }
+    }
+    tracing::info!(config = ?config.source_path, "config loaded");
+
+    // CLI parameter overrides win over the model profile; unset flags fall through
+    config.param_overrides = ParamOverrides {
+        gpu_layers: args.gpu_layers,
+        context_window: args.ctx_size,
+        batch_size: args.batch_size,
+    };
+
     // Handle --list
     if args.list {
         println!(
@@ -52,7 +106,17 @@ async fn main() -> anyhow::Result<()> {
             config.ui.color_orange, config.ui.color_reset
         );
         for (name, path) in &config.models.registry {
-            println!("  {} -> {}", name, path);
+            let missing = if config.model_path_exists(name) { "" } else { " (file not found)" };
+            println!("  {} -> {}{}", name, path, missing);
+        }
+        return Ok(());
+    }
+
+    // Handle --stop-server
+    if args.stop_server {
+        match stop_kept_server(&config).await? {
+            Some(pid) => println!("Stopped llama-server (pid {})", pid),
+            None => println!("No kept llama-server is running"),
         }
         return Ok(());
     }
@@ -64,27 +128,144 @@ async fn main() -> anyhow::Result<()> {
             config.ui.color_orange, config.ui.color_reset);
     }
 
+    // Resolve the prompt up front so a bad --prompt-file fails before the server starts
+    let initial_prompt = initial_prompt(args.prompt.as_deref(), args.prompt_file.as_deref(), Path::new(PROMPT_INPUT_FILE))?;
+    let batch_prompts = args.batch.as_deref().map(read_batch_file).transpose()?;
+
     // Create directories
     ensure_directories(&config)?;
+    if config.llm.manage_server {
+        warn_if_models_dir_empty(&config);
+    }
 
-    // Use default model (qwen3-vl with vision support)
+    // Use default model (qwen3-vl with vision support), or the last one with --resume
+    let saved_state = AppState::load();
     let default_model = "qwen3-vl";
+    let (start_model, start_target) = match saved_state.last_model.as_deref() {
+        Some(last) if args.resume => match resolve_model_target(&config, last) {
+            Some(target) => (last.to_string(), target),
+            None => {
+                println!(
+                    "{}Last model '{}' is no longer available, using {}{}",
+                    config.ui.color_orange, last, default_model, config.ui.color_reset
+                );
+                (default_model.to_string(), default_model.to_string())
+            }
+        },
+        _ => (default_model.to_string(), default_model.to_string()),
+    };
+
+    // --model wins over the default and --resume; "auto" routes on the first prompt
+    let mut notice = None;
+    let (start_model, start_target) = match args.model.as_deref() {
+        Some(router::AUTO_MODEL) => {
+            if batch_prompts.is_some() || args.models.is_some() {
+                anyhow::bail!("--model auto picks one model for the chat; use --model <name> with --batch, or --models alone");
+            }
+            let models = router::model_infos(&config, Path::new(MODELS_DIR));
+            let route = match initial_prompt.as_deref() {
+                Some(prompt) => router::choose_model(prompt, &models, router::default_router, &start_model),
+                None => router::Route {
+                    model: start_model.clone(),
+                    reason: "no prompt given: default model".to_string(),
+                },
+            };
+            println!(
+                "{}auto → {} ({}){}",
+                config.ui.color_orange, route.model, route.reason, config.ui.color_reset
+            );
+            tracing::info!(model = %route.model, reason = %route.reason, "auto model routing");
+            notice = Some(format!("auto → {}: {}", route.model, route.reason));
+            match resolve_model_target(&config, &route.model) {
+                Some(target) => (route.model, target),
+                None => (start_model, start_target),
+            }
+        }
+        Some(name) => match resolve_model_target(&config, name) {
+            Some(target) => (name.to_string(), target),
+            None => anyhow::bail!("Unknown model '{}' (see rubox --list)", name),
+        },
+        None => (start_model, start_target),
+    };
+
+    tracing::debug!(model = %start_model, target = %start_target, resume = args.resume, "resolved start model");
 
     let mut server = ServerManager::new();
-    server.ensure_running(&config, Some(default_model)).await?;
+    server.keep_on_exit = args.keep_server || config.llm.keep_server;
+
+    // Several models: the batch as a matrix, or one prompt compared side by side
+    if let Some(selection) = &args.models {
+        let mut models = config.available_models(Path::new(MODELS_DIR));
+        if !config.llm.manage_server {
+            let mut remote: Vec<&String> = config.llm.endpoints.keys().filter(|m| !models.contains(m)).collect();
+            remote.sort();
+            models.extend(remote.into_iter().cloned());
+        }
+        let mut targets = Vec::new();
+        for index in ui::parse_model_selection(selection, &models)? {
+            let name = &models[index];
+            match resolve_model_target(&config, name) {
+                Some(target) => targets.push((name.clone(), target)),
+                None => anyhow::bail!("Unknown model '{}' (see rubox --list)", name),
+            }
+        }
+        match &batch_prompts {
+            Some(prompts) => {
+                batch::run_matrix(prompts.clone(), &targets, &config, &mut server).await?;
+                cleanup_old_files(&config)?;
+            }
+            None => {
+                let prompt = match initial_prompt {
+                    Some(prompt) => prompt,
+                    None => ask_prompt(&config)?,
+                };
+                multi_model::run_multi_model(&targets, prompt, &config, &mut server, || multi_model::ask_follow_up(&config)).await?;
+                clear_prompt_file(PROMPT_INPUT_FILE)?;
+            }
+        }
+        return Ok(());
+    }
+
+    if config.llm.manage_server {
+        server.ensure_running(&config, Some(&start_target)).await?;
+    }
+
+    if let Some(prompts) = batch_prompts {
+        batch::run_batch(prompts, &start_model, &config).await?;
+        cleanup_old_files(&config)?;
+        return Ok(());
+    }
 
     let client = LlmClient::new(&config);
-    // Start chat with verbose ON by default (unless explicitly disabled)
-    let verbose = args.verbose || true; // Always ON for now
-    chat::run_chat_mode(&client, default_model, &config, verbose, &mut server).await?;
+    let start = chat::ChatStart { prompt: initial_prompt, notice };
+    chat::run_chat_mode(&client, &start_model, &config, args.verbose, &mut server, start, saved_state).await?;
+
+    if let Some(pid) = server.pid().filter(|_| server.keep_on_exit) {
+        println!(
+            "{}llama-server is still running (pid {}) and will be reused next time; stop it with rubox --stop-server{}",
+            config.ui.color_orange, pid, config.ui.color_reset
+        );
+    }
 
     // Cleanup
     cleanup_old_files(&config)?;
-    clear_prompt_file("prompt_input.txt")?;
+    clear_prompt_file(PROMPT_INPUT_FILE)?;
 
     Ok(())
 }
 
+// What to hand ensure_running for a model name: registry names resolve
+// themselves, scanned models are launched by path. Unmanaged servers take any name.
+fn resolve_model_target(config: &RuboxConfig, name: &str) -> Option<String> {
+    if config.models.registry.contains_key(name) || !config.llm.manage_server {
+        return Some(name.to_string());
+    }
+    config::scan_models_dir()
+        .into_iter()
+        .find(|(scanned, _)| scanned == name)
+        .map(|(_, path)| path)
+}
+
 fn ensure_directories(config: &RuboxConfig) -> anyhow::Result<()> {
     fs::create_dir_all(&config.directories.output)?;
     fs::create_dir_all(&config.directories.tmp_md)?;
@@ -96,23 +277,20 @@ fn ensure_directories(config: &RuboxConfig) -> anyhow::Result<()> {
     Ok(())
 }
 
-#[allow(dead_code)]
-fn get_or_prompt_user(config: &RuboxConfig) -> anyhow::Result<String> {
-    let prompt_file = "prompt_input.txt";
-
-    // Check if prompt_input.txt exists and has content
-    if let Ok(content) = fs::read_to_string(prompt_file) {
-        if !content.trim().is_empty() {
-            println!();
-            println!(
-                "{}✓ Existing prompt detected in prompt_input.txt. Proceeding...{}",
-                config.ui.color_orange, config.ui.color_reset
-            );
-            println!();
-            return Ok(content.trim().to_string());
-        }
+fn read_batch_file(path: &Path) -> anyhow::Result<Vec<String>> {
+    if !path.is_file() {
+        anyhow::bail!("Batch file not found: {}", path.display());
+    }
+    let prompts = batch::split_prompts(&read_text_file(path, "batch file")?);
+    if prompts.is_empty() {
+        anyhow::bail!("Batch file has no prompts: {}", path.display());
     }
+    Ok(prompts)
+}
 
+// The last step after --prompt, --prompt-file and prompt_input.txt (see
+// initial_prompt), for runs that can't start without a prompt
+fn ask_prompt(config: &RuboxConfig) -> anyhow::Result<String> {
     // Ask user for prompt
     println!();
     println!(
@@ -138,31 +316,18 @@ fn get_or_prompt_user(config: &RuboxConfig) -> anyhow::Result<String> {
     Ok(prompt.trim().to_string())
 }
 
-#[allow(dead_code)]
-fn get_available_models(config: &RuboxConfig) -> Vec<String> {
-    let mut models = Vec::new();
-
-    // Add models from registry
-    for name in config.models.registry.keys() {
-        models.push(name.clone());
+// Startup hint for a managed server when models/ is missing or holds no .gguf
+// files; registry entries may still point elsewhere, so this only warns
+fn warn_if_models_dir_empty(config: &RuboxConfig) {
+    if !config::scan_models_dir().is_empty() {
+        return;
     }
-
-    // Add models from models directory
-    if let Ok(entries) = fs::read_dir(&Path::new("models")) {
-        for entry in entries.flatten() {
-            let path = entry.path();
-            if path.extension().and_then(|s| s.to_str()) == Some("gguf") {
-                if let Some(file_name) = path.file_name().and_then(|n| n.to_str()) {
-                    let model_name = file_name.replace(".gguf", "");
-                    if !models.contains(&model_name) {
-                        models.push(model_name);
-                    }
-                }
-            }
-        }
-    }
-
-    models
+    let reason = if Path::new(MODELS_DIR).is_dir() { "contains no .gguf files" } else { "does not exist" };
+    tracing::warn!(dir = MODELS_DIR, reason, "no local models found");
+    println!(
+        "{}⚠ The {}/ directory {}. Place .gguf files in ./{}/ or point models.registry in rubox_config.json at existing files.{}",
+        config.ui.color_orange, MODELS_DIR, reason, MODELS_DIR, config.ui.color_reset
+    );
 }
 
 fn cleanup_old_files(config: &RuboxConfig) -> anyhow::Result<()> {
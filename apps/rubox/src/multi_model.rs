@@ -1,105 +1,193 @@
 use std::fs;
+use std::future::Future;
+use std::io::{self, IsTerminal, Write};
 use std::path::Path;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use chrono::Local;
-use crate::config::RuboxConfig;
-use crate::llm_client::{LlmClient, ChatMessage};
+use futures::stream::{FuturesUnordered, StreamExt};
+use crate::config::{render_filename, RuboxConfig, FILENAME_TIMESTAMP};
+use crate::llm_client::{LlmClient, ChatMessage, Usage};
 use crate::server_manager::ServerManager;
+use crate::session::Session;
+use crate::ui::{ansi, get_user_input, CLEAR_LINE};
 
-#[allow(dead_code)]
+// One model's answer to a round, with how long it took
+pub(crate) type Reply = (anyhow::Result<(String, Option<Usage>)>, Duration);
+
+// Pause before retrying a failed model, multiplied by the attempt number
+const RETRY_DELAY: Duration = Duration::from_secs(2);
+
+// Waiting-line frames when ui.spinner names no known style
+const FALLBACK_SPINNER: &[&str] = &["|", "/", "-", "\\"];
+
+// Ask every model `prompt`, then keep going with the follow-ups `next_prompt`
+// returns until it gives an empty line or /exit. `models` pairs each name with
+// what ensure_running starts for it, as in batch::run_matrix.
 pub async fn run_multi_model(
-    selected_models: Vec<String>,
+    models: &[(String, String)],
     prompt: String,
     config: &RuboxConfig,
     server_manager: &mut ServerManager,
+    mut next_prompt: impl FnMut() -> anyhow::Result<String>,
 ) -> anyhow::Result<()> {
-    let timestamp = Local::now().format("%d_%m_%Y_%H_%M_%S").to_string();
+    let selected_models: Vec<String> = models.iter().map(|(name, _)| name.clone()).collect();
+    let timestamp = Local::now().format(FILENAME_TIMESTAMP).to_string();
 
     // Create directories
     fs::create_dir_all(&config.directories.prompts)?;
     fs::create_dir_all(&config.directories.tmp_md)?;
     fs::create_dir_all(&config.directories.output)?;
 
-    // Save prompt
-    let prompt_file = format!("{}/Prompt_{}.md", config.directories.prompts, timestamp);
-    fs::write(&prompt_file, &prompt)?;
+    // One independent conversation per model, so follow-ups continue each thread
+    let mut sessions: Vec<Session> = selected_models
+        .iter()
+        .map(|model_name| Session::new(model_name.clone(), config.temperature.default))
+        .collect();
 
+    let concurrent = runs_concurrently(config, &selected_models);
+    let results_name = render_filename(&config.filenames.results, &[("timestamp", &timestamp)]);
+    let results_file = format!("{}/{}.md", config.directories.output, results_name);
     let mut results = String::new();
+    let mut failures: Vec<(usize, String, String)> = Vec::new();  // (round, model, error)
+    let mut prompt = prompt;
+    let mut round = 1;
+    let started = Instant::now();
 
-    for model_name in selected_models {
-        // Stop and restart server with new model
-        server_manager.stop()?;
-        server_manager.ensure_running(config, Some(&model_name)).await?;
+    loop {
+        // Round 1 keeps the original file names; follow-ups get a round suffix
+        let suffix = if round == 1 { String::new() } else { format!("_r{}", round) };
 
-        // Create client and send prompt
-        let client = LlmClient::new(config);
-        let messages = vec![ChatMessage {
-            role: "user".to_string(),
-            content: prompt.clone(),
-        }];
+        // Save prompt
+        let prompt_name = render_filename(&config.filenames.prompt, &[("timestamp", &timestamp)]);
+        let prompt_file = format!("{}/{}{}.md", config.directories.prompts, prompt_name, suffix);
+        fs::write(&prompt_file, &prompt)?;
 
-        let start_time = Instant::now();
-        match client.chat_completion_with_usage(messages, config.temperature.default).await {
-            Ok((response, usage)) => {
-                let elapsed = start_time.elapsed();
-
-                // Save individual response
-                let sanitized_model = sanitize_filename(&model_name);
-                let response_file = format!(
-                    "{}/{}_{}.md",
-                    config.directories.tmp_md, sanitized_model, timestamp
-                );
-                fs::write(&response_file, &response)?;
+        if round > 1 {
+            results.push_str(&format!("\n---\n\n## Follow-up {}\n\n{}\n", round, prompt));
+        }
 
-                // Display response
-                println!();
-                println!("{}┌─ {} {}{}", config.ui.color_orange, model_name, "─", config.ui.color_reset);
-                println!("{}{}{}", config.ui.color_orange, response, config.ui.color_reset);
+        // Append the prompt to every model's history; each gets its whole thread
+        for session in sessions.iter_mut() {
+            session.add_message("user".to_string(), prompt.clone(), None);
+        }
 
-                let tps = if let Some(usage) = &usage {
-                    let tokens = usage.completion_tokens as f32;
-                    tokens / elapsed.as_secs_f32()
-                } else {
-                    0.0
+        // Replies (or errors) by model index, so the results file keeps the selection order
+        let mut replies: Vec<Result<String, String>> = vec![Err(String::new()); sessions.len()];
+        if concurrent {
+            let limit = config.llm.multi_model_concurrency.max(1);
+            println!(
+                "{}Asking {} models, up to {} at a time...{}",
+                config.ui.color_orange, sessions.len(), limit, config.ui.color_reset
+            );
+            let jobs: Vec<(usize, String, Vec<ChatMessage>)> = sessions
+                .iter()
+                .enumerate()
+                .map(|(index, session)| (index, session.metadata.model_name.clone(), api_messages(session)))
+                .collect();
+            let mut jobs = jobs.into_iter();
+
+            // Keep at most `limit` requests in flight, starting the next as one finishes
+            let ask = |(index, model_name, messages): (usize, String, Vec<ChatMessage>)| async move {
+                (index, ask_model(config, &model_name, messages).await)
+            };
+            let mut pending: FuturesUnordered<_> = jobs.by_ref().take(limit).map(ask).collect();
+            let total = sessions.len();
+            let mut answered = 0;
+            let round_started = Instant::now();
+            loop {
+                let label = format!("Waiting for replies ({}/{} done)", answered, total);
+                let Some((index, reply)) = with_spinner(config, &label, round_started, started, pending.next()).await else {
+                    break;
                 };
+                answered += 1;
+                if let Some(job) = jobs.next() {
+                    pending.push(ask(job));
+                }
+                replies[index] = record_reply(config, &mut sessions[index], reply, &timestamp, &suffix)?;
+            }
+        } else {
+            let total = sessions.len();
+            for ((index, session), (_, target)) in sessions.iter_mut().enumerate().zip(models) {
+                let model_name = session.metadata.model_name.clone();
+                let name = config.display_name(&model_name);
                 println!(
-                    "{}└─ ({:.1} tps, {:.2}s){} {}",
-                    config.ui.color_orange, tps, elapsed.as_secs_f32(), "─", config.ui.color_reset
+                    "{}[{}/{}] {} {}... (total {}){}",
+                    config.ui.color_orange,
+                    index + 1,
+                    total,
+                    if config.llm.manage_server { "Loading" } else { "Asking" },
+                    name,
+                    elapsed_text(started.elapsed()),
+                    config.ui.color_reset
                 );
-                println!();
 
-                // Add to results
-                if !results.is_empty() {
-                    results.push_str("\n---\n\n");
-                }
-                results.push_str(&format!("# {}\n\n{}\n", model_name, response));
+                // Stop and restart server with new model; a model that won't load
+                // fails on its own instead of ending the comparison
+                let loaded = if config.llm.manage_server {
+                    server_manager.stop()?;
+                    server_manager.ensure_running(config, Some(target)).await
+                } else {
+                    Ok(())
+                };
+                let reply = match loaded {
+                    Ok(()) => {
+                        let label = format!("[{}/{}] Waiting for {}", index + 1, total, name);
+                        let asked = Instant::now();
+                        with_spinner(config, &label, asked, started, ask_model(config, &model_name, api_messages(session))).await
+                    }
+                    Err(e) => (Err(e.context("llama-server did not start")), Duration::ZERO),
+                };
+                replies[index] = record_reply(config, session, reply, &timestamp, &suffix)?;
             }
-            Err(e) => {
-                eprintln!(
-                    "{}⚠ Error getting response from {}: {}{}",
-                    config.ui.color_orange, model_name, e, config.ui.color_reset
-                );
+        }
+
+        // Add to results; failures get a section too, so they can't go unnoticed
+        for (session, reply) in sessions.iter().zip(replies) {
+            if !results.is_empty() {
+                results.push_str("\n---\n\n");
             }
+            let name = config.display_name(&session.metadata.model_name);
+            match reply {
+                Ok(response) => results.push_str(&format!("# {}\n\n{}\n", name, response)),
+                Err(error) => {
+                    results.push_str(&format!("# {}\n\n_Error: {}_\n", name, error));
+                    failures.push((round, name, error));
+                }
+            }
+        }
+
+        // Save combined results (rewritten after every round)
+        fs::write(&results_file, &results)?;
+        println!();
+        println!(
+            "{}═══════════════════════════════════════{}",
+            config.ui.color_orange, config.ui.color_reset
+        );
+        println!(
+            "{}Results saved to: {}{}",
+            config.ui.color_orange, results_file, config.ui.color_reset
+        );
+        println!(
+            "{}═══════════════════════════════════════{}",
+            config.ui.color_orange, config.ui.color_reset
+        );
+        println!();
+
+        // A follow-up goes to every model; empty input ends the comparison
+        let follow_up = next_prompt()?;
+        if follow_up.is_empty() || follow_up == "/exit" {
+            break;
         }
+
+        prompt = follow_up;
+        round += 1;
     }
 
-    // Save combined results
-    let results_file = format!("{}/Results_{}.md", config.directories.output, timestamp);
+    // The summary closes the results file too, so a failure shows in the artifact
+    let names: Vec<String> = sessions.iter().map(|s| config.display_name(&s.metadata.model_name)).collect();
+    print_summary(config, &names, &failures, round);
+    results.push_str(&summary_section(&names, &failures, round));
     fs::write(&results_file, &results)?;
-    println!();
-    println!(
-        "{}═══════════════════════════════════════{}",
-        config.ui.color_orange, config.ui.color_reset
-    );
-    println!(
-        "{}Results saved to: {}{}",
-        config.ui.color_orange, results_file, config.ui.color_reset
-    );
-    println!(
-        "{}═══════════════════════════════════════{}",
-        config.ui.color_orange, config.ui.color_reset
-    );
-    println!();
 
     // Cleanup old files
     cleanup_old_files(config)?;
@@ -107,17 +195,204 @@ pub async fn run_multi_model(
     Ok(())
 }
 
-#[allow(dead_code)]
-fn sanitize_filename(name: &str) -> String {
-    name.chars()
-        .map(|c| match c {
-            ':' | '\\' | '/' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
-            _ => c,
+// The follow-up question between rounds, read from the terminal
+pub fn ask_follow_up(config: &RuboxConfig) -> anyhow::Result<String> {
+    get_user_input(&format!(
+        "{}Follow-up for all models (empty to finish): {}",
+        config.ui.color_orange, config.ui.color_reset
+    ))
+}
+
+// Await `work` behind an animated status line with the time spent on it and
+// on the whole run, cleared once it finishes. Without a terminal (piped
+// output) nothing is drawn.
+pub(crate) async fn with_spinner<F: Future>(config: &RuboxConfig, label: &str, since: Instant, run_started: Instant, work: F) -> F::Output {
+    if !io::stdout().is_terminal() {
+        return work.await;
+    }
+    let frames = crate::tui::spinner_frames(&config.ui.spinner).unwrap_or(FALLBACK_SPINNER);
+    let mut tick = tokio::time::interval(Duration::from_millis(config.ui.tick_ms.max(50)));
+    let mut frame = 0;
+    tokio::pin!(work);
+    loop {
+        tokio::select! {
+            output = &mut work => {
+                print!("\r{}", ansi(CLEAR_LINE));
+                let _ = io::stdout().flush();
+                return output;
+            }
+            _ = tick.tick() => {
+                print!(
+                    "\r{}   {}{} {}... {} (total {}){}",
+                    ansi(CLEAR_LINE),
+                    config.ui.color_orange,
+                    frames[frame % frames.len()],
+                    label,
+                    elapsed_text(since.elapsed()),
+                    elapsed_text(run_started.elapsed()),
+                    config.ui.color_reset
+                );
+                let _ = io::stdout().flush();
+                frame += 1;
+            }
+        }
+    }
+}
+
+// "42s", or "3m05s" from a minute on
+pub(crate) fn elapsed_text(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs();
+    if secs < 60 {
+        format!("{}s", secs)
+    } else {
+        format!("{}m{:02}s", secs / 60, secs % 60)
+    }
+}
+
+// Models on their own remote endpoints don't share a GPU, so they can be asked
+// at once. The local single-server case stays sequential: one model at a time.
+fn runs_concurrently(config: &RuboxConfig, models: &[String]) -> bool {
+    !config.llm.manage_server
+        && config.llm.multi_model_concurrency > 1
+        && models.len() > 1
+        && models.iter().all(|model| config.llm.endpoints.contains_key(model))
+}
+
+fn api_messages(session: &Session) -> Vec<ChatMessage> {
+    session
+        .messages
+        .iter()
+        .map(|m| ChatMessage {
+            role: m.role.clone(),
+            content: m.content.clone(),
         })
         .collect()
 }
 
-#[allow(dead_code)]
+// Ask with up to `llm.multi_model_retries` more attempts after a failure. The
+// time reported is that of the last attempt.
+pub(crate) async fn ask_model(config: &RuboxConfig, model_name: &str, messages: Vec<ChatMessage>) -> Reply {
+    let mut endpoint = config.clone();
+    if let Some(url) = config.llm.endpoints.get(model_name) {
+        endpoint.llm.api_url = url.clone();
+    }
+    // Name the model actually loaded, so cached replies stay per model
+    let mut client = LlmClient::new(&endpoint);
+    client.model_name = model_name.to_string();
+
+    let attempts = config.llm.multi_model_retries + 1;
+    let mut attempt = 1;
+    loop {
+        let start_time = Instant::now();
+        let result = client.chat_completion_with_usage(messages.clone(), config.temperature.default).await;
+        match result {
+            Err(e) if attempt < attempts => {
+                eprintln!(
+                    "{}⚠ {} failed ({}), retrying ({}/{}){}",
+                    config.ui.color_orange, model_name, e, attempt + 1, attempts, config.ui.color_reset
+                );
+                tokio::time::sleep(RETRY_DELAY * attempt).await;
+                attempt += 1;
+            }
+            result => return (result, start_time.elapsed()),
+        }
+    }
+}
+
+// Models that answered every round
+fn succeeded<'a>(names: &'a [String], failures: &[(usize, String, String)]) -> Vec<&'a str> {
+    names
+        .iter()
+        .filter(|name| !failures.iter().any(|(_, failed, _)| failed == *name))
+        .map(String::as_str)
+        .collect()
+}
+
+// Which models answered every round and which failed, and when
+fn print_summary(config: &RuboxConfig, names: &[String], failures: &[(usize, String, String)], rounds: usize) {
+    let succeeded = succeeded(names, failures);
+    println!("{}Summary ({} round(s)){}", config.ui.color_orange, rounds, config.ui.color_reset);
+    if !succeeded.is_empty() {
+        println!("  ✓ Succeeded: {}", succeeded.join(", "));
+    }
+    for (round, name, error) in failures {
+        println!("  {}✗ {} failed in round {}: {}{}", config.ui.color_red, name, round, error, config.ui.color_reset);
+    }
+    println!();
+}
+
+// The same summary as the last section of the results file
+fn summary_section(names: &[String], failures: &[(usize, String, String)], rounds: usize) -> String {
+    let mut section = format!("\n---\n\n## Summary ({} round(s))\n\n", rounds);
+    let succeeded = succeeded(names, failures);
+    if !succeeded.is_empty() {
+        section.push_str(&format!("- Succeeded: {}\n", succeeded.join(", ")));
+    }
+    for (round, name, error) in failures {
+        section.push_str(&format!("- {} failed in round {}: {}\n", name, round, error));
+    }
+    section
+}
+
+// Show a model's reply, add it to its session and save it to tmp_md. Returns
+// the response for the results file, or the error text when the model failed.
+fn record_reply(
+    config: &RuboxConfig,
+    session: &mut Session,
+    (result, elapsed): Reply,
+    timestamp: &str,
+    suffix: &str,
+) -> anyhow::Result<Result<String, String>> {
+    let model_name = session.metadata.model_name.clone();
+    match result {
+        Ok((response, usage)) => {
+            session.add_message(
+                "assistant".to_string(),
+                response.clone(),
+                usage.as_ref().map(|u| u.completion_tokens),
+            );
+
+            // Save individual response
+            let response_name = render_filename(
+                &config.filenames.response,
+                &[("model", &model_name), ("timestamp", timestamp)],
+            );
+            let response_file = format!("{}/{}{}.md", config.directories.tmp_md, response_name, suffix);
+            fs::write(&response_file, &response)?;
+
+            // Display response
+            println!();
+            println!("{}┌─ {} ─{}", config.ui.color_orange, config.display_name(&model_name), config.ui.color_reset);
+            println!("{}{}{}", config.ui.color_orange, response, config.ui.color_reset);
+
+            let tps = if let Some(usage) = &usage {
+                let tokens = usage.completion_tokens as f32;
+                tokens / elapsed.as_secs_f32()
+            } else {
+                0.0
+            };
+            println!(
+                "{}└─ ({:.1} tps, {:.2}s)─ {}",
+                config.ui.color_orange, tps, elapsed.as_secs_f32(), config.ui.color_reset
+            );
+            println!();
+            Ok(Ok(response))
+        }
+        Err(e) => {
+            // Drop the unanswered prompt so the next round doesn't send two user turns
+            if let Some(last) = session.messages.last().map(|m| m.id) {
+                let _ = session.delete_message(last);
+            }
+            eprintln!(
+                "{}⚠ Error getting response from {}: {}{}",
+                config.ui.color_orange, model_name, e, config.ui.color_reset
+            );
+            // One line, so it fits the italic error section of the results file
+            Ok(Err(format!("{:#}", e).replace('\n', " ")))
+        }
+    }
+}
+
 fn cleanup_old_files(config: &RuboxConfig) -> anyhow::Result<()> {
     let tmp_path = Path::new(&config.directories.tmp_md);
     if !tmp_path.exists() {
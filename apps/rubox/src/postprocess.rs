@@ -0,0 +1,82 @@
+use crate::config::ResponseConfig;
+
+// Cleanup of assistant replies, applied before they are stored in the session
+// and rendered. Some models leave trailing whitespace or stop inside a code
+// block, which would swallow everything after it in the markdown renderer.
+// Each step is a pure function so it can be checked on its own.
+pub fn clean_response(text: &str, options: &ResponseConfig) -> String {
+    let mut text = text.to_string();
+    if options.trim_trailing_whitespace {
+        text = trim_trailing_whitespace(&text);
+    }
+    if options.collapse_blank_lines {
+        text = collapse_blank_lines(&text);
+    }
+    if options.close_code_fences {
+        text = close_code_fences(&text);
+    }
+    text
+}
+
+// Spaces and tabs at the end of every line, and blank lines at the end
+pub fn trim_trailing_whitespace(text: &str) -> String {
+    let lines: Vec<&str> = text.lines().map(str::trim_end).collect();
+    lines.join("\n").trim_end().to_string()
+}
+
+// Runs of blank lines become a single one; code blocks are left as they are
+pub fn collapse_blank_lines(text: &str) -> String {
+    let mut fence = None;
+    let mut previous_blank = false;
+    let mut out = Vec::new();
+    for line in text.lines() {
+        fence = next_fence(fence, line);
+        let blank = line.trim().is_empty();
+        if blank && previous_blank && fence.is_none() {
+            continue;
+        }
+        previous_blank = blank;
+        out.push(line);
+    }
+    let mut collapsed = out.join("\n");
+    if text.ends_with('\n') {
+        collapsed.push('\n');
+    }
+    collapsed
+}
+
+// Append the closing fence of a code block the text leaves open
+pub fn close_code_fences(text: &str) -> String {
+    let open = text.lines().fold(None, next_fence);
+    match open {
+        Some((marker, len)) => {
+            let separator = if text.ends_with('\n') { "" } else { "\n" };
+            format!("{}{}{}", text, separator, marker.to_string().repeat(len))
+        }
+        None => text.to_string(),
+    }
+}
+
+// Fence state after `line`: the open block's marker and length, if any. A
+// block opens with 3+ backticks or tildes (indented at most 3 spaces) and
+// closes with at least as many of the same marker and nothing else.
+fn next_fence(open: Option<(char, usize)>, line: &str) -> Option<(char, usize)> {
+    let indent = line.len() - line.trim_start_matches(' ').len();
+    let trimmed = line.trim_start_matches(' ');
+    let marker = trimmed.chars().next().filter(|c| matches!(c, '`' | '~'));
+    let (Some(marker), true) = (marker, indent <= 3) else {
+        return open;
+    };
+    let len = trimmed.chars().take_while(|&c| c == marker).count();
+    if len < 3 {
+        return open;
+    }
+
+    match open {
+        Some((open_marker, open_len)) => {
+            let closes = marker == open_marker && len >= open_len && trimmed[len..].trim().is_empty();
+            if closes { None } else { open }
+        }
+        None => Some((marker, len)),
+    }
+}
@@ -2,6 +2,49 @@ use std::fs;
 use std::path::Path;
 use anyhow::Result;
 
+// Read a file that must hold UTF-8 text. A binary file (a path mistyped to
+// point at a .gguf or an image) gets a plain error naming `what` it was meant
+// to be, instead of the raw io error.
+pub fn read_text_file(path: &Path, what: &str) -> Result<String> {
+    let bytes = fs::read(path).map_err(|e| anyhow::anyhow!("Failed to read {} {}: {}", what, path.display(), e))?;
+    String::from_utf8(bytes)
+        .map_err(|_| anyhow::anyhow!("{} is not valid UTF-8 text: {}", capitalize(what), path.display()))
+}
+
+// Picked up as the first prompt when none is given on the command line
+pub const PROMPT_INPUT_FILE: &str = "prompt_input.txt";
+
+// The first prompt, by precedence: `prompt` (--prompt), then `prompt_file`
+// (--prompt-file), which must exist and hold text, then `input_file` if it
+// holds any. None leaves asking interactively to the caller.
+pub fn initial_prompt(prompt: Option<&str>, prompt_file: Option<&Path>, input_file: &Path) -> Result<Option<String>> {
+    if let Some(prompt) = prompt {
+        return Ok(Some(prompt.to_string()));
+    }
+    if let Some(path) = prompt_file {
+        if !path.is_file() {
+            anyhow::bail!("Prompt file not found: {}", path.display());
+        }
+        let content = read_text_file(path, "prompt file")?;
+        if content.trim().is_empty() {
+            anyhow::bail!("Prompt file is empty: {}", path.display());
+        }
+        return Ok(Some(content.trim().to_string()));
+    }
+    if input_file.is_file() {
+        let content = read_text_file(input_file, "prompt file")?;
+        if !content.trim().is_empty() {
+            return Ok(Some(content.trim().to_string()));
+        }
+    }
+    Ok(None)
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    chars.next().map(|first| first.to_uppercase().chain(chars).collect()).unwrap_or_default()
+}
+
 pub struct PromptManager {
     prompts_dir: String,
 }
@@ -37,7 +80,7 @@ impl PromptManager {
 
     pub fn load_prompt(&self, name: &str) -> Result<String> {
         let path = Path::new(&self.prompts_dir).join(format!("{}.txt", name));
-        Ok(fs::read_to_string(path)?)
+        read_text_file(&path, "prompt file")
     }
 
     #[allow(dead_code)]
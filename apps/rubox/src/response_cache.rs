@@ -0,0 +1,92 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::llm_client::Usage;
+
+// On-disk cache of deterministic (temperature 0) completions: one JSON file per
+// request, named after a hash of the serialized request body. Opt-in via
+// `llm.response_cache`; entries never expire, `/cache clear` drops them.
+pub const CACHE_DIR: &str = "response_cache";
+
+#[derive(Serialize, Deserialize)]
+struct CachedResponse {
+    content: String,
+    usage: Option<Usage>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ResponseCache {
+    dir: PathBuf,
+}
+
+impl ResponseCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        ResponseCache { dir: dir.into() }
+    }
+
+    // Lives under the output directory so cleanup and backups treat it like
+    // the other generated files
+    pub fn dir_for(output_dir: &str) -> PathBuf {
+        Path::new(output_dir).join(CACHE_DIR)
+    }
+
+    // FNV-1a rather than std's hasher, whose output may change between Rust
+    // releases and would silently invalidate the cache
+    pub fn key(request_body: &str) -> String {
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        for byte in request_body.bytes() {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+        format!("{:016x}", hash)
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+
+    // A missing or unreadable entry is a miss, never an error
+    pub fn get(&self, key: &str) -> Option<(String, Option<Usage>)> {
+        let content = fs::read_to_string(self.path(key)).ok()?;
+        let cached: CachedResponse = serde_json::from_str(&content).ok()?;
+        Some((cached.content, cached.usage))
+    }
+
+    pub fn put(&self, key: &str, content: &str, usage: Option<&Usage>) -> anyhow::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let cached = CachedResponse {
+            content: content.to_string(),
+            usage: usage.cloned(),
+        };
+        fs::write(self.path(key), serde_json::to_string(&cached)?)?;
+        Ok(())
+    }
+
+    fn entries(&self) -> Vec<PathBuf> {
+        fs::read_dir(&self.dir)
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok().map(|e| e.path()))
+                    .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // Remove every entry, returning how many were dropped
+    pub fn clear(&self) -> anyhow::Result<usize> {
+        let entries = self.entries();
+        for path in &entries {
+            fs::remove_file(path)?;
+        }
+        Ok(entries.len())
+    }
+}
@@ -0,0 +1,124 @@
+use std::path::Path;
+use crate::config::{scan_models_in, RuboxConfig};
+
+// Model name that asks rubox to pick one from the prompt (`--model auto`)
+pub const AUTO_MODEL: &str = "auto";
+
+// What a router knows about each model it may pick
+#[derive(Debug, Clone)]
+pub struct ModelInfo {
+    pub name: String,
+    pub vision: bool,             // Has an mmproj, or is named like a vision model
+    pub size_bytes: Option<u64>,  // GGUF file size, a stand-in for speed; None if the file is missing
+    pub context_window: u32,
+}
+
+// A router's pick and the reason shown to the user
+#[derive(Debug, Clone, PartialEq)]
+pub struct Route {
+    pub model: String,
+    pub reason: String,
+}
+
+// Picks a model for a prompt, or None to leave it to the default. Swap in
+// another function to change the policy.
+pub type Router = fn(&str, &[ModelInfo]) -> Option<Route>;
+
+// Name hints, most preferred first, for when file sizes don't settle it
+const FAST_HINTS: [&str; 6] = ["lfm", "gemma", "phi", "mini", "small", "tiny"];
+const REASONING_HINTS: [&str; 5] = ["128k", "think", "reason", "qwq", "r1"];
+const IMAGE_EXTENSIONS: [&str; 6] = [".png", ".jpg", ".jpeg", ".webp", ".gif", ".bmp"];
+const COMPLEX_WORDS: [&str; 10] = [
+    "step by step", "prove", "analyze", "analyse", "design", "debug", "refactor", "algorithm", "optimiz", "trade-off",
+];
+// Up to this long, a one-line prompt without complex wording counts as a quick question
+const SHORT_PROMPT_CHARS: usize = 200;
+// From this long on, a prompt counts as complex whatever it says
+const LONG_PROMPT_CHARS: usize = 1200;
+
+// Models `--model auto` can choose from, with what the default router looks at
+pub fn model_infos(config: &RuboxConfig, models_dir: &Path) -> Vec<ModelInfo> {
+    let scanned = scan_models_in(models_dir);
+    config
+        .available_models(models_dir)
+        .into_iter()
+        .map(|name| {
+            let params = config.get_model_params(&name);
+            let path = config
+                .models
+                .registry
+                .get(&name)
+                .cloned()
+                .or_else(|| scanned.iter().find(|(scanned, _)| *scanned == name).map(|(_, path)| path.clone()));
+            let lower = name.to_lowercase();
+            ModelInfo {
+                vision: params.mmproj.is_some() || lower.contains("vl") || lower.contains("vision"),
+                size_bytes: path.and_then(|p| std::fs::metadata(p).ok()).map(|m| m.len()),
+                context_window: params.context_window,
+                name,
+            }
+        })
+        .collect()
+}
+
+// Images go to a vision model, quick one-line questions to the smallest
+// model, long or involved prompts to a reasoning model. Anything in between,
+// or a case without a suitable model, is left to the default.
+pub fn default_router(prompt: &str, models: &[ModelInfo]) -> Option<Route> {
+    if let Some(image) = prompt
+        .split_whitespace()
+        .map(|word| word.trim_matches(|c: char| matches!(c, '"' | '\'' | '(' | ')' | ',' | ';' | '?' | '!' | ':' | '.')))
+        .find(|word| IMAGE_EXTENSIONS.iter().any(|ext| word.to_lowercase().ends_with(ext)))
+    {
+        let model = models.iter().find(|m| m.vision)?;
+        return Some(Route { model: model.name.clone(), reason: format!("the prompt refers to an image ({})", image) });
+    }
+
+    let chars = prompt.chars().count();
+    let lower = prompt.to_lowercase();
+    let complex_word = COMPLEX_WORDS.iter().find(|word| lower.contains(*word));
+    if chars >= LONG_PROMPT_CHARS || prompt.contains("```") || complex_word.is_some() {
+        let model = pick(models, &REASONING_HINTS, |a, b| {
+            b.context_window.cmp(&a.context_window).then(b.size_bytes.cmp(&a.size_bytes))
+        })?;
+        let reason = match complex_word {
+            _ if chars >= LONG_PROMPT_CHARS => format!("long prompt ({} characters)", chars),
+            _ if prompt.contains("```") => "the prompt contains code".to_string(),
+            Some(word) => format!("the prompt asks to {}", word),
+            None => "complex prompt".to_string(),
+        };
+        return Some(Route { model: model.name.clone(), reason: format!("{}: reasoning model", reason) });
+    }
+
+    if chars <= SHORT_PROMPT_CHARS && !prompt.trim().contains('\n') {
+        let text_models: Vec<ModelInfo> = models.iter().filter(|m| !m.vision).cloned().collect();
+        let model = pick(&text_models, &FAST_HINTS, |a, b| {
+            a.size_bytes.unwrap_or(u64::MAX).cmp(&b.size_bytes.unwrap_or(u64::MAX))
+        })?;
+        return Some(Route {
+            model: model.name.clone(),
+            reason: format!("short question ({} characters): fast model", chars),
+        });
+    }
+    None
+}
+
+// The model named by the earliest hint, else the first in `order`
+fn pick<'a>(
+    models: &'a [ModelInfo],
+    hints: &[&str],
+    order: impl Fn(&ModelInfo, &ModelInfo) -> std::cmp::Ordering,
+) -> Option<&'a ModelInfo> {
+    hints
+        .iter()
+        .find_map(|hint| models.iter().find(|m| m.name.to_lowercase().contains(hint)))
+        .or_else(|| models.iter().min_by(|a, b| order(a, b)))
+}
+
+// Route `prompt` with `router`, falling back to `default_model`
+pub fn choose_model(prompt: &str, models: &[ModelInfo], router: Router, default_model: &str) -> Route {
+    router(prompt, models).unwrap_or_else(|| Route {
+        model: default_model.to_string(),
+        reason: "no strong signal in the prompt: default model".to_string(),
+    })
+}
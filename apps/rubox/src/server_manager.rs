@@ -1,36 +1,69 @@
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::process::{Command, Child, Stdio};
 use std::time::Duration;
 use std::net::TcpStream;
 use std::io::{self, Write};
-use crate::config::RuboxConfig;
+use serde::{Deserialize, Serialize};
+use crate::config::{RuboxConfig, MODELS_DIR};
+use crate::ui::{ansi, CLEAR_LINE};
+
+pub const SERVER_PATH: &str = "./third_party/llama.cpp/build/bin/llama-server";
 
 pub struct ServerManager {
     child: Option<Child>,
+    pid_file: Option<PathBuf>,  // Written on spawn so `rubox --stop-server` can find a kept server
+    pub context_override: Option<u32>,  // Session-level --ctx-size, set by /set ctx
+    pub keep_on_exit: bool,  // Detach instead of killing the server when dropped
+}
+
+// Next to the other generated files; describes the last spawned server
+pub const PID_FILE: &str = "llama-server.pid";
+
+pub fn pid_file_path(output_dir: &str) -> PathBuf {
+    Path::new(output_dir).join(PID_FILE)
+}
+
+// Contents of the pid file: enough for a later run to tell whether the server
+// on the port is one rubox started, and with which model
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ServerRecord {
+    pub pid: u32,
+    pub port: u16,
+    pub model: String,  // Model file path the server was started with
+}
+
+impl ServerRecord {
+    pub fn read(output_dir: &str) -> Option<Self> {
+        serde_json::from_str(&fs::read_to_string(pid_file_path(output_dir)).ok()?).ok()
+    }
+
+    pub fn write(&self, output_dir: &str) -> anyhow::Result<()> {
+        fs::write(pid_file_path(output_dir), serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    // Whether the recorded process is still a llama-server
+    pub fn is_alive(&self) -> bool {
+        server_args(self.pid).is_some()
+    }
 }
 
 impl ServerManager {
     pub fn new() -> Self {
-        ServerManager { child: None }
+        ServerManager { child: None, pid_file: None, context_override: None, keep_on_exit: false }
     }
 
-    pub async fn ensure_running(&mut self, config: &RuboxConfig, model_override: Option<&str>) -> anyhow::Result<()> {
-        // Parse port from API URL
-        let port = config.llm.api_url
-            .split(':')
-            .last()
-            .and_then(|s| s.split('/').next())
-            .and_then(|s| s.parse::<u16>().ok())
-            .unwrap_or(8081);
-
-        // Check if something is already listening
-        if is_server_running(port).await {
-            return Ok(());
-        }
+    // Pid of the server this manager spawned, if it is still attached
+    pub fn pid(&self) -> Option<u32> {
+        self.child.as_ref().map(Child::id)
+    }
 
-        println!();
-        println!("{}═══════════════════════════════════════{}", config.ui.color_orange, config.ui.color_reset);
-        println!("{}   Initializing LLM Engine...{}", config.ui.color_orange, config.ui.color_reset);
-        println!("{}═══════════════════════════════════════{}", config.ui.color_orange, config.ui.color_reset);
+    // Takes `&mut self`, so two starts can never overlap; callers that hold the
+    // manager (the chat loop) await this inline after cancelling any generation
+    pub async fn ensure_running(&mut self, config: &RuboxConfig, model_override: Option<&str>) -> anyhow::Result<()> {
+        let port = api_port(&config.llm.api_url);
+        let output_dir = &config.directories.output;
 
         // Determine model path
         let model_path = if let Some(path) = model_override {
@@ -48,7 +81,44 @@ impl ServerManager {
             config.models.default.clone()
         };
 
-        let server_path = "./third_party/llama.cpp/build/bin/llama-server";
+        // A server already on the port is reused, unless an earlier run started
+        // it with a different model. A record whose process is gone is stale.
+        let mut record = ServerRecord::read(output_dir);
+        if record.as_ref().is_some_and(|r| r.port != port || !r.is_alive()) {
+            let _ = fs::remove_file(pid_file_path(output_dir));
+            record = None;
+        }
+        if is_server_running(port).await {
+            // Anything else on the port would answer chat requests with cryptic errors
+            if !is_llama_server(port).await {
+                anyhow::bail!(
+                    "Port {} is in use by another service, not llama-server. Stop it, or point llm.api_url in rubox_config.json at a free port (e.g. http://127.0.0.1:{}/v1)",
+                    port,
+                    port.saturating_add(1)
+                );
+            }
+            match record {
+                Some(record) if record.model != model_path => {
+                    tracing::info!(pid = record.pid, loaded = %record.model, requested = %model_path, "restarting kept llama-server for another model");
+                    terminate(record.pid).await?;
+                    let _ = fs::remove_file(pid_file_path(output_dir));
+                }
+                _ => {
+                    tracing::debug!(port, "llama-server already listening");
+                    return Ok(());
+                }
+            }
+        }
+
+        // Nothing to launch unless a model file was named directly
+        if model_override.is_none_or(|name| !name.contains('/') && !name.contains('.')) {
+            config.require_models(std::path::Path::new(MODELS_DIR))?;
+        }
+
+        println!();
+        println!("{}═══════════════════════════════════════{}", config.ui.color_orange, config.ui.color_reset);
+        println!("{}   Initializing LLM Engine...{}", config.ui.color_orange, config.ui.color_reset);
+        println!("{}═══════════════════════════════════════{}", config.ui.color_orange, config.ui.color_reset);
 
         // Get model-specific parameters
         let model_key = if let Some(override_name) = model_override {
@@ -65,39 +135,65 @@ impl ServerManager {
             "qwen3-vl".to_string()
         };
 
-        let model_params = config.get_model_params(&model_key);
+        let mut model_params = config.get_model_params(&model_key);
+        if let Some(context_window) = self.context_override {
+            model_params.context_window = context_window;
+        }
 
         // Build command with model-specific parameters
-        let mut cmd = Command::new(server_path);
-        cmd.args(&[
+        let mut cmd = Command::new(SERVER_PATH);
+        cmd.args([
             "--model", &model_path,
             "--ctx-size", &model_params.context_window.to_string(),
             "--port", &port.to_string(),
             "--n-gpu-layers", &model_params.gpu_layers.to_string(),
-            "--parallel", "4",
+            "--parallel", &model_params.parallel.to_string(),
             "--batch-size", &model_params.batch_size.to_string(),
             "--ubatch-size", &model_params.ubatch_size.to_string(),
             "--log-disable"
         ]);
 
         if let Some(ctk) = &model_params.cache_type_k {
-             cmd.args(&["--cache-type-k", ctk]);
+             cmd.args(["--cache-type-k", ctk]);
         }
         if let Some(ctv) = &model_params.cache_type_v {
-             cmd.args(&["--cache-type-v", ctv]);
+             cmd.args(["--cache-type-v", ctv]);
+        }
+
+        if let Some(template) = &model_params.chat_template {
+            cmd.args(["--chat-template", template]);
         }
 
         // Add vision model projection if present
         if let Some(mmproj_path) = &model_params.mmproj {
             if std::path::Path::new(mmproj_path).exists() {
-                cmd.args(&["--mmproj", mmproj_path]);
+                cmd.args(["--mmproj", mmproj_path]);
             }
         }
 
+        tracing::info!(
+            model = %model_path,
+            profile = %model_key,
+            port,
+            ctx_size = model_params.context_window,
+            gpu_layers = model_params.gpu_layers,
+            "spawning llama-server"
+        );
+        // Its own process group keeps a kept server out of the terminal's job signals
+        #[cfg(unix)]
+        std::os::unix::process::CommandExt::process_group(&mut cmd, 0);
         let child = cmd
+            .stdin(Stdio::null())
             .stdout(Stdio::null())
             .stderr(Stdio::null())
             .spawn()?;
+        let record = ServerRecord { pid: child.id(), port, model: model_path.clone() };
+        if let Err(e) = record.write(output_dir) {
+            tracing::warn!(error = %e, "could not write the llama-server pid file");
+        }
+        self.pid_file = Some(pid_file_path(output_dir));
+        // Held from the start so a failed launch is cleaned up by stop()
+        self.child = Some(child);
 
         // Progress simulation
         let states = ["Loading Config", "Allocating Context", "Loading Model Weights", "Warming Up"];
@@ -108,7 +204,7 @@ impl ServerManager {
             let filled = (i as f32 / total_steps as f32 * 20.0) as usize;
             let bar: String = "█".repeat(filled) + &"░".repeat(20_usize.saturating_sub(filled));
 
-            let state_idx = (i as usize * states.len()) / total_steps;
+            let state_idx = (i * states.len()) / total_steps;
             let current_state = states[state_idx.min(states.len() - 1)];
 
             print!("\r   {}[{}] {}% - {}...{}", config.ui.color_orange, bar, percentage, current_state, config.ui.color_reset);
@@ -117,76 +213,238 @@ impl ServerManager {
             tokio::time::sleep(Duration::from_millis(250)).await;
         }
 
-        // Wait for server to be ready
-        print!("\r\x1b[K"); // Clear line
+        // Wait for server to be ready, backing off so a struggling server isn't hammered
+        print!("\r{}", ansi(CLEAR_LINE)); // Clear line
         let start = std::time::Instant::now();
-        let max_wait = 180; // 3 minutes for large models
+        let max_wait = config.llm.server_start_timeout_secs;
+        let health_client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(2))
+            .build()?;
+        let mut delay = READY_POLL_MIN;
 
         loop {
-            tokio::time::sleep(Duration::from_millis(200)).await;
-
-            // Check if server is responding on port
-            if is_server_running(port).await {
-                // Try a simple health check with generous timeout
-                let client = reqwest::Client::builder()
-                    .timeout(Duration::from_secs(10))
-                    .build()?;
-
-                match client.get(format!("http://127.0.0.1:{}/health", port)).send().await {
-                    Ok(_) => {
-                        // Server responded - wait longer for large models to fully initialize
-                        tokio::time::sleep(Duration::from_secs(5)).await;
-                        print!("\r\x1b[K");
-                        println!();
-                        break;
-                    }
-                    Err(_) => {
-                        // Health check failed, but server is listening - might still be loading
-                        // Continue waiting
-                    }
+            tokio::time::sleep(with_jitter(delay)).await;
+            delay = (delay * 2).min(READY_POLL_MAX);
+
+            let status = match readiness(&health_client, port).await {
+                Readiness::Ready => {
+                    print!("\r{}", ansi(CLEAR_LINE));
+                    println!();
+                    tracing::info!(wait_secs = start.elapsed().as_secs_f32(), "llama-server ready");
+                    break;
                 }
-            }
+                Readiness::Loading => "Loading model",
+                Readiness::NotListening => "Waiting for server to start",
+            };
 
-            // Show progress dots
             let elapsed = start.elapsed().as_secs();
-            if elapsed % 10 == 0 {
-                print!(".");
-                let _ = io::stdout().flush();
-            }
+            print!("\r{}   {}{}... {}s{}", ansi(CLEAR_LINE), config.ui.color_orange, status, elapsed, config.ui.color_reset);
+            let _ = io::stdout().flush();
 
             if elapsed > max_wait {
-                print!("\r\x1b[K");
-                return Err(anyhow::anyhow!("Timeout waiting for llama-server ({}s).", max_wait));
+                print!("\r{}", ansi(CLEAR_LINE));
+                tracing::error!(max_wait, status, "timed out waiting for llama-server");
+                // A server that never got ready would otherwise hold the port and the pid file
+                let _ = self.stop();
+                return Err(anyhow::anyhow!("Timeout waiting for llama-server ({}s, last state: {}).", max_wait, status));
             }
         }
 
-        self.child = Some(child);
         Ok(())
     }
 
     pub fn stop(&mut self) -> anyhow::Result<()> {
         if let Some(mut child) = self.child.take() {
+            tracing::info!(pid = child.id(), "stopping llama-server");
             let _ = child.kill();
+            let _ = child.wait();
+            if let Some(pid_file) = self.pid_file.take() {
+                let _ = fs::remove_file(pid_file);
+            }
         }
         Ok(())
     }
 }
 
+// `rubox --stop-server`: stop the server a --keep-server run left behind. The
+// pid file names it; without one, the llama-server started on the configured
+// port is looked up instead. Returns the stopped pid, or None if there was none.
+pub async fn stop_kept_server(config: &RuboxConfig) -> anyhow::Result<Option<u32>> {
+    let recorded = ServerRecord::read(&config.directories.output)
+        // A server that died on its own may have had its pid reused by something else
+        .filter(ServerRecord::is_alive)
+        .map(|record| record.pid);
+    let _ = fs::remove_file(pid_file_path(&config.directories.output));
+
+    let port = api_port(&config.llm.api_url);
+    let Some(pid) = recorded.or_else(|| find_server_on_port(port)) else {
+        return Ok(None);
+    };
+    terminate(pid).await?;
+    tracing::info!(pid, port, "stopped kept llama-server");
+    Ok(Some(pid))
+}
+
+// Port of the configured API URL, llama-server's default when it names none
+pub fn api_port(api_url: &str) -> u16 {
+    api_url
+        .split(':')
+        .next_back()
+        .and_then(|s| s.split('/').next())
+        .and_then(|s| s.parse::<u16>().ok())
+        .unwrap_or(8081)
+}
+
+// Command-line arguments of `pid` if it is a llama-server. Only Linux exposes
+// them in /proc; elsewhere the process can't be identified, so it is never
+// taken for a server (a recorded pid may since belong to anything).
+fn server_args(pid: u32) -> Option<Vec<String>> {
+    let cmdline = fs::read(Path::new("/proc").join(pid.to_string()).join("cmdline")).ok()?;
+    let args: Vec<String> = cmdline
+        .split(|&b| b == 0)
+        .map(|arg| String::from_utf8_lossy(arg).into_owned())
+        .collect();
+    args.first().is_some_and(|program| program.ends_with("llama-server")).then_some(args)
+}
+
+// The llama-server process started with `--port <port>`, if any
+fn find_server_on_port(port: u16) -> Option<u32> {
+    let port = port.to_string();
+    fs::read_dir("/proc")
+        .ok()?
+        .flatten()
+        .filter_map(|entry| entry.file_name().to_str()?.parse::<u32>().ok())
+        .find(|&pid| {
+            server_args(pid).is_some_and(|args| args.windows(2).any(|pair| pair[0] == "--port" && pair[1] == port))
+        })
+}
+
+// How long a server gets to exit after the polite request before it is killed
+const STOP_GRACE: Duration = Duration::from_secs(5);
+
+// SIGTERM first so llama-server can shut down cleanly, SIGKILL if it doesn't.
+// Callers are async (model switches, exit), so the grace period must not
+// hold a runtime worker.
+async fn terminate(pid: u32) -> anyhow::Result<()> {
+    let pid = pid.to_string();
+    let signal = |force: bool| -> io::Result<bool> {
+        let status = if cfg!(windows) {
+            // Console programs don't get a close request on Windows, so force it right away
+            Command::new("taskkill").args(["/PID", pid.as_str(), "/F"]).stdout(Stdio::null()).status()?
+        } else {
+            Command::new("kill").args([if force { "-KILL" } else { "-TERM" }, pid.as_str()]).status()?
+        };
+        Ok(status.success())
+    };
+    let alive = || {
+        if cfg!(windows) {
+            return false;
+        }
+        Command::new("kill")
+            .args(["-0", pid.as_str()])
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success())
+    };
+
+    if !signal(false)? {
+        // Already gone
+        return Ok(());
+    }
+    let deadline = std::time::Instant::now() + STOP_GRACE;
+    while alive() {
+        if std::time::Instant::now() >= deadline {
+            signal(true)?;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    Ok(())
+}
+
+impl Default for ServerManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Drop for ServerManager {
     fn drop(&mut self) {
-        if let Some(mut child) = self.child.take() {
-            let _ = child.kill();
+        if self.keep_on_exit {
+            // Dropping a Child doesn't kill it; the next launch finds the port busy and reuses it
+            if let Some(child) = self.child.take() {
+                tracing::info!(pid = child.id(), "leaving llama-server running");
+            }
+            return;
+        }
+        let _ = self.stop();
+    }
+}
+
+// How long the llama-server identity probe waits for each answer
+const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+// Readiness polling interval: doubles from MIN up to MAX
+const READY_POLL_MIN: Duration = Duration::from_millis(200);
+const READY_POLL_MAX: Duration = Duration::from_secs(3);
+
+enum Readiness {
+    NotListening,  // Connection refused: the process hasn't opened its port yet
+    Loading,       // Listening, but /health isn't 200 yet (503 while the model loads)
+    Ready,
+}
+
+async fn readiness(client: &reqwest::Client, port: u16) -> Readiness {
+    if !is_server_running(port).await {
+        return Readiness::NotListening;
+    }
+    match client.get(format!("http://127.0.0.1:{}/health", port)).send().await {
+        Ok(res) if res.status().is_success() => Readiness::Ready,
+        _ => Readiness::Loading,
+    }
+}
+
+// Scale `delay` by a factor in [0.75, 1.25). The clock's sub-second nanos
+// are random enough to keep retries from lining up.
+fn with_jitter(delay: Duration) -> Duration {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or(0);
+    let factor = 0.75 + (nanos % 1000) as f64 / 2000.0;
+    delay.mul_f64(factor)
+}
+
+// Whether what listens on `port` answers like llama-server: /health returns
+// {"status": ...} once ready and a JSON error with 503 while the model loads,
+// and /props describes the generation settings. Web servers that answer every
+// path with a page fail all of these.
+pub async fn is_llama_server(port: u16) -> bool {
+    let Ok(client) = reqwest::Client::builder().timeout(PROBE_TIMEOUT).build() else {
+        return false;
+    };
+    let json = |path: &'static str| {
+        let client = &client;
+        async move {
+            let res = client.get(format!("http://127.0.0.1:{}{}", port, path)).send().await.ok()?;
+            res.json::<serde_json::Value>().await.ok()
+        }
+    };
+
+    if let Some(health) = json("/health").await {
+        if health.get("status").is_some() || health.pointer("/error/code").is_some() {
+            return true;
         }
     }
+    json("/props")
+        .await
+        .is_some_and(|props| props.get("default_generation_settings").is_some())
 }
 
 async fn is_server_running(port: u16) -> bool {
-    match TcpStream::connect_timeout(
+    TcpStream::connect_timeout(
         &format!("127.0.0.1:{}", port).parse().unwrap(),
         Duration::from_millis(200),
-    ) {
-        Ok(_) => true,
-        Err(_) => false,
-    }
+    ).is_ok()
 }
 
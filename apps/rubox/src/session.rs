@@ -1,7 +1,20 @@
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc, Local};
-use std::fs;
-use std::path::Path;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use crate::llm_client::{OutputFormat, ThinkLevel};
+
+// Session ids are `session_` + a `DDMMYYYY_HHMMSS` timestamp, then an optional `_label`
+const ID_PREFIX: &str = "session_";
+const ID_TIMESTAMP_LEN: usize = "DDMMYYYY_HHMMSS".len();
+
+// Messages moved out of memory by `archive_overflow`, one JSON object per line
+pub const ARCHIVE_FILE: &str = "archive.jsonl";
+
+// Chat template tokens (role markers, separators) added around each message
+const MESSAGE_OVERHEAD_TOKENS: u32 = 4;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ChatMessage {
@@ -11,8 +24,59 @@ pub struct ChatMessage {
     pub timestamp: DateTime<Utc>,
     #[allow(dead_code)]
     pub tokens: Option<u32>,    // Token count if available
+    #[serde(default)]
+    pub model: Option<String>,  // Model that produced an assistant message
+    #[serde(default)]
+    pub temperature: Option<f32>, // Temperature used for an assistant message
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub variants: Vec<String>,  // Every answer /retry produced for this turn; empty until the first retry
+    #[serde(default)]
+    pub selected: usize,        // Index of the variant mirrored in `content`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rating: Option<i8>,     // RATING_GOOD or RATING_BAD, for curating exports
+}
+
+// Where a saved message came from: what `/save --meta` writes alongside it
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct MessageProvenance {
+    pub session: String,
+    pub message: usize,
+    pub role: String,
+    pub model: Option<String>,
+    pub temperature: Option<f32>,
+    pub timestamp: DateTime<Utc>,
+    pub tokens: Option<u32>,  // Completion tokens the server reported for an answer
+}
+
+impl MessageProvenance {
+    // YAML front-matter block, ending in the blank line before the content.
+    // Strings are written JSON-quoted, which YAML reads as-is.
+    pub fn front_matter(&self) -> String {
+        let quote = |s: &str| serde_json::to_string(s).unwrap_or_default();
+        let mut lines = vec![
+            "---".to_string(),
+            format!("session: {}", quote(&self.session)),
+            format!("message: {}", self.message),
+            format!("role: {}", self.role),
+        ];
+        if let Some(model) = &self.model {
+            lines.push(format!("model: {}", quote(model)));
+        }
+        if let Some(temperature) = self.temperature {
+            lines.push(format!("temperature: {}", temperature));
+        }
+        lines.push(format!("timestamp: {}", self.timestamp.to_rfc3339()));
+        if let Some(tokens) = self.tokens {
+            lines.push(format!("tokens: {}", tokens));
+        }
+        lines.push("---".to_string());
+        lines.join("\n") + "\n\n"
+    }
 }
 
+pub const RATING_GOOD: i8 = 1;
+pub const RATING_BAD: i8 = -1;
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct SessionMetadata {
     pub id: String,             // session_DDMMYYYY_HHMMSS[_label]
@@ -24,18 +88,38 @@ pub struct SessionMetadata {
     #[allow(dead_code)]
     pub total_tokens: u32,
     pub label: Option<String>,
+    #[serde(default)]
+    pub next_id: usize,         // Next message ID to assign (ids are never reused)
+    #[serde(default)]
+    pub context_window: Option<u32>,  // Context size chosen for this session (/set ctx, --ctx-size)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,           // /set top_p
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,      // /set max_tokens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub candidate_count: Option<u32>, // /set n
+    #[serde(default, skip_serializing_if = "OutputFormat::is_text")]
+    pub output_format: OutputFormat,  // /set format, /set grammar (the grammar source, not its path)
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub logit_bias: HashMap<u32, f32>,  // /bias
+    #[serde(default, skip_serializing_if = "ThinkLevel::is_default")]
+    pub think: ThinkLevel,              // /set think
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Session {
     pub metadata: SessionMetadata,
     pub messages: Vec<ChatMessage>,
+    #[serde(skip)]
+    saved_id: Option<String>,  // Directory name on disk, if saved; differs from the id after a rename
+    #[serde(skip)]
+    regenerating: Option<usize>,  // Answer /retry is replacing; the next reply becomes a variant of it
 }
 
 impl Session {
     pub fn new(model_name: String, temperature: f32) -> Self {
         let timestamp = Local::now().format("%d%m%Y_%H%M%S").to_string();
-        let id = format!("session_{}", timestamp);
+        let id = format!("{}{}", ID_PREFIX, timestamp);
 
         Session {
             metadata: SessionMetadata {
@@ -47,19 +131,43 @@ impl Session {
                 message_count: 0,
                 total_tokens: 0,
                 label: None,
+                next_id: 1,
+                context_window: None,
+                top_p: None,
+                max_tokens: None,
+                candidate_count: None,
+                output_format: OutputFormat::Text,
+                logit_bias: HashMap::new(),
+                think: ThinkLevel::Default,
             },
             messages: Vec::new(),
+            saved_id: None,
+            regenerating: None,
         }
     }
 
     pub fn add_message(&mut self, role: String, content: String, tokens: Option<u32>) {
-        let id = self.messages.len() + 1;
+        let id = self.metadata.next_id.max(1);
+        self.metadata.next_id = id + 1;
+
+        // Snapshot the active model/temperature so mid-session switches stay traceable
+        let (model, temperature) = if role == "assistant" {
+            (Some(self.metadata.model_name.clone()), Some(self.metadata.temperature))
+        } else {
+            (None, None)
+        };
+
         self.messages.push(ChatMessage {
             id,
             role,
             content,
             timestamp: Utc::now(),
             tokens,
+            model,
+            temperature,
+            variants: Vec::new(),
+            selected: 0,
+            rating: None,
         });
         self.metadata.message_count = self.messages.len();
         self.metadata.last_modified = Utc::now();
@@ -68,16 +176,28 @@ impl Session {
         }
     }
 
-    pub fn save(&self, base_dir: &str) -> anyhow::Result<()> {
+    // Write the session under `<base_dir>/<id>`. After a rename the existing
+    // directory is moved, so each session keeps exactly one directory.
+    pub fn save(&mut self, base_dir: &str) -> anyhow::Result<()> {
         let session_dir = Path::new(base_dir).join(&self.metadata.id);
+        if let Some(old_id) = self.saved_id.as_deref().filter(|old| *old != self.metadata.id) {
+            let old_dir = Path::new(base_dir).join(old_id);
+            if old_dir.exists() {
+                if session_dir.exists() {
+                    anyhow::bail!("a session named '{}' already exists", self.metadata.id);
+                }
+                fs::rename(&old_dir, &session_dir)?;
+            }
+        }
         fs::create_dir_all(&session_dir)?;
 
         let metadata_path = session_dir.join("metadata.json");
         let messages_path = session_dir.join("messages.json");
 
-        fs::write(metadata_path, serde_json::to_string_pretty(&self.metadata)?)?;
-        fs::write(messages_path, serde_json::to_string_pretty(&self.messages)?)?;
+        write_atomic(&metadata_path, &serde_json::to_string_pretty(&self.metadata)?)?;
+        write_atomic(&messages_path, &serde_json::to_string_pretty(&self.messages)?)?;
 
+        self.saved_id = Some(self.metadata.id.clone());
         Ok(())
     }
 
@@ -85,7 +205,7 @@ impl Session {
     pub fn load(base_dir: &str, session_id: &str) -> anyhow::Result<Self> {
         let session_dir = Path::new(base_dir).join(session_id);
 
-        let metadata: SessionMetadata = serde_json::from_str(
+        let mut metadata: SessionMetadata = serde_json::from_str(
             &fs::read_to_string(session_dir.join("metadata.json"))?
         )?;
 
@@ -93,7 +213,13 @@ impl Session {
             &fs::read_to_string(session_dir.join("messages.json"))?
         )?;
 
-        Ok(Session { metadata, messages })
+        // Sessions saved before next_id existed: seed it past the highest id
+        let max_id = messages.iter().map(|m| m.id).max().unwrap_or(0);
+        if metadata.next_id <= max_id {
+            metadata.next_id = max_id + 1;
+        }
+
+        Ok(Session { metadata, messages, saved_id: Some(session_id.to_string()), regenerating: None })
     }
 
     #[allow(dead_code)]
@@ -118,7 +244,7 @@ impl Session {
         }
 
         // Sort by last modified, newest first
-        sessions.sort_by(|a, b| b.last_modified.cmp(&a.last_modified));
+        sessions.sort_by_key(|s| std::cmp::Reverse(s.last_modified));
 
         Ok(sessions)
     }
@@ -131,17 +257,168 @@ impl Session {
     }
 
     pub fn rename(&mut self, label: String) {
-        self.metadata.label = Some(label.clone());
-        // Update ID to include label
-        let base_parts: Vec<&str> = self.metadata.id.split('_').collect();
-        if base_parts.len() >= 3 {
-            let base_id = format!("{}_{}", base_parts[0], base_parts[1]);
-            self.metadata.id = format!("{}_{}", base_id, label);
+        self.metadata.id = self.id_for_label(&label);
+        self.metadata.label = Some(label);
+        self.metadata.last_modified = Utc::now();
+    }
+
+    // The id (and directory name) the session would have with `label`. Always
+    // built from the unlabeled id, so renaming again replaces the label.
+    pub fn id_for_label(&self, label: &str) -> String {
+        format!("{}_{}", self.base_id(), label)
+    }
+
+    // The id without its label: `session_` plus the timestamp. Ids in another
+    // shape only lose the current label's suffix, if they carry one.
+    fn base_id(&self) -> &str {
+        let id = self.metadata.id.as_str();
+        let base_len = ID_PREFIX.len() + ID_TIMESTAMP_LEN;
+        if id.starts_with(ID_PREFIX) && id.len() >= base_len && id.is_char_boundary(base_len) {
+            return &id[..base_len];
+        }
+        match &self.metadata.label {
+            Some(label) => id.strip_suffix(label.as_str()).and_then(|s| s.strip_suffix('_')).unwrap_or(id),
+            None => id,
+        }
+    }
+
+    // Directory holding the session on disk; after an unsaved rename it is
+    // still the old one (the next save moves it)
+    fn dir(&self, base_dir: &str) -> PathBuf {
+        Path::new(base_dir).join(self.saved_id.as_deref().unwrap_or(&self.metadata.id))
+    }
+
+    // Keep only the newest `max_messages` in memory, appending older ones to
+    // the session's archive.jsonl. Returns how many were archived; 0 = unlimited.
+    pub fn archive_overflow(&mut self, base_dir: &str, max_messages: usize) -> anyhow::Result<usize> {
+        if max_messages == 0 || self.messages.len() <= max_messages {
+            return Ok(0);
+        }
+
+        let dir = self.dir(base_dir);
+        fs::create_dir_all(&dir)?;
+        let overflow = self.messages.len() - max_messages;
+        let mut lines = String::new();
+        for msg in &self.messages[..overflow] {
+            lines.push_str(&serde_json::to_string(msg)?);
+            lines.push('\n');
         }
+        // Written in one call so a failure never leaves messages half-archived
+        let mut file = OpenOptions::new().create(true).append(true).open(dir.join(ARCHIVE_FILE))?;
+        file.write_all(lines.as_bytes())?;
+
+        self.messages.drain(..overflow);
+        self.metadata.message_count = self.messages.len();
+        Ok(overflow)
+    }
+
+    // Messages archived by `archive_overflow`, oldest first
+    pub fn load_archive(&self, base_dir: &str) -> Vec<ChatMessage> {
+        fs::read_to_string(self.dir(base_dir).join(ARCHIVE_FILE))
+            .map(|content| content.lines().filter_map(|line| serde_json::from_str(line).ok()).collect())
+            .unwrap_or_default()
+    }
+
+    // Mark the trailing answer for regeneration. It stays in the history (and
+    // on disk, should the generation never finish) but is left out of
+    // `context_messages` until `add_response` files the new reply next to it.
+    pub fn begin_regenerate(&mut self) -> bool {
+        self.regenerating = self.messages.last().filter(|m| m.role == "assistant").map(|m| m.id);
+        self.regenerating.is_some()
+    }
+
+    // Only while the answer is still the last message: after a cancelled
+    // retry the user may have moved on, and the mark no longer applies
+    fn regenerating(&self) -> Option<usize> {
+        self.regenerating.filter(|id| self.messages.last().is_some_and(|m| m.id == *id))
+    }
+
+    // The conversation to send to the model
+    pub fn context_messages(&self) -> impl Iterator<Item = &ChatMessage> {
+        let skip = self.regenerating();
+        self.messages.iter().filter(move |m| Some(m.id) != skip)
+    }
+
+    // Rough prompt size of the conversation, for when the server hasn't reported one
+    pub fn estimated_tokens(&self) -> u32 {
+        self.context_messages()
+            .map(|m| estimate_tokens(&m.content) + MESSAGE_OVERHEAD_TOKENS)
+            .sum()
+    }
+
+    // Record an assistant reply: a new message, or another variant of the
+    // answer being regenerated, which then shows the new variant
+    pub fn add_response(&mut self, content: String, tokens: Option<u32>) {
+        let Some(id) = self.regenerating() else {
+            self.regenerating = None;
+            self.add_message("assistant".to_string(), content, tokens);
+            return;
+        };
+        self.regenerating = None;
+
+        let (model, temperature) = (self.metadata.model_name.clone(), self.metadata.temperature);
+        if let Some(msg) = self.messages.iter_mut().find(|m| m.id == id) {
+            if msg.variants.is_empty() {
+                msg.variants.push(msg.content.clone());
+            }
+            msg.variants.push(content.clone());
+            msg.selected = msg.variants.len() - 1;
+            msg.content = content;
+            msg.tokens = tokens;
+            msg.timestamp = Utc::now();
+            msg.model = Some(model);
+            msg.temperature = Some(temperature);
+        }
+        self.metadata.last_modified = Utc::now();
+        if let Some(t) = tokens {
+            self.metadata.total_tokens += t;
+        }
+    }
+
+    // Show the next (or previous) variant of a message, wrapping around.
+    // Returns the new 1-based position, or None if there is nothing to cycle.
+    pub fn cycle_variant(&mut self, id: usize, forward: bool) -> Option<usize> {
+        let msg = self.messages.iter_mut().find(|m| m.id == id)?;
+        let count = msg.variants.len();
+        if count < 2 {
+            return None;
+        }
+        msg.selected = if forward { (msg.selected + 1) % count } else { (msg.selected + count - 1) % count };
+        msg.content = msg.variants[msg.selected].clone();
+        self.metadata.last_modified = Utc::now();
+        Some(msg.selected + 1)
+    }
+
+    // Replace a message's text; for a regenerated answer only the variant on show changes
+    pub fn edit_message(&mut self, id: usize, content: String) -> anyhow::Result<()> {
+        let Some(msg) = self.messages.iter_mut().find(|m| m.id == id) else {
+            anyhow::bail!("Message {} not found", id);
+        };
+        if let Some(variant) = msg.variants.get_mut(msg.selected) {
+            *variant = content.clone();
+        }
+        msg.content = content;
+        self.metadata.last_modified = Utc::now();
+        Ok(())
+    }
+
+    // Rate an assistant message; None clears the rating
+    pub fn rate(&mut self, id: usize, rating: Option<i8>) -> anyhow::Result<()> {
+        let Some(msg) = self.messages.iter_mut().find(|m| m.id == id) else {
+            anyhow::bail!("Message {} not found", id);
+        };
+        if msg.role != "assistant" {
+            anyhow::bail!("Only assistant messages can be rated");
+        }
+        msg.rating = rating;
         self.metadata.last_modified = Utc::now();
+        Ok(())
     }
 
     pub fn delete_message(&mut self, id: usize) -> anyhow::Result<()> {
+        if self.get_message(id).is_none() {
+            anyhow::bail!("Message {} not found", id);
+        }
         self.messages.retain(|m| m.id != id);
         self.metadata.message_count = self.messages.len();
         self.metadata.last_modified = Utc::now();
@@ -158,4 +435,66 @@ impl Session {
     pub fn get_message(&self, id: usize) -> Option<&ChatMessage> {
         self.messages.iter().find(|m| m.id == id)
     }
+
+    pub fn provenance(&self, msg: &ChatMessage) -> MessageProvenance {
+        MessageProvenance {
+            session: self.metadata.id.clone(),
+            message: msg.id,
+            role: msg.role.clone(),
+            model: msg.model.clone(),
+            temperature: msg.temperature,
+            timestamp: msg.timestamp,
+            tokens: msg.tokens,
+        }
+    }
+}
+
+// About four characters per token for English text and code; good enough to
+// tell how close a conversation is to the context window
+pub fn estimate_tokens(text: &str) -> u32 {
+    (text.chars().count() as u32).div_ceil(4)
+}
+
+// Separate `<think>...</think>` reasoning (as emitted by Qwen3) from the answer.
+// Multiple blocks are joined; an unterminated block runs to the end of the
+// content. Returns None for the reasoning when there is none (or it's empty).
+pub fn split_thinking(content: &str) -> (Option<String>, String) {
+    const OPEN: &str = "<think>";
+    const CLOSE: &str = "</think>";
+
+    if !content.contains(OPEN) {
+        return (None, content.to_string());
+    }
+
+    let mut thinking = Vec::new();
+    let mut answer = String::new();
+    let mut rest = content;
+    while let Some(start) = rest.find(OPEN) {
+        answer.push_str(&rest[..start]);
+        let inner = &rest[start + OPEN.len()..];
+        match inner.find(CLOSE) {
+            Some(end) => {
+                thinking.push(inner[..end].trim());
+                rest = &inner[end + CLOSE.len()..];
+            }
+            None => {
+                thinking.push(inner.trim());
+                rest = "";
+            }
+        }
+    }
+    answer.push_str(rest);
+
+    let thinking = thinking.into_iter().filter(|t| !t.is_empty()).collect::<Vec<_>>().join("\n\n");
+    let thinking = if thinking.is_empty() { None } else { Some(thinking) };
+    (thinking, answer.trim().to_string())
+}
+
+// Write to a temp file and rename over the target, so an interrupted save
+// never leaves a truncated JSON file behind
+fn write_atomic(path: &Path, contents: &str) -> anyhow::Result<()> {
+    let tmp_path = path.with_extension("json.tmp");
+    fs::write(&tmp_path, contents)?;
+    fs::rename(&tmp_path, path)?;
+    Ok(())
 }
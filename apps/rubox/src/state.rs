@@ -0,0 +1,32 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+// Machine-local state remembered between launches. Kept out of
+// rubox_config.json so the config stays shareable.
+pub const STATE_PATH: &str = ".rubox_state.json";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct AppState {
+    pub last_model: Option<String>,
+    pub last_temperature: Option<f32>,
+}
+
+impl AppState {
+    // Missing or unreadable state is not an error; it just means a fresh start
+    pub fn load() -> Self {
+        fs::read_to_string(STATE_PATH)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        let tmp_path = Path::new(STATE_PATH).with_extension("json.tmp");
+        fs::write(&tmp_path, json)?;
+        fs::rename(&tmp_path, STATE_PATH)?;
+        Ok(())
+    }
+}
@@ -1,5 +1,66 @@
+use std::cell::{Cell, RefCell};
+use std::collections::HashSet;
+use std::time::Instant;
 use crate::session::Session;
-use crate::commands::CommandRegistry;
+use crate::commands::{CommandOutput, CommandRegistry, SettingRow};
+use crate::config::{ResponseConfig, UiConfig};
+use crate::llm_client::{LlmClient, OutputFormat, ThinkLevel};
+
+// Loading spinner animations, selected with `ui.spinner`
+const SPINNER_BRAILLE: &[&str] = &["⠋", "⠙", "⠹", "⠸"];
+const SPINNER_DOTS: &[&str] = &[".  ", ".. ", "...", "   "];
+const SPINNER_BAR: &[&str] = &["|", "/", "-", "\\"];
+const SPINNER_MOON: &[&str] = &["🌑", "🌒", "🌓", "🌔", "🌕", "🌖", "🌗", "🌘"];
+
+pub fn spinner_frames(style: &str) -> Option<&'static [&'static str]> {
+    match style {
+        "braille" => Some(SPINNER_BRAILLE),
+        "dots" => Some(SPINNER_DOTS),
+        "bar" => Some(SPINNER_BAR),
+        "moon" => Some(SPINNER_MOON),
+        _ => None,
+    }
+}
+
+// Pane arrangement, selected with `ui.layout` and toggled with Ctrl+L
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LayoutMode {
+    Auto,     // Compact below COMPACT_HEIGHT rows, full otherwise
+    Compact,  // Single-line input, chat takes the rest
+    Full,     // Chat, boxed input area and status bar
+}
+
+// Terminals shorter than this get the compact layout in Auto mode
+pub const COMPACT_HEIGHT: u16 = 24;
+
+// Longest a typewriter reveal runs; long replies are revealed faster to fit
+const REVEAL_MAX_SECS: u64 = 3;
+
+// Follow-up prompts kept from a suggestions reply; picked with keys 1..=MAX_SUGGESTIONS
+pub const MAX_SUGGESTIONS: usize = 3;
+
+// "1. text", "2) text", "- text", "* text" or "• text" -> "text"
+fn strip_list_marker(line: &str) -> &str {
+    let unnumbered = line.trim_start_matches(|c: char| c.is_ascii_digit());
+    let rest = if unnumbered.len() < line.len() {
+        unnumbered.strip_prefix(['.', ')'])
+    } else {
+        line.strip_prefix(['-', '*', '•'])
+    };
+    match rest {
+        Some(rest) if rest.starts_with(' ') => rest.trim_start(),
+        _ => line,
+    }
+}
+
+pub fn layout_mode(name: &str) -> Option<LayoutMode> {
+    match name {
+        "auto" => Some(LayoutMode::Auto),
+        "compact" => Some(LayoutMode::Compact),
+        "full" => Some(LayoutMode::Full),
+        _ => None,
+    }
+}
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum UIMode {
@@ -16,6 +77,12 @@ pub enum ModalType {
     SaveResponse,
     RenameSession,
     LoadPrompt,
+    ErrorDetails,
+    Confirm,
+    PickCandidate,
+    Settings,
+    DebugRequest,
+    Output,
 }
 
 pub struct App {
@@ -23,34 +90,113 @@ pub struct App {
     pub session: Session,
     pub current_model: String,
     pub temperature: f32,
+    pub default_temperature: f32,  // temperature.default; differing from it counts as custom sampling
+    pub temperature_range: (f32, f32),  // temperature.min and temperature.max, shown by the temperature modal
+    pub verbose: bool,
+    pub undo_snapshot: Option<Session>,  // Session before the last destructive command
+    pub output_format: OutputFormat,     // Set with /set format and /set grammar
+    pub client: LlmClient,               // Active endpoint; replaced by /connect
+    pub can_manage_server: bool,         // llm.manage_server as configured; /connect re-applies it to local endpoints
+    pub candidate_count: u32,            // Replies requested per generation, set with /set n
+    pub logit_bias: std::collections::HashMap<u32, f32>,  // Token id -> bias, set with /bias
+    pub think: ThinkLevel,  // Reasoning effort, set with /set think
+    pub display_names: std::collections::HashMap<String, String>,  // Model key -> profile display_name
+    pub assistant_name: Option<String>,  // ui.assistant_name: labels every reply when set
+    pub response_cleanup: ResponseConfig,  // `response` config: cleanup applied to each reply
+    pub top_p: Option<f32>,              // Set with /set top_p; None leaves it to the server
+    pub max_tokens: u32,                 // Set with /set max_tokens
+    pub context_windows: std::collections::HashMap<String, u32>,  // Model key -> profile context_window
+    // Prompt + reply tokens the server reported for the last answer, with the
+    // message count and modification time they apply to
+    reported_context: Option<(usize, chrono::DateTime<chrono::Utc>, u32)>,
 
     // UI state
     pub mode: UIMode,
     pub input_buffer: String,
+    pub multiline: bool,  // Enter inserts a newline instead of sending (no $EDITOR fallback)
+    pub layout: LayoutMode,
+    pub compact: Cell<bool>,  // Whether the last draw used the compact layout
+    pub chat_percent: u16,  // ui.chat_percent: chat history share of the full layout
+    pub input_min_rows: u16,  // ui.input_min_rows: input rows the full layout keeps at least
     pub command_search: String,
-    pub scroll_offset: usize,
+    // Lines scrolled up from the bottom; a Cell because the draw grows it as
+    // lines arrive below a scrolled-up view
+    pub scroll_offset: Cell<usize>,
+    // Measured on each draw: wrapped line count of the chat at the pane's
+    // width, how many of those lines fit in the pane, and that width
+    pub chat_lines: Cell<usize>,
+    pub chat_viewport: Cell<usize>,
+    pub chat_width: Cell<u16>,
+    pub message_rows: RefCell<Vec<(usize, usize, usize)>>,  // (message id, first wrapped line, line count)
+    pub focused_message_id: Option<usize>,  // Moved with Tab/Shift-Tab; default target of /copy, /save, /delete, /edit, /rate
+    pub editing_message_id: Option<usize>,  // Set by /edit: Enter replaces this message instead of sending
+    pub expanded_thinking: HashSet<usize>,  // Message ids whose <think> block is shown
+    pub follow: bool,          // Stick to the bottom as new messages arrive
+    pub has_new_message: bool, // A message arrived while scrolled up
     pub selected_command_idx: usize,
-    pub selected_model_idx: usize,  // For model selector navigation
+    pub selected_model_idx: usize,  // For model selector navigation, an index into filtered_model_names
+    // First model shown in the selector, and how many fit (measured on each draw)
+    pub model_scroll: Cell<usize>,
+    pub model_viewport: Cell<usize>,
+    pub selected_prompt_idx: usize,  // For the LoadPrompt list
+    pub prompt_list: Vec<(String, String)>,  // (name, content), read when LoadPrompt opens
+    pub settings: Vec<SettingRow>,  // Rows of the Settings modal, refreshed after each change
+    pub selected_setting_idx: usize,
+    pub debug_request: String,  // Pretty JSON shown by /debug request
+    pub debug_scroll: u16,      // Lines scrolled down in the DebugRequest modal
+    pub output: CommandOutput,  // Shown by the Output modal
+    pub output_scroll: u16,     // Lines scrolled down in the Output modal
+    pub transcript_requested: bool,  // /transcript: print the chat once the current event is handled
+    pub theme_reload_requested: bool,  // /reload: parse the (changed) theme colors before the next draw
     pub modal_input: String,
+    pub pending_confirm: Option<(String, String)>,  // (question, command to run on yes)
+    pub candidates: Vec<String>,  // Replies waiting in PickCandidate; not yet in the session
+    pub selected_candidate_idx: usize,
+    pub typewriter_cps: u32,  // ui.typewriter_cps; 0 turns the reveal off
+    pub saved_message: String,  // directories.saved_responses joined with filenames.saved_message
+    reveal: Option<(usize, usize, usize)>,  // (message id, characters shown, characters added per tick)
+    pub suggestions: Vec<String>,  // Follow-up prompts offered under the latest reply (ui.suggestions)
+    suggestions_for: Option<usize>,  // Message id they were generated for
 
     // Runtime state
     pub is_loading: bool,
+    pub generation_started: Option<Instant>,  // Set while waiting on a completion
+    pub generation_task: Option<tokio::task::JoinHandle<()>>,  // In-flight completion, if any
+    generation_temperature: f32,  // Temperature of the in-flight generation
+    pub generation_id: u64,       // Bumped per generation; events from older ones are dropped
+    pub prefill: Option<String>,  // Start of the in-flight reply, set by /continue
+    pub request_timeout_secs: u64,
+    pub streaming_tokens: u32,  // Deltas received for the in-flight generation
+    pub last_delta: Option<Instant>,  // When the latest of those arrived
+    pub benchmark_progress: Option<(usize, usize)>,  // (runs done, runs) while /benchmark runs
+    pub live_tps: f32,          // Tokens received during the last tick interval
+    tokens_at_last_tick: u32,
     pub error_message: Option<String>,
+    pub last_error: Option<String>,  // Full text of the latest error, kept after it clears
     pub last_tps: f32,
     pub last_response_time: f32,
     error_ticks: u32,  // Auto-clear errors after 3 seconds
+    pub status_message: Option<String>,  // Confirmation from the last command
+    status_ticks: u32,  // Cleared like errors
 
     // Command registry
     pub command_registry: CommandRegistry,
 
     // Model registry (name -> full filename path)
     pub model_registry: std::collections::HashMap<String, String>,
+    pub last_model: Option<String>,  // Used in the previous run; pre-selected until a switch
+    pub server_models: Vec<String>,  // Reported by an externally-managed server
+    pub server_alive: Option<bool>,  // Latest /health result; None before the first check
+    pub health_check_pending: bool,
+    pub last_health_check: Option<Instant>,
 
     // Exit flag
     pub should_exit: bool,
 
     // Animation/tick state
     tick_count: u32,
+    pub tick_ms: u64,
+    pub spinner: &'static [&'static str],
 }
 
 impl App {
@@ -58,30 +204,115 @@ impl App {
         model: String,
         temperature: f32,
         model_registry: std::collections::HashMap<String, String>,
+        client: LlmClient,
     ) -> Self {
         App {
             session: Session::new(model.clone(), temperature),
             current_model: model,
             temperature,
+            default_temperature: temperature,
+            temperature_range: (0.0, 2.0),
+            verbose: false,
+            undo_snapshot: None,
+            output_format: OutputFormat::Text,
+            candidate_count: 1,
+            logit_bias: std::collections::HashMap::new(),
+            think: ThinkLevel::Default,
+            display_names: std::collections::HashMap::new(),
+            assistant_name: None,
+            response_cleanup: ResponseConfig::default(),
+            top_p: None,
+            max_tokens: crate::llm_client::DEFAULT_MAX_TOKENS,
+            context_windows: std::collections::HashMap::new(),
+            reported_context: None,
+            client,
+            can_manage_server: true,
             mode: UIMode::Chat,
             input_buffer: String::new(),
+            multiline: false,
+            layout: LayoutMode::Auto,
+            compact: Cell::new(false),
+            chat_percent: UiConfig::default_chat_percent(),
+            input_min_rows: UiConfig::default_input_min_rows(),
             command_search: String::new(),
-            scroll_offset: 0,
+            scroll_offset: Cell::new(0),
+            chat_lines: Cell::new(0),
+            chat_viewport: Cell::new(0),
+            chat_width: Cell::new(0),
+            message_rows: RefCell::new(Vec::new()),
+            focused_message_id: None,
+            editing_message_id: None,
+            expanded_thinking: HashSet::new(),
+            follow: true,
+            has_new_message: false,
             selected_command_idx: 0,
             selected_model_idx: 0,
+            model_scroll: Cell::new(0),
+            model_viewport: Cell::new(0),
+            selected_prompt_idx: 0,
+            prompt_list: Vec::new(),
+            settings: Vec::new(),
+            selected_setting_idx: 0,
+            debug_request: String::new(),
+            debug_scroll: 0,
+            output: CommandOutput::default(),
+            output_scroll: 0,
+            transcript_requested: false,
+            theme_reload_requested: false,
             modal_input: String::new(),
+            pending_confirm: None,
+            candidates: Vec::new(),
+            selected_candidate_idx: 0,
+            typewriter_cps: 0,
+            saved_message: "Chat/saved/{session}_{id}".to_string(),
+            reveal: None,
+            suggestions: Vec::new(),
+            suggestions_for: None,
             is_loading: false,
+            generation_started: None,
+            generation_task: None,
+            generation_temperature: temperature,
+            generation_id: 0,
+            prefill: None,
+            request_timeout_secs: 0,
+            streaming_tokens: 0,
+            last_delta: None,
+            benchmark_progress: None,
+            live_tps: 0.0,
+            tokens_at_last_tick: 0,
             error_message: None,
+            last_error: None,
             last_tps: 0.0,
             last_response_time: 0.0,
             error_ticks: 0,
+            status_message: None,
+            status_ticks: 0,
             command_registry: CommandRegistry::new(),
             model_registry,
+            server_models: Vec::new(),
+            server_alive: None,
+            health_check_pending: false,
+            last_health_check: None,
+            last_model: None,
             should_exit: false,
             tick_count: 0,
+            tick_ms: 250,
+            spinner: SPINNER_BRAILLE,
         }
     }
 
+    // Keep the session metadata in sync so new messages record the active model
+    pub fn set_model(&mut self, model: String) {
+        self.last_model = None;
+        self.session.metadata.model_name = model.clone();
+        self.current_model = model;
+    }
+
+    pub fn set_temperature(&mut self, temperature: f32) {
+        self.session.metadata.temperature = temperature;
+        self.temperature = temperature;
+    }
+
     pub fn open_command_palette(&mut self) {
         self.mode = UIMode::CommandPalette;
         self.command_search.clear();
@@ -95,21 +326,51 @@ impl App {
         self.selected_command_idx = 0;
     }
 
+    pub fn show_debug_request(&mut self, json: String) {
+        self.debug_request = json;
+        self.open_modal(ModalType::DebugRequest);
+    }
+
+    pub fn show_output(&mut self, output: CommandOutput) {
+        self.output = output;
+        self.open_modal(ModalType::Output);
+    }
+
     pub fn open_modal(&mut self, modal: ModalType) {
-        self.selected_command_idx = 0;  // Reset both indices
+        self.selected_command_idx = 0;  // Reset all indices
         self.selected_model_idx = 0;
+        self.selected_prompt_idx = 0;
+        self.selected_candidate_idx = 0;
+        self.selected_setting_idx = 0;
+        self.debug_scroll = 0;
+        self.output_scroll = 0;
+        self.model_scroll.set(0);
+        self.modal_input.clear();
         if matches!(modal, ModalType::ModelSelector) {
-            self.selected_model_idx = 0;  // Ensure model selector starts at top
+            // Start on the previous run's model, else the active one
+            let target = self.last_model.clone().unwrap_or_else(|| self.current_model.clone());
+            self.selected_model_idx = self
+                .filtered_model_names()
+                .iter()
+                .position(|n| *n == target)
+                .unwrap_or(0);
+            self.keep_model_visible();
         }
         self.mode = UIMode::Modal(modal);
-        self.modal_input.clear();
         self.error_message = None;  // Fresh start
     }
 
+    // Ask a yes/no question before running a destructive command
+    pub fn request_confirm(&mut self, question: String, command: String) {
+        self.open_modal(ModalType::Confirm);
+        self.pending_confirm = Some((question, command));
+    }
+
     pub fn close_modal(&mut self) {
         self.mode = UIMode::Chat;
         self.modal_input.clear();
         self.selected_model_idx = 0;
+        self.model_scroll.set(0);
     }
 
     pub fn handle_input_char(&mut self, c: char) {
@@ -120,6 +381,10 @@ impl App {
             UIMode::CommandPalette => {
                 self.command_search.push(c);
             }
+            UIMode::Modal(ModalType::ModelSelector) => {
+                self.modal_input.push(c);
+                self.select_first_model();
+            }
             UIMode::Modal(_) => {
                 self.modal_input.push(c);
             }
@@ -134,6 +399,10 @@ impl App {
             UIMode::CommandPalette => {
                 self.command_search.pop();
             }
+            UIMode::Modal(ModalType::ModelSelector) => {
+                self.modal_input.pop();
+                self.select_first_model();
+            }
             UIMode::Modal(_) => {
                 self.modal_input.pop();
             }
@@ -144,10 +413,28 @@ impl App {
         match self.mode {
             UIMode::Chat => {
                 let input = self.input_buffer.trim().to_string();
-                if !input.is_empty() {
+                if input.is_empty() {
+                    // Only whitespace: nothing to send, nothing to report
                     self.input_buffer.clear();
-                    return Some(input);
+                    return None;
+                }
+                // An edited message is stored as typed, slashes and all
+                let command = input.trim_start_matches('/');
+                if self.editing_message_id.is_none() && command.len() < input.len() {
+                    if command.trim().is_empty() {
+                        // A lone `/` asks for the command list
+                        self.input_buffer.clear();
+                        self.open_command_palette();
+                        return None;
+                    }
+                    if command.starts_with(char::is_whitespace) {
+                        // Left in the box to be fixed rather than sent as a message
+                        self.set_error(format!("No command name after '/' (did you mean /{}?)", command.trim_start()));
+                        return None;
+                    }
                 }
+                self.input_buffer.clear();
+                return Some(input);
             }
             UIMode::CommandPalette => {
                 // User is selecting a command
@@ -188,15 +475,32 @@ impl App {
     pub fn scroll_up(&mut self) {
         match self.mode {
             UIMode::Chat => {
-                // Bound scroll offset to reasonable max (estimate 5 lines per message)
-                let max_scroll = self.session.messages.len().saturating_mul(5);
-                self.scroll_offset = (self.scroll_offset + 3).min(max_scroll);
+                self.scroll_offset.set((self.scroll_offset.get() + 3).min(self.max_scroll()));
+                self.follow = self.scroll_offset.get() == 0;
             }
             UIMode::CommandPalette => {
                 self.selected_command_idx = self.selected_command_idx.saturating_sub(1);
             }
             UIMode::Modal(ModalType::ModelSelector) => {
                 self.selected_model_idx = self.selected_model_idx.saturating_sub(1);
+                self.keep_model_visible();
+            }
+            UIMode::Modal(ModalType::LoadPrompt) => {
+                self.selected_prompt_idx = self.selected_prompt_idx.saturating_sub(1);
+            }
+            UIMode::Modal(ModalType::PickCandidate) => {
+                self.selected_candidate_idx = self.selected_candidate_idx.saturating_sub(1);
+            }
+            UIMode::Modal(ModalType::Settings) => {
+                // A half-typed value belongs to the row it was typed on
+                self.selected_setting_idx = self.selected_setting_idx.saturating_sub(1);
+                self.modal_input.clear();
+            }
+            UIMode::Modal(ModalType::DebugRequest) => {
+                self.debug_scroll = self.debug_scroll.saturating_sub(3);
+            }
+            UIMode::Modal(ModalType::Output) => {
+                self.output_scroll = self.output_scroll.saturating_sub(3);
             }
             UIMode::Modal(_) => {
                 // No scrolling in other modals
@@ -207,7 +511,10 @@ impl App {
     pub fn scroll_down(&mut self) {
         match self.mode {
             UIMode::Chat => {
-                self.scroll_offset = self.scroll_offset.saturating_sub(3);
+                self.scroll_offset.set(self.scroll_offset.get().saturating_sub(3));
+                if self.scroll_offset.get() == 0 {
+                    self.scroll_to_bottom();
+                }
             }
             UIMode::CommandPalette => {
                 let count = self.get_filtered_commands().len();
@@ -216,10 +523,35 @@ impl App {
                 }
             }
             UIMode::Modal(ModalType::ModelSelector) => {
-                let count = self.model_registry.len();
+                let count = self.filtered_model_names().len();
                 if self.selected_model_idx < count.saturating_sub(1) {
                     self.selected_model_idx += 1;
                 }
+                self.keep_model_visible();
+            }
+            UIMode::Modal(ModalType::LoadPrompt) => {
+                if self.selected_prompt_idx < self.prompt_list.len().saturating_sub(1) {
+                    self.selected_prompt_idx += 1;
+                }
+            }
+            UIMode::Modal(ModalType::PickCandidate) => {
+                if self.selected_candidate_idx < self.candidates.len().saturating_sub(1) {
+                    self.selected_candidate_idx += 1;
+                }
+            }
+            UIMode::Modal(ModalType::Settings) => {
+                if self.selected_setting_idx < self.settings.len().saturating_sub(1) {
+                    self.selected_setting_idx += 1;
+                }
+                self.modal_input.clear();
+            }
+            UIMode::Modal(ModalType::DebugRequest) => {
+                let last = self.debug_request.lines().count().saturating_sub(1) as u16;
+                self.debug_scroll = (self.debug_scroll + 3).min(last);
+            }
+            UIMode::Modal(ModalType::Output) => {
+                let last = self.output.lines.len().saturating_sub(1) as u16;
+                self.output_scroll = (self.output_scroll + 3).min(last);
             }
             UIMode::Modal(_) => {
                 // No scrolling in other modals
@@ -227,39 +559,477 @@ impl App {
         }
     }
 
+    // Expand or collapse the reasoning of the newest message that has any
+    pub fn toggle_latest_thinking(&mut self) {
+        let latest = self
+            .session
+            .messages
+            .iter()
+            .rev()
+            .find(|m| m.role == "assistant" && crate::session::split_thinking(&m.content).0.is_some())
+            .map(|m| m.id);
+        if let Some(id) = latest {
+            if !self.expanded_thinking.remove(&id) {
+                self.expanded_thinking.insert(id);
+            }
+        }
+    }
+
+    // Tab walks from the newest message towards older ones, Shift-Tab back;
+    // stepping past the newest drops the focus
+    pub fn move_focus(&mut self, older: bool) {
+        let ids: Vec<usize> = self.get_visible_messages().iter().map(|m| m.id).collect();
+        let position = self.focused_message_id.and_then(|id| ids.iter().position(|&m| m == id));
+        self.focused_message_id = match position {
+            None => ids.last().copied(),
+            Some(i) if older => Some(ids[i.saturating_sub(1)]),
+            Some(i) => ids.get(i + 1).copied(),
+        };
+        self.reveal_focused();
+    }
+
+    // Forget a focus whose message was deleted or archived
+    pub fn drop_stale_focus(&mut self) {
+        self.focused_message_id = self.focused_message_id.filter(|&id| self.session.get_message(id).is_some());
+        if self.editing_message_id.is_some_and(|id| self.session.get_message(id).is_none()) {
+            self.cancel_edit();
+        }
+    }
+
+    // Scroll just enough to bring the focused message into view, using the
+    // line positions measured on the last draw
+    fn reveal_focused(&mut self) {
+        let Some(id) = self.focused_message_id else { return };
+        let Some((_, start, count)) = self.message_rows.borrow().iter().copied().find(|r| r.0 == id) else {
+            return;
+        };
+        let visible = self.chat_viewport.get();
+        let bottom_top = self.max_scroll();
+        let top = bottom_top.saturating_sub(self.scroll_offset.get());
+        let new_top = if start < top {
+            start
+        } else if start + count > top + visible {
+            (start + count).saturating_sub(visible).min(start)
+        } else {
+            return;
+        };
+        self.scroll_offset.set(bottom_top.saturating_sub(new_top));
+        self.follow = self.scroll_offset.get() == 0;
+    }
+
+    // Put a message's text in the input box; Enter then stores it in place
+    pub fn begin_edit(&mut self, id: usize) {
+        if let Some(msg) = self.session.get_message(id) {
+            self.input_buffer = msg.content.clone();
+            self.editing_message_id = Some(id);
+        }
+    }
+
+    pub fn cancel_edit(&mut self) {
+        self.editing_message_id = None;
+        self.input_buffer.clear();
+    }
+
+    // +/- on the focused answer only; pressing the same key again clears
+    // the rating
+    pub fn rate_focused(&mut self, rating: i8) {
+        let target = self
+            .focused_message_id
+            .and_then(|id| self.session.get_message(id))
+            .filter(|m| m.role == "assistant");
+        if let Some((id, current)) = target.map(|m| (m.id, m.rating)) {
+            let rating = (current != Some(rating)).then_some(rating);
+            let _ = self.session.rate(id, rating);
+        }
+    }
+
+    // Step through the /retry variants of the latest answer; later answers
+    // built on it, so only the newest one can be swapped
+    pub fn cycle_latest_variant(&mut self, forward: bool) {
+        let latest = self.session.messages.last().filter(|m| m.role == "assistant").map(|m| m.id);
+        if let Some(id) = latest {
+            self.session.cycle_variant(id, forward);
+        }
+    }
+
+    // Furthest the chat can scroll up: the top line at the top of the pane
+    pub fn max_scroll(&self) -> usize {
+        self.chat_lines.get().saturating_sub(self.chat_viewport.get())
+    }
+
+    pub fn is_compact(&self, height: u16) -> bool {
+        match self.layout {
+            LayoutMode::Auto => height < COMPACT_HEIGHT,
+            LayoutMode::Compact => true,
+            LayoutMode::Full => false,
+        }
+    }
+
+    // Flip what is on screen now; after a toggle the terminal size no longer decides
+    pub fn toggle_layout(&mut self) {
+        self.layout = if self.compact.get() { LayoutMode::Full } else { LayoutMode::Compact };
+    }
+
+    // Jump back to the newest message and resume following new ones
+    pub fn scroll_to_bottom(&mut self) {
+        self.scroll_offset.set(0);
+        self.follow = true;
+        self.has_new_message = false;
+    }
+
+    // Rescan the models directory, keeping the selector on the same model
+    pub fn refresh_models(&mut self) -> usize {
+        let selected = self.filtered_model_names().get(self.selected_model_idx).cloned();
+        let added = crate::config::merge_scanned_models(&mut self.model_registry);
+        if let Some(name) = selected {
+            self.selected_model_idx = self
+                .filtered_model_names()
+                .iter()
+                .position(|n| *n == name)
+                .unwrap_or(0);
+            self.keep_model_visible();
+        }
+        added
+    }
+
+    // The selector's list: models whose name contains the typed filter, case-insensitively
+    pub fn filtered_model_names(&self) -> Vec<String> {
+        let filter = self.modal_input.trim().to_lowercase();
+        let mut models = self.sorted_model_names();
+        models.retain(|name| name.to_lowercase().contains(&filter));
+        models
+    }
+
+    // Scroll the selector just enough to show the selected model
+    pub fn keep_model_visible(&self) {
+        let viewport = self.model_viewport.get().max(1);
+        let scroll = self.model_scroll.get();
+        if self.selected_model_idx < scroll {
+            self.model_scroll.set(self.selected_model_idx);
+        } else if self.selected_model_idx >= scroll + viewport {
+            self.model_scroll.set(self.selected_model_idx + 1 - viewport);
+        }
+    }
+
+    // A changed filter starts over at the top of what matches
+    fn select_first_model(&mut self) {
+        self.selected_model_idx = 0;
+        self.model_scroll.set(0);
+    }
+
+    // Local models first, then server-only models, each group sorted
+    pub fn sorted_model_names(&self) -> Vec<String> {
+        let mut models: Vec<String> = self.model_registry.keys().cloned().collect();
+        models.sort();
+        let mut remote: Vec<String> = self
+            .server_models
+            .iter()
+            .filter(|name| !self.model_registry.contains_key(*name))
+            .cloned()
+            .collect();
+        remote.sort();
+        models.extend(remote);
+        models
+    }
+
+    pub fn is_server_model(&self, name: &str) -> bool {
+        !self.model_registry.contains_key(name) && self.server_models.iter().any(|m| m == name)
+    }
+
     pub fn add_assistant_message(&mut self, text: String, usage: Option<crate::llm_client::Usage>) {
         let tokens = usage.as_ref().map(|u| u.completion_tokens);
-        self.session.add_message("assistant".to_string(), text, tokens);
+        // The server returns only the continuation; store prefill + continuation as one reply
+        let text = match self.prefill.take() {
+            Some(prefill) => prefill + &text,
+            None => text,
+        };
+        let text = crate::postprocess::clean_response(&text, &self.response_cleanup);
+        self.session.add_response(text, tokens);
+        // Record the temperature actually used (a /retry override may differ)
+        if let Some(msg) = self.session.messages.last_mut() {
+            msg.temperature = Some(self.generation_temperature);
+        }
+        self.begin_reveal();
+        self.reported_context = usage.map(|u| {
+            let meta = &self.session.metadata;
+            (self.session.messages.len(), meta.last_modified, u.prompt_tokens + u.completion_tokens)
+        });
         self.is_loading = false;
-        self.scroll_offset = 0;  // Auto-scroll to bottom to show new message
+        self.generation_started = None;
+        self.generation_task = None;
+        self.reset_streaming();
+        // Only auto-scroll when already at the bottom; otherwise keep the
+        // user's position (the next draw holds the top line) and flag the
+        // new message instead
+        if self.follow {
+            self.scroll_offset.set(0);
+        } else {
+            self.has_new_message = true;
+        }
+    }
+
+    pub fn start_generation(&mut self, temperature: f32) {
+        self.clear_suggestions();
+        self.generation_id += 1;
+        self.generation_temperature = temperature;
+        self.is_loading = true;
+        self.generation_started = Some(Instant::now());
+        self.reset_streaming();
+    }
+
+    // Forget an aborted generation; its partial output is never added to the session
+    pub fn clear_generation(&mut self) {
+        self.generation_task = None;
+        self.benchmark_progress = None;
+        self.prefill = None;
+        self.is_loading = false;
+        self.generation_started = None;
+        self.reset_streaming();
+    }
+
+    // A multi-candidate generation finished: stop loading and let the user pick.
+    // The prefill stays until a candidate is accepted, so it is prepended to it.
+    pub fn show_candidates(&mut self, candidates: Vec<String>) {
+        let prefill = self.prefill.take();
+        self.clear_generation();
+        self.prefill = prefill;
+        self.candidates = candidates;
+        self.open_modal(ModalType::PickCandidate);
+    }
+
+    // Take candidate `idx` out of the picker, discarding the others
+    pub fn take_candidate(&mut self, idx: usize) -> Option<String> {
+        if idx >= self.candidates.len() {
+            return None;
+        }
+        let chosen = self.candidates.swap_remove(idx);
+        self.candidates.clear();
+        self.close_modal();
+        Some(chosen)
+    }
+
+    pub fn discard_candidates(&mut self) {
+        self.candidates.clear();
+        self.prefill = None;
+        self.close_modal();
+    }
+
+    // What the input area says while the app waits
+    pub fn loading_label(&self) -> String {
+        match self.benchmark_progress {
+            Some((done, runs)) => format!("Benchmark run {}/{}... (esc to stop)", (done + 1).min(runs), runs),
+            None => "Generating response...".to_string(),
+        }
+    }
+
+    pub fn on_stream_delta(&mut self, _delta: &str) {
+        self.streaming_tokens += 1;
+        self.last_delta = Some(Instant::now());
+    }
+
+    fn reset_streaming(&mut self) {
+        self.streaming_tokens = 0;
+        self.last_delta = None;
+        self.tokens_at_last_tick = 0;
+        self.live_tps = 0.0;
+    }
+
+    // The temperature the in-flight reply was requested with; /set temp
+    // during a generation only applies to the next one
+    pub fn generation_temperature(&self) -> f32 {
+        self.generation_temperature
+    }
+
+    // Seconds left before the request times out, once the server has been
+    // quiet for 30 seconds (None otherwise). A stream only times out when no
+    // delta arrives for the whole timeout, so each one restarts the count.
+    pub fn timeout_countdown(&self) -> Option<u64> {
+        let started = self.generation_started?;
+        let elapsed = self.last_delta.unwrap_or(started).elapsed().as_secs();
+        if elapsed < 30 || self.request_timeout_secs == 0 {
+            return None;
+        }
+        Some(self.request_timeout_secs.saturating_sub(elapsed))
     }
 
     pub fn set_error(&mut self, error: String) {
+        tracing::warn!(%error, "shown in status bar");
+        self.last_error = Some(error.clone());
         self.error_message = Some(error);
-        self.is_loading = false;
+        self.status_message = None;
+        // A reply still streaming keeps its loading state: the request runs on
+        // and its answer is still taken. Failed generations clear the task first.
+        if self.generation_task.is_none() {
+            self.is_loading = false;
+            self.generation_started = None;
+            self.reset_streaming();
+        }
         self.error_ticks = 0;  // Start error timeout
     }
 
+    // A command's confirmation, shown in the status bar until it times out
+    pub fn set_status(&mut self, status: String) {
+        self.status_message = Some(status);
+        self.status_ticks = 0;
+        self.error_message = None;  // The newer message wins
+    }
+
     pub fn tick(&mut self) {
         self.tick_count = self.tick_count.wrapping_add(1);
 
-        // Auto-clear errors after 3 seconds (12 ticks at 250ms each)
-        if let Some(_) = self.error_message {
+        // Instantaneous speed: tokens received since the previous tick
+        if self.is_loading {
+            let delta = self.streaming_tokens.saturating_sub(self.tokens_at_last_tick);
+            self.live_tps = delta as f32 / (self.tick_ms.max(1) as f32 / 1000.0);
+            self.tokens_at_last_tick = self.streaming_tokens;
+        }
+
+        if let Some((id, shown, step)) = self.reveal {
+            let total = self.session.get_message(id).map_or(0, |m| m.content.chars().count());
+            self.reveal = (shown + step < total).then_some((id, shown + step, step));
+        }
+
+        // Auto-clear errors after 3 seconds
+        if self.error_message.is_some() {
             self.error_ticks += 1;
-            if self.error_ticks > 12 {
+            if self.error_ticks as u64 * self.tick_ms > 3000 {
                 self.error_message = None;
                 self.error_ticks = 0;
             }
         }
+        if self.status_message.is_some() {
+            self.status_ticks += 1;
+            if self.status_ticks as u64 * self.tick_ms > 3000 {
+                self.status_message = None;
+                self.status_ticks = 0;
+            }
+        }
     }
 
+    // Advances with ticks, not renders, so the animation speed is independent of FPS
     pub fn get_loading_spinner(&self) -> &'static str {
-        match self.tick_count % 4 {
-            0 => "⠋",
-            1 => "⠙",
-            2 => "⠹",
-            _ => "⠸",
+        self.spinner[self.tick_count as usize % self.spinner.len()]
+    }
+
+    // Start revealing the newest reply with the typewriter effect. The chat only
+    // shows a token count while a reply streams, so every reply arrives whole;
+    // a reply already drawn as it streamed would have nothing left to reveal.
+    fn begin_reveal(&mut self) {
+        self.reveal = None;
+        let Some(msg) = self.session.messages.last().filter(|_| self.typewriter_cps > 0) else {
+            return;
+        };
+        let tick_ms = self.tick_ms.max(1);
+        let per_tick = (self.typewriter_cps as u64 * tick_ms / 1000).max(1) as usize;
+        let ticks = (REVEAL_MAX_SECS * 1000 / tick_ms).max(1) as usize;
+        let total = msg.content.chars().count();
+        self.reveal = Some((msg.id, 0, per_tick.max(total.div_ceil(ticks))));
+    }
+
+    pub fn is_revealing(&self) -> bool {
+        self.reveal.is_some()
+    }
+
+    // Show the whole reply now (any key does this)
+    pub fn finish_reveal(&mut self) {
+        self.reveal = None;
+    }
+
+    // How many characters of message `id` to draw; None draws all of it
+    pub fn revealed_chars(&self, id: usize) -> Option<usize> {
+        self.reveal.filter(|(reveal_id, ..)| *reveal_id == id).map(|(_, shown, _)| shown)
+    }
+
+    // Keep up to MAX_SUGGESTIONS follow-ups from the model's reply, one per line
+    // with any list markers or quotes stripped. Ignored unless `id` is still the
+    // latest message: a newer turn makes them stale.
+    pub fn set_suggestions(&mut self, id: usize, reply: &str) {
+        if self.is_loading || self.session.messages.last().map(|m| m.id) != Some(id) {
+            return;
+        }
+        let (_, answer) = crate::session::split_thinking(reply);
+        self.suggestions = answer
+            .lines()
+            .map(|line| strip_list_marker(line.trim()).trim_matches('"').trim().to_string())
+            .filter(|line| !line.is_empty())
+            .take(MAX_SUGGESTIONS)
+            .collect();
+        self.suggestions_for = Some(id);
+    }
+
+    pub fn clear_suggestions(&mut self) {
+        self.suggestions.clear();
+        self.suggestions_for = None;
+    }
+
+    // Suggestions to show: only under the reply they belong to, and not while
+    // something else is going on
+    pub fn visible_suggestions(&self) -> &[String] {
+        let latest = self.session.messages.last().map(|m| m.id);
+        if self.is_loading || self.is_revealing() || self.suggestions_for.is_none() || self.suggestions_for != latest {
+            return &[];
         }
+        &self.suggestions
+    }
+
+    // The suggestion picked with number key `key` ('1' is the first)
+    pub fn suggestion(&self, key: char) -> Option<String> {
+        let index = key.to_digit(10)?.checked_sub(1)? as usize;
+        self.visible_suggestions().get(index).cloned()
+    }
+
+    // Friendly name of a model for the chat, falling back to its key
+    pub fn display_name<'a>(&'a self, model: &'a str) -> &'a str {
+        self.display_names.get(model).map_or(model, String::as_str)
+    }
+
+    // Where /save <id> would write, for the save modal's example
+    pub fn saved_message_path(&self, id: usize) -> String {
+        let name = crate::config::render_filename(
+            &self.saved_message,
+            &[
+                ("model", &self.session.metadata.model_name),
+                ("timestamp", &chrono::Local::now().format(crate::config::FILENAME_TIMESTAMP).to_string()),
+                ("session", &self.session.metadata.id),
+                ("id", &id.to_string()),
+            ],
+        );
+        format!("{}.txt", name)
+    }
+
+    // Sampling settings changed from the config defaults, for the status bar tag
+    pub fn custom_sampling(&self) -> Vec<String> {
+        crate::commands::custom_sampling(self.default_temperature, &crate::commands::Sampling {
+            temperature: self.temperature,
+            top_p: self.top_p,
+            max_tokens: self.max_tokens,
+            candidate_count: self.candidate_count,
+            output_format: &self.output_format,
+            logit_bias: &self.logit_bias,
+            think: self.think,
+        })
+    }
+
+    // Context size of the current model: /set ctx or --ctx-size, else its profile.
+    // None for models without a profile, whose window isn't known.
+    pub fn context_window(&self) -> Option<u32> {
+        self.session
+            .metadata
+            .context_window
+            .or_else(|| self.context_windows.get(&self.current_model).copied())
+    }
+
+    // Tokens the conversation occupies: the server's count while the session is
+    // unchanged since the last answer, an estimate otherwise, plus the reply
+    // being streamed
+    pub fn context_used(&self) -> u32 {
+        let meta = &self.session.metadata;
+        let used = match self.reported_context {
+            Some((count, modified, tokens))
+                if count == self.session.messages.len() && modified == meta.last_modified => tokens,
+            _ => self.session.estimated_tokens(),
+        };
+        used + if self.is_loading { self.streaming_tokens } else { 0 }
     }
 
     pub fn get_visible_messages(&self) -> Vec<&crate::session::ChatMessage> {
@@ -1,5 +1,6 @@
 use crossterm::event::{self, KeyEvent};
-use std::sync::mpsc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
 use std::thread;
 use std::time::Duration;
 
@@ -7,35 +8,73 @@ pub enum AppEvent {
     Key(KeyEvent),
     Tick,
     Render,
-    LlmResponse(String, Option<crate::llm_client::Usage>, f32),  // Added elapsed time in seconds
-    LlmError(String),
+    // Generation events lead with the id of the generation that sent them, so
+    // ones still queued after a cancel can't leak into the next generation
+    LlmDelta(u64, String),  // Streamed content fragment
+    LlmResponse(u64, String, Option<crate::llm_client::Usage>, f32),  // Added elapsed time in seconds
+    LlmCandidates(u64, Vec<String>, Option<crate::llm_client::Usage>, f32),  // Several replies (n > 1) to pick from
+    LlmError(u64, String),
+    BenchmarkProgress(u64, usize),  // Runs finished so far
+    BenchmarkDone(u64, crate::commands::CommandOutput),  // The results table
+    ServerHealth(bool),  // Result of a periodic /health check
+    ServerModel(String),  // What the server reports it loaded, for an open /info
+    Suggestions(usize, String),  // Follow-up prompts for this assistant message, as the model wrote them
+    Shutdown,  // OS-level SIGINT: exit through the normal save/cleanup path
+}
+
+impl AppEvent {
+    pub fn generation(&self) -> Option<u64> {
+        match self {
+            AppEvent::LlmDelta(id, ..)
+            | AppEvent::LlmResponse(id, ..)
+            | AppEvent::LlmCandidates(id, ..)
+            | AppEvent::LlmError(id, ..)
+            | AppEvent::BenchmarkProgress(id, ..)
+            | AppEvent::BenchmarkDone(id, ..) => Some(*id),
+            _ => None,
+        }
+    }
 }
 
 pub struct EventHandler {
     rx: mpsc::Receiver<AppEvent>,
     tx: mpsc::Sender<AppEvent>,
+    input_paused: Arc<AtomicBool>,
 }
 
+// How often the input thread checks for keys (and for being paused)
+const INPUT_POLL: Duration = Duration::from_millis(50);
+
 impl EventHandler {
-    pub fn new() -> Self {
+    // `tick_ms` drives animation and timers; rendering runs on its own clock
+    pub fn new(tick_ms: u64) -> Self {
         let (tx, rx) = mpsc::channel();
 
-        // Spawn keyboard input thread
+        // Spawn keyboard input thread. It polls rather than blocking in read()
+        // so it can stand aside while an external program owns the terminal.
         let key_tx = tx.clone();
+        let input_paused = Arc::new(AtomicBool::new(false));
+        let paused = input_paused.clone();
         thread::spawn(move || loop {
-            if let Ok(event) = event::read() {
-                if let crossterm::event::Event::Key(key) = event {
-                    if key_tx.send(AppEvent::Key(key)).is_err() {
-                        break;
-                    }
+            if paused.load(Ordering::Acquire) {
+                thread::sleep(INPUT_POLL);
+                continue;
+            }
+            if !event::poll(INPUT_POLL).unwrap_or(false) || paused.load(Ordering::Acquire) {
+                continue;
+            }
+            if let Ok(crossterm::event::Event::Key(key)) = event::read() {
+                if key_tx.send(AppEvent::Key(key)).is_err() {
+                    break;
                 }
             }
         });
 
-        // Spawn tick thread (250ms interval)
+        // Spawn tick thread
         let tick_tx = tx.clone();
+        let tick = Duration::from_millis(tick_ms.max(10));
         thread::spawn(move || loop {
-            thread::sleep(Duration::from_millis(250));
+            thread::sleep(tick);
             if tick_tx.send(AppEvent::Tick).is_err() {
                 break;
             }
@@ -50,7 +89,18 @@ impl EventHandler {
             }
         });
 
-        EventHandler { rx, tx }
+        EventHandler { rx, tx, input_paused }
+    }
+
+    // Stop reading the terminal, e.g. while $EDITOR runs. Returns once any
+    // in-progress poll has had time to finish.
+    pub fn pause_input(&self) {
+        self.input_paused.store(true, Ordering::Release);
+        thread::sleep(INPUT_POLL + Duration::from_millis(10));
+    }
+
+    pub fn resume_input(&self) {
+        self.input_paused.store(false, Ordering::Release);
     }
 
     pub fn next(&self) -> Option<AppEvent> {
@@ -64,6 +114,6 @@ impl EventHandler {
 
 impl Default for EventHandler {
     fn default() -> Self {
-        Self::new()
+        Self::new(250)
     }
 }
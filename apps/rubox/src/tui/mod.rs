@@ -1,7 +1,11 @@
 pub mod app;
 pub mod event;
+pub mod theme;
+pub mod transcript;
 pub mod ui;
 
-pub use app::{App, UIMode, ModalType};
+pub use app::{App, UIMode, ModalType, LayoutMode, spinner_frames, layout_mode};
 pub use event::{EventHandler, AppEvent};
-pub use ui::draw;
+pub use theme::Theme;
+pub use transcript::render_transcript;
+pub use ui::{code_blocks, draw, parse_markdown_to_lines};
@@ -0,0 +1,54 @@
+use ratatui::style::Color;
+use crate::config::ThemeConfig;
+
+#[derive(Debug, Clone)]
+pub struct Theme {
+    pub user: Color,       // "You" label
+    pub assistant: Color,  // Model label, headings, secondary text
+    pub accent: Color,     // Selection, palette/modal borders, H1
+    pub dim: Color,        // Hints and metadata
+    pub border: Color,     // Chat/input borders, code fences
+    pub code: Color,       // Inline code, code blocks, links
+    pub error: Color,      // Status bar errors
+}
+
+impl Theme {
+    // Parse the configured hex colors, falling back to the default palette
+    // for any malformed entry. Returns the names of the roles that fell back.
+    pub fn from_config(config: &ThemeConfig) -> (Self, Vec<&'static str>) {
+        let defaults = ThemeConfig::default();
+        let mut invalid = Vec::new();
+
+        let mut pick = |role: &'static str, value: &str, fallback: &str| {
+            parse_hex_color(value).unwrap_or_else(|| {
+                invalid.push(role);
+                parse_hex_color(fallback).unwrap_or(Color::Reset)
+            })
+        };
+
+        let theme = Theme {
+            user: pick("user", &config.user, &defaults.user),
+            assistant: pick("assistant", &config.assistant, &defaults.assistant),
+            accent: pick("accent", &config.accent, &defaults.accent),
+            dim: pick("dim", &config.dim, &defaults.dim),
+            border: pick("border", &config.border, &defaults.border),
+            code: pick("code", &config.code, &defaults.code),
+            error: pick("error", &config.error, &defaults.error),
+        };
+
+        (theme, invalid)
+    }
+}
+
+// Accepts "#RRGGBB" or "RRGGBB"
+fn parse_hex_color(value: &str) -> Option<Color> {
+    let hex = value.trim().trim_start_matches('#');
+    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    let r = u8::from_str_radix(&hex[0..2], 16).ok()?;
+    let g = u8::from_str_radix(&hex[2..4], 16).ok()?;
+    let b = u8::from_str_radix(&hex[4..6], 16).ok()?;
+    Some(Color::Rgb(r, g, b))
+}
@@ -0,0 +1,64 @@
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Line;
+
+use crate::config::RuboxConfig;
+use crate::tui::{parse_markdown_to_lines, App, Theme};
+
+// The conversation as terminal text for the normal screen, laid out like the
+// multi-model output: a `┌─ name ─` header per message, then its rendered
+// markdown. Reasoning blocks are left out. Without `color` it is plain text.
+pub fn render_transcript(app: &App, theme: &Theme, config: &RuboxConfig, color: bool) -> String {
+    let (header, reset) = if color {
+        (config.ui.color_orange.as_str(), config.ui.color_reset.as_str())
+    } else {
+        ("", "")
+    };
+
+    let mut out = String::new();
+    for msg in &app.session.messages {
+        let name = if msg.role == "user" {
+            "You"
+        } else {
+            let model = msg.model.as_deref().unwrap_or(&app.current_model);
+            app.assistant_name.as_deref().unwrap_or_else(|| app.display_name(model))
+        };
+        let (_, answer) = crate::session::split_thinking(&msg.content);
+
+        out.push('\n');
+        out.push_str(&format!("{}┌─ {} ─{}\n", header, name, reset));
+        for line in parse_markdown_to_lines(&answer, theme) {
+            out.push_str(&line_to_ansi(&line, color));
+            out.push('\n');
+        }
+    }
+    out
+}
+
+// Spans with their colors and emphasis as ANSI escapes
+fn line_to_ansi(line: &Line, color: bool) -> String {
+    let mut text = String::new();
+    for span in &line.spans {
+        let codes = if color { style_codes(span.style) } else { String::new() };
+        if codes.is_empty() {
+            text.push_str(&span.content);
+        } else {
+            text.push_str(&format!("\x1b[{}m{}\x1b[0m", codes, span.content));
+        }
+    }
+    text
+}
+
+fn style_codes(style: Style) -> String {
+    let mut codes = Vec::new();
+    for (modifier, code) in [(Modifier::BOLD, "1"), (Modifier::ITALIC, "3"), (Modifier::UNDERLINED, "4")] {
+        if style.add_modifier.contains(modifier) {
+            codes.push(code.to_string());
+        }
+    }
+    match style.fg {
+        Some(Color::Rgb(r, g, b)) => codes.push(format!("38;2;{};{};{}", r, g, b)),
+        Some(Color::White) => codes.push("37".to_string()),
+        _ => {}
+    }
+    codes.join(";")
+}
@@ -2,26 +2,42 @@ use ratatui::{
     layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, List, ListItem, Paragraph, Wrap},
+    widgets::{Block, Borders, LineGauge, List, ListItem, Paragraph, Wrap},
     Frame,
 };
-use pulldown_cmark::{Parser, Event, Tag, TagEnd, HeadingLevel};
+use pulldown_cmark::{CodeBlockKind, Parser, Event, Tag, TagEnd, HeadingLevel};
 
-use crate::tui::{App, UIMode, ModalType};
+use crate::commands::Tone;
+use crate::tui::{App, UIMode, ModalType, Theme};
 
-const ORANGE: Color = Color::Rgb(255, 135, 0);
-const EMERALD: Color = Color::Rgb(0, 255, 135);
-const GRAY: Color = Color::Rgb(128, 128, 128);
-const DARK_GRAY: Color = Color::Rgb(60, 60, 60);
-const CYAN: Color = Color::Rgb(0, 255, 255);
+// Below this size the layout regions collapse, so draw a notice instead
+const MIN_WIDTH: u16 = 20;
+const MIN_HEIGHT: u16 = 8;
 
-fn parse_markdown_to_lines(text: &str) -> Vec<Line<'static>> {
+// Tallest the input line grows to in the compact layout
+const COMPACT_INPUT_ROWS: u16 = 3;
+
+// Context gauge at the right of the status bar, shown from GAUGE_MIN_WIDTH columns
+const GAUGE_WIDTH: u16 = 24;
+const GAUGE_MIN_WIDTH: u16 = 80;
+// Fill ratios where the gauge turns amber, then red
+const GAUGE_WARN: f64 = 0.75;
+const GAUGE_CRITICAL: f64 = 0.9;
+const AMBER: Color = Color::Rgb(255, 176, 0);
+
+// Render model output as styled lines. Blocks (paragraphs, headings, code,
+// lists, quotes) are separated by exactly one blank line; list items stay tight,
+// and everything inside an item, including fenced code, is indented to its text.
+// Quoted lines get a dim `│ ` gutter per nesting level.
+pub fn parse_markdown_to_lines(text: &str, theme: &Theme) -> Vec<Line<'static>> {
     let parser = Parser::new(text);
     let mut lines = Vec::new();
     let mut current_line = Vec::new();
     let mut current_style = Style::default();
-    let mut list_indent = 0;
-    let mut code_block_lines = Vec::new();
+    let mut lists: Vec<Option<u64>> = Vec::new();  // Next number of each open list; None for bullets
+    let mut item_indents: Vec<String> = Vec::new();  // Continuation indent of each open item
+    let mut quote_starts: Vec<usize> = Vec::new();  // First line of each open block quote
+    let mut code_block = String::new();
     let mut in_code_block = false;
 
     for event in parser {
@@ -29,13 +45,19 @@ fn parse_markdown_to_lines(text: &str) -> Vec<Line<'static>> {
             Event::Start(tag) => {
                 match tag {
                     Tag::Heading { level, .. } => {
+                        flush_line(&mut lines, &mut current_line);
                         current_style = match level {
-                            HeadingLevel::H1 => Style::default().fg(ORANGE).add_modifier(Modifier::BOLD),
-                            HeadingLevel::H2 => Style::default().fg(EMERALD).add_modifier(Modifier::BOLD),
-                            _ => Style::default().fg(EMERALD).add_modifier(Modifier::BOLD),
+                            HeadingLevel::H1 => Style::default().fg(theme.accent).add_modifier(Modifier::BOLD),
+                            HeadingLevel::H2 => Style::default().fg(theme.assistant).add_modifier(Modifier::BOLD),
+                            _ => Style::default().fg(theme.assistant).add_modifier(Modifier::BOLD),
                         };
                     }
-                    Tag::Paragraph => {}
+                    Tag::Paragraph => {
+                        // Later paragraphs of a list item line up with its first
+                        if let Some(indent) = item_indents.last().filter(|_| current_line.is_empty()) {
+                            current_line.push(Span::raw(indent.clone()));
+                        }
+                    }
                     Tag::Strong => {
                         current_style = current_style.add_modifier(Modifier::BOLD);
                     }
@@ -43,18 +65,34 @@ fn parse_markdown_to_lines(text: &str) -> Vec<Line<'static>> {
                         current_style = current_style.add_modifier(Modifier::ITALIC);
                     }
                     Tag::CodeBlock(_) => {
+                        flush_line(&mut lines, &mut current_line);
                         in_code_block = true;
-                        code_block_lines.clear();
+                        code_block.clear();
                     }
-                    Tag::List(_) => {
-                        list_indent += 1;
+                    Tag::BlockQuote(_) => {
+                        flush_line(&mut lines, &mut current_line);
+                        quote_starts.push(lines.len());
+                    }
+                    Tag::List(start) => {
+                        // A nested list starts below its parent item's text
+                        flush_line(&mut lines, &mut current_line);
+                        lists.push(start);
                     }
                     Tag::Item => {
-                        let indent = "  ".repeat(list_indent);
-                        current_line.push(Span::raw(format!("{}• ", indent)));
+                        flush_line(&mut lines, &mut current_line);
+                        let marker = match lists.last_mut() {
+                            Some(Some(number)) => {
+                                *number += 1;
+                                format!("{}. ", *number - 1)
+                            }
+                            _ => "• ".to_string(),
+                        };
+                        let indent = "  ".repeat(lists.len());
+                        item_indents.push(format!("{}{}", indent, " ".repeat(marker.chars().count())));
+                        current_line.push(Span::raw(format!("{}{}", indent, marker)));
                     }
                     Tag::Link { .. } => {
-                        current_style = current_style.fg(CYAN).add_modifier(Modifier::UNDERLINED);
+                        current_style = current_style.fg(theme.code).add_modifier(Modifier::UNDERLINED);
                     }
                     _ => {}
                 }
@@ -62,19 +100,15 @@ fn parse_markdown_to_lines(text: &str) -> Vec<Line<'static>> {
             Event::End(tag_end) => {
                 match tag_end {
                     TagEnd::Heading(_) => {
-                        if !current_line.is_empty() {
-                            lines.push(Line::from(current_line.clone()));
-                            current_line.clear();
-                        }
-                        lines.push(Line::from(""));
+                        flush_line(&mut lines, &mut current_line);
+                        separate_block(&mut lines);
                         current_style = Style::default();
                     }
                     TagEnd::Paragraph => {
-                        if !current_line.is_empty() {
-                            lines.push(Line::from(current_line.clone()));
-                            current_line.clear();
+                        flush_line(&mut lines, &mut current_line);
+                        if lists.is_empty() {
+                            separate_block(&mut lines);
                         }
-                        lines.push(Line::from(""));
                     }
                     TagEnd::Strong => {
                         current_style = current_style.remove_modifier(Modifier::BOLD);
@@ -84,26 +118,44 @@ fn parse_markdown_to_lines(text: &str) -> Vec<Line<'static>> {
                     }
                     TagEnd::CodeBlock => {
                         in_code_block = false;
-                        // Add code block with styling
-                        lines.push(Line::from(Span::styled("┌─ Code ─", Style::default().fg(DARK_GRAY))));
-                        for code_line in &code_block_lines {
-                            lines.push(Line::from(Span::styled(
-                                format!("│ {}", code_line),
-                                Style::default().fg(CYAN),
-                            )));
+                        let indent = item_indents.last().cloned().unwrap_or_default();
+                        let border = Style::default().fg(theme.border);
+                        lines.push(Line::from(Span::styled(format!("{}┌─ Code ─", indent), border)));
+                        for code_line in code_block.lines() {
+                            lines.push(Line::from(vec![
+                                Span::styled(format!("{}│ ", indent), border),
+                                Span::styled(code_line.to_string(), Style::default().fg(theme.code)),
+                            ]));
+                        }
+                        lines.push(Line::from(Span::styled(format!("{}└─────────", indent), border)));
+                        if lists.is_empty() {
+                            separate_block(&mut lines);
+                        }
+                        code_block.clear();
+                    }
+                    TagEnd::BlockQuote(_) => {
+                        flush_line(&mut lines, &mut current_line);
+                        let start = quote_starts.pop().unwrap_or(lines.len());
+                        while lines.len() > start && lines.last().is_some_and(|line| line.width() == 0) {
+                            lines.pop();
+                        }
+                        // Nested quotes are closed first, so their lines collect one gutter per level
+                        for line in &mut lines[start..] {
+                            line.spans.insert(0, Span::styled("│ ", Style::default().fg(theme.dim)));
+                        }
+                        if lists.is_empty() {
+                            separate_block(&mut lines);
                         }
-                        lines.push(Line::from(Span::styled("└─────────", Style::default().fg(DARK_GRAY))));
-                        lines.push(Line::from(""));
-                        code_block_lines.clear();
                     }
                     TagEnd::List(_) => {
-                        list_indent = list_indent.saturating_sub(1);
+                        lists.pop();
+                        if lists.is_empty() {
+                            separate_block(&mut lines);
+                        }
                     }
                     TagEnd::Item => {
-                        if !current_line.is_empty() {
-                            lines.push(Line::from(current_line.clone()));
-                            current_line.clear();
-                        }
+                        flush_line(&mut lines, &mut current_line);
+                        item_indents.pop();
                     }
                     TagEnd::Link => {
                         current_style = Style::default();
@@ -113,7 +165,7 @@ fn parse_markdown_to_lines(text: &str) -> Vec<Line<'static>> {
             }
             Event::Text(text) => {
                 if in_code_block {
-                    code_block_lines.push(text.to_string());
+                    code_block.push_str(&text);
                 } else {
                     current_line.push(Span::styled(text.to_string(), current_style));
                 }
@@ -121,82 +173,220 @@ fn parse_markdown_to_lines(text: &str) -> Vec<Line<'static>> {
             Event::Code(code) => {
                 current_line.push(Span::styled(
                     format!("`{}`", code),
-                    Style::default().fg(CYAN),
+                    Style::default().fg(theme.code),
                 ));
             }
             Event::SoftBreak | Event::HardBreak => {
-                if !current_line.is_empty() {
-                    lines.push(Line::from(current_line.clone()));
-                    current_line.clear();
+                // Keep the model's line breaks; wrapped lines of an item stay indented
+                lines.push(Line::from(std::mem::take(&mut current_line)));
+                if let Some(indent) = item_indents.last() {
+                    current_line.push(Span::raw(indent.clone()));
                 }
             }
             _ => {}
         }
     }
 
-    // Flush any remaining content
-    if !current_line.is_empty() {
-        lines.push(Line::from(current_line));
+    flush_line(&mut lines, &mut current_line);
+    // The caller spaces messages apart itself
+    while lines.last().is_some_and(|line| line.width() == 0) {
+        lines.pop();
     }
 
     lines
 }
 
-pub fn draw(f: &mut Frame, app: &App) {
+// Contents of the fenced code blocks in `text`, in order, without the fences,
+// language tag or final newline (a pasted command shouldn't run by itself).
+// Parsed like the renderer does, so the blocks are the ones the chat shows.
+pub fn code_blocks(text: &str) -> Vec<String> {
+    let mut blocks = Vec::new();
+    let mut current: Option<String> = None;
+    for event in Parser::new(text) {
+        match event {
+            Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(_))) => current = Some(String::new()),
+            Event::Text(code) => {
+                if let Some(block) = current.as_mut() {
+                    block.push_str(&code);
+                }
+            }
+            Event::End(TagEnd::CodeBlock) => {
+                blocks.extend(current.take().map(|block| block.trim_end_matches('\n').to_string()))
+            }
+            _ => {}
+        }
+    }
+    blocks
+}
+
+fn flush_line(lines: &mut Vec<Line<'static>>, current_line: &mut Vec<Span<'static>>) {
+    if !current_line.is_empty() {
+        lines.push(Line::from(std::mem::take(current_line)));
+    }
+}
+
+// One blank line after a block, never two in a row and never at the top
+fn separate_block(lines: &mut Vec<Line<'static>>) {
+    if lines.last().is_some_and(|line| line.width() > 0) {
+        lines.push(Line::from(""));
+    }
+}
+
+pub fn draw(f: &mut Frame, app: &App, theme: &Theme) {
     let size = f.area();
 
-    // Main layout with clean proportions
+    if size.width < MIN_WIDTH || size.height < MIN_HEIGHT {
+        draw_too_small(f, size, theme);
+        return;
+    }
+
+    // The compact layout only applies while chatting; the command palette and
+    // modals keep the full layout so their lists have room
+    let compact = app.is_compact(size.height) && app.mode == UIMode::Chat;
+    app.compact.set(compact);
+
+    let input = if compact {
+        // Grows with multiline input, up to a few rows
+        let rows = app.input_buffer.matches('\n').count() as u16 + 1;
+        Constraint::Length(if app.is_loading { 1 } else { rows.min(COMPACT_INPUT_ROWS) })
+    } else {
+        Constraint::Min(app.input_min_rows)
+    };
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
-            Constraint::Percentage(60),  // Chat area
-            Constraint::Min(10),         // Input/command area
-            Constraint::Length(1),       // Status bar
+            if compact { Constraint::Min(1) } else { Constraint::Percentage(app.chat_percent) },  // Chat area
+            input,                                                                  // Input/command area
+            Constraint::Length(1),                                                  // Status bar
         ])
         .split(size);
 
     // Always draw chat history
-    draw_chat_history(f, chunks[0], app);
+    draw_chat_history(f, chunks[0], app, theme, compact);
 
     // Draw appropriate mode
     match app.mode {
-        UIMode::Chat => draw_input_normal(f, chunks[1], app),
-        UIMode::CommandPalette => draw_command_list(f, chunks[1], app),
-        UIMode::Modal(ref modal_type) => draw_modal_form(f, chunks[1], app, modal_type),
+        UIMode::Chat if compact => draw_input_compact(f, chunks[1], app, theme),
+        UIMode::Chat => draw_input_normal(f, chunks[1], app, theme),
+        UIMode::CommandPalette => draw_command_list(f, chunks[1], app, theme),
+        UIMode::Modal(ModalType::ErrorDetails) => draw_error_details(f, chunks[1], app, theme),
+        UIMode::Modal(ModalType::DebugRequest) => draw_debug_request(f, chunks[1], app, theme),
+        UIMode::Modal(ModalType::Output) => draw_output(f, chunks[1], app, theme),
+        UIMode::Modal(ref modal_type) => draw_modal_form(f, chunks[1], app, modal_type, theme),
     }
 
     // Status bar
-    draw_status_bar(f, chunks[2], app);
+    draw_status_bar(f, chunks[2], app, theme);
+}
+
+fn draw_too_small(f: &mut Frame, area: Rect, theme: &Theme) {
+    let message = format!("Terminal too small (min {}x{})", MIN_WIDTH, MIN_HEIGHT);
+    let paragraph = Paragraph::new(message)
+        .style(Style::default().fg(theme.accent))
+        .alignment(Alignment::Center)
+        .wrap(Wrap { trim: true });
+
+    // Vertically center when there's room for it
+    let y = area.y + area.height.saturating_sub(1) / 2;
+    let height = area.height.saturating_sub(y - area.y);
+    f.render_widget(paragraph, Rect { y, height, ..area });
 }
 
-fn draw_chat_history(f: &mut Frame, area: Rect, app: &App) {
+fn draw_chat_history(f: &mut Frame, area: Rect, app: &App, theme: &Theme, compact: bool) {
     let messages = app.get_visible_messages();
     let mut lines = Vec::new();
+    let mut bounds = Vec::new();  // (message id, index of its first line)
 
     if messages.is_empty() {
         lines.push(Line::from(""));
         lines.push(Line::from(Span::styled(
             "  Start chatting by typing a message below",
-            Style::default().fg(GRAY),
+            Style::default().fg(theme.dim),
         )));
         lines.push(Line::from(Span::styled(
             "  Press / to see available commands",
-            Style::default().fg(GRAY),
+            Style::default().fg(theme.dim),
         )));
     } else {
-        for msg in messages {
+        for msg in &messages {
+            bounds.push((msg.id, lines.len()));
             lines.push(Line::from(""));
 
-            let role_text = if msg.role == "user" { "You" } else { &app.current_model };
-            lines.push(Line::from(Span::styled(
-                role_text,
-                Style::default()
-                    .fg(if msg.role == "user" { ORANGE } else { EMERALD })
-                    .add_modifier(Modifier::BOLD),
-            )));
+            let role_text = if msg.role == "user" {
+                "You"
+            } else {
+                let model = msg.model.as_deref().unwrap_or(&app.current_model);
+                app.assistant_name.as_deref().unwrap_or_else(|| app.display_name(model))
+            };
+            let focused = app.focused_message_id == Some(msg.id);
+            let mut role_style = Style::default()
+                .fg(if msg.role == "user" { theme.user } else { theme.assistant })
+                .add_modifier(Modifier::BOLD);
+            if focused {
+                role_style = role_style.add_modifier(Modifier::REVERSED);
+            }
+            let mut header = vec![Span::styled(role_text.to_string(), role_style)];
+            if focused && !app.verbose {
+                // Verbose headers already carry the id
+                header.push(Span::styled(format!("  #{}", msg.id), Style::default().fg(theme.dim)));
+            }
+            match msg.rating {
+                Some(crate::session::RATING_GOOD) => header.push(Span::styled(" ▲", Style::default().fg(theme.assistant))),
+                Some(_) => header.push(Span::styled(" ▼", Style::default().fg(theme.error))),
+                None => {}
+            }
+            if msg.variants.len() > 1 {
+                header.push(Span::styled(
+                    format!("  ◀ {}/{} ▶", msg.selected + 1, msg.variants.len()),
+                    Style::default().fg(theme.accent),
+                ));
+            }
+            if app.verbose {
+                // Counts are computed on each draw rather than stored on the message
+                let mut meta = format!("  #{}", msg.id);
+                if let Some(temp) = msg.temperature {
+                    meta.push_str(&format!(" · temp {:.1}", temp));
+                }
+                meta.push_str(&format!(
+                    " · {} words · {} chars",
+                    msg.content.split_whitespace().count(),
+                    msg.content.chars().count()
+                ));
+                header.push(Span::styled(meta, Style::default().fg(theme.dim)));
+            }
+            lines.push(Line::from(header));
+
+            // A reply still being revealed by the typewriter effect shows only its start
+            let content = match app.revealed_chars(msg.id) {
+                Some(shown) => msg.content.chars().take(shown).collect(),
+                None => msg.content.clone(),
+            };
+            // Reasoning is collapsed to a single line unless expanded
+            let (thinking, answer) = if msg.role == "assistant" {
+                crate::session::split_thinking(&content)
+            } else {
+                (None, content)
+            };
+            if let Some(thinking) = thinking {
+                let dim = Style::default().fg(theme.dim);
+                if app.expanded_thinking.contains(&msg.id) {
+                    lines.push(Line::from(Span::styled("  ▾ reasoning", dim)));
+                    for line in thinking.lines() {
+                        lines.push(Line::from(Span::styled(
+                            format!("  │ {}", line),
+                            dim.add_modifier(Modifier::ITALIC),
+                        )));
+                    }
+                } else {
+                    lines.push(Line::from(Span::styled(
+                        format!("  ▸ reasoning ({} lines) · Ctrl+T", thinking.lines().count()),
+                        dim,
+                    )));
+                }
+            }
 
             // Parse markdown and render content
-            let content_lines = parse_markdown_to_lines(&msg.content);
+            let content_lines = parse_markdown_to_lines(&answer, theme);
             for content_line in content_lines {
                 // Add indent to content lines
                 let mut indented_spans = vec![Span::raw("  ")];
@@ -204,36 +394,110 @@ fn draw_chat_history(f: &mut Frame, area: Rect, app: &App) {
                 lines.push(Line::from(indented_spans));
             }
         }
+
+        let suggestions = app.visible_suggestions();
+        if !suggestions.is_empty() {
+            lines.push(Line::from(""));
+            lines.push(Line::from(Span::styled(
+                format!("  Follow-ups · press Alt+1-{} to send", suggestions.len()),
+                Style::default().fg(theme.dim),
+            )));
+            for (i, suggestion) in suggestions.iter().enumerate() {
+                lines.push(Line::from(vec![
+                    Span::styled(format!("  [{}] ", i + 1), Style::default().fg(theme.accent)),
+                    Span::styled(suggestion.clone(), Style::default().fg(theme.assistant)),
+                ]));
+            }
+        }
     }
 
-    let block = Block::default()
-        .title(format!(" {} ", app.current_model))
-        .title_style(Style::default().fg(EMERALD).add_modifier(Modifier::BOLD))
-        .borders(Borders::ALL)
-        .border_style(Style::default().fg(DARK_GRAY));
+    let title = if app.verbose {
+        let words: usize = messages.iter().map(|m| m.content.split_whitespace().count()).sum();
+        let chars: usize = messages.iter().map(|m| m.content.chars().count()).sum();
+        format!(" {} · {} words · {} chars ", app.display_name(&app.current_model), words, chars)
+    } else {
+        format!(" {} ", app.display_name(&app.current_model))
+    };
+
+    // Compact keeps only the top border, which still carries the title
+    let mut block = Block::default()
+        .title(title)
+        .title_style(Style::default().fg(theme.assistant).add_modifier(Modifier::BOLD))
+        .borders(if compact { Borders::TOP } else { Borders::ALL })
+        .border_style(Style::default().fg(theme.border));
+
+    if app.has_new_message {
+        let notice = Line::from(Span::styled(
+            " ↓ new message (End) ",
+            Style::default().fg(theme.accent).add_modifier(Modifier::BOLD),
+        ))
+        .right_aligned();
+        block = if compact { block.title_top(notice) } else { block.title_bottom(notice) };
+    }
+
+    // Measure before the block is attached: line_count wraps at the width it
+    // is given and doesn't subtract the borders itself
+    let inner = block.inner(area);
+
+    // Per-message positions, so moving the focus can scroll to it
+    let mut rows = Vec::with_capacity(bounds.len());
+    let mut row = 0;
+    for (i, &(id, start)) in bounds.iter().enumerate() {
+        let end = bounds.get(i + 1).map_or(lines.len(), |b| b.1);
+        let count = Paragraph::new(lines[start..end].to_vec())
+            .wrap(Wrap { trim: false })
+            .line_count(inner.width);
+        rows.push((id, row, count));
+        row += count;
+    }
+    *app.message_rows.borrow_mut() = rows;
 
     let paragraph = Paragraph::new(lines)
-        .block(block)
         .alignment(Alignment::Left)
-        .wrap(Wrap { trim: false })
-        .scroll((app.scroll_offset as u16, 0));
+        .wrap(Wrap { trim: false });
+    let total = paragraph.line_count(inner.width);
+    let visible = inner.height as usize;
+    // While scrolled up, lines added below (a new message, a streaming or
+    // revealed reply) raise the offset by as much, so the top line stays put
+    if !app.follow && app.chat_width.get() == inner.width {
+        let grown = total.saturating_sub(app.chat_lines.get());
+        app.scroll_offset.set(app.scroll_offset.get() + grown);
+    }
+    app.chat_lines.set(total);
+    app.chat_viewport.set(visible);
+    app.chat_width.set(inner.width);
+
+    // scroll_offset counts lines up from the bottom; Paragraph scrolls from the top
+    let top = total.saturating_sub(visible).saturating_sub(app.scroll_offset.get());
+    let paragraph = paragraph
+        .block(block)
+        .scroll((top.min(u16::MAX as usize) as u16, 0));
 
     f.render_widget(paragraph, area);
 }
 
-fn draw_input_normal(f: &mut Frame, area: Rect, app: &App) {
+fn draw_input_normal(f: &mut Frame, area: Rect, app: &App, theme: &Theme) {
     let input_text = if app.is_loading {
-        format!("  {}  Generating response...", app.get_loading_spinner())
+        format!("  {}  {}", app.get_loading_spinner(), app.loading_label())
     } else {
-        format!("  {}", app.input_buffer)
+        format!("  {}", app.input_buffer.replace('\n', "\n  "))
     };
 
-    let block = Block::default()
+    let mut block = Block::default()
         .borders(Borders::ALL)
-        .border_style(Style::default().fg(DARK_GRAY));
+        .border_style(Style::default().fg(theme.border));
+    if app.multiline {
+        block = block
+            .title(" multiline · enter newline · Ctrl+E done ")
+            .title_style(Style::default().fg(theme.accent));
+    } else if let Some(id) = app.editing_message_id {
+        block = block
+            .title(format!(" editing #{} · enter save · esc cancel ", id))
+            .title_style(Style::default().fg(theme.accent));
+    }
 
     let style = if app.is_loading {
-        Style::default().fg(GRAY)
+        Style::default().fg(theme.dim)
     } else {
         Style::default().fg(Color::White)
     };
@@ -247,13 +511,63 @@ fn draw_input_normal(f: &mut Frame, area: Rect, app: &App) {
 
     // Cursor position
     if !app.is_loading && area.height > 2 && area.width > 4 {
-        let cursor_x = area.x + 3 + (app.input_buffer.len() as u16).min(area.width - 5);
-        let cursor_y = area.y + 1;
+        // End of the last line; multiline input may span several rows
+        let last_line = app.input_buffer.rsplit('\n').next().unwrap_or("");
+        let row = app.input_buffer.matches('\n').count() as u16;
+        let cursor_x = area.x + 3 + (last_line.len() as u16).min(area.width.saturating_sub(5));
+        let cursor_y = area.y + 1 + row.min(area.height.saturating_sub(3));
         f.set_cursor_position((cursor_x, cursor_y));
     }
 }
 
-fn draw_command_list(f: &mut Frame, area: Rect, app: &App) {
+// Borderless input for the compact layout: a prompt marker and up to
+// COMPACT_INPUT_ROWS rows, scrolled so the cursor stays visible
+fn draw_input_compact(f: &mut Frame, area: Rect, app: &App, theme: &Theme) {
+    const PROMPT_WIDTH: u16 = 2;
+
+    if app.is_loading {
+        let line = Line::from(Span::styled(
+            format!("{} {}", app.get_loading_spinner(), app.loading_label()),
+            Style::default().fg(theme.dim),
+        ));
+        f.render_widget(Paragraph::new(line), area);
+        return;
+    }
+
+    let marker = if app.editing_message_id.is_some() {
+        "✎ "
+    } else if app.multiline {
+        "┆ "
+    } else {
+        "› "
+    };
+    let lines: Vec<Line> = app
+        .input_buffer
+        .split('\n')
+        .map(|text| {
+            Line::from(vec![
+                Span::styled(marker, Style::default().fg(theme.accent).add_modifier(Modifier::BOLD)),
+                Span::styled(text.to_string(), Style::default().fg(Color::White)),
+            ])
+        })
+        .collect();
+
+    let row = lines.len().saturating_sub(1) as u16;
+    let last_width = app.input_buffer.rsplit('\n').next().unwrap_or("").chars().count() as u16;
+    let text_width = area.width.saturating_sub(PROMPT_WIDTH + 1);
+    let scroll_y = row.saturating_sub(area.height.saturating_sub(1));
+    let scroll_x = last_width.saturating_sub(text_width);
+
+    f.render_widget(Paragraph::new(lines).scroll((scroll_y, scroll_x)), area);
+
+    if area.width > PROMPT_WIDTH + 1 {
+        let cursor_x = area.x + PROMPT_WIDTH + last_width.min(text_width);
+        let cursor_y = area.y + row - scroll_y;
+        f.set_cursor_position((cursor_x, cursor_y));
+    }
+}
+
+fn draw_command_list(f: &mut Frame, area: Rect, app: &App, theme: &Theme) {
     let commands = app.get_filtered_commands();
     let mut items = Vec::new();
 
@@ -261,7 +575,7 @@ fn draw_command_list(f: &mut Frame, area: Rect, app: &App) {
     let search_text = format!("/{}", app.command_search);
     items.push(ListItem::new(Line::from(vec![
         Span::raw("  "),
-        Span::styled(search_text, Style::default().fg(ORANGE).add_modifier(Modifier::BOLD)),
+        Span::styled(search_text, Style::default().fg(theme.accent).add_modifier(Modifier::BOLD)),
     ])));
 
     items.push(ListItem::new(Line::from("")));
@@ -270,7 +584,7 @@ fn draw_command_list(f: &mut Frame, area: Rect, app: &App) {
     if commands.is_empty() {
         items.push(ListItem::new(Line::from(Span::styled(
             "  No matching commands",
-            Style::default().fg(GRAY),
+            Style::default().fg(theme.dim),
         ))));
     } else {
         for (idx, cmd) in commands.iter().enumerate() {
@@ -279,20 +593,20 @@ fn draw_command_list(f: &mut Frame, area: Rect, app: &App) {
             let line = if is_selected {
                 Line::from(vec![
                     Span::raw("  "),
-                    Span::styled("▶ ", Style::default().fg(ORANGE)),
+                    Span::styled("▶ ", Style::default().fg(theme.accent)),
                     Span::styled(
                         format!("{:<12}", cmd.name),
-                        Style::default().fg(ORANGE).add_modifier(Modifier::BOLD),
+                        Style::default().fg(theme.accent).add_modifier(Modifier::BOLD),
                     ),
                     Span::raw(" "),
-                    Span::styled(cmd.help, Style::default().fg(EMERALD)),
+                    Span::styled(cmd.help, Style::default().fg(theme.assistant)),
                 ])
             } else {
                 Line::from(vec![
                     Span::raw("    "),
-                    Span::styled(format!("{:<12}", cmd.name), Style::default().fg(EMERALD)),
+                    Span::styled(format!("{:<12}", cmd.name), Style::default().fg(theme.assistant)),
                     Span::raw(" "),
-                    Span::styled(cmd.help, Style::default().fg(GRAY)),
+                    Span::styled(cmd.help, Style::default().fg(theme.dim)),
                 ])
             };
 
@@ -304,38 +618,74 @@ fn draw_command_list(f: &mut Frame, area: Rect, app: &App) {
     items.push(ListItem::new(Line::from("")));
     items.push(ListItem::new(Line::from(Span::styled(
         "  ↑↓ navigate  •  enter select  •  esc cancel",
-        Style::default().fg(DARK_GRAY),
+        Style::default().fg(theme.border),
     ))));
 
     let block = Block::default()
         .borders(Borders::ALL)
-        .border_style(Style::default().fg(ORANGE));
+        .border_style(Style::default().fg(theme.accent));
 
     let list = List::new(items).block(block);
     f.render_widget(list, area);
 }
 
-fn draw_modal_form(f: &mut Frame, area: Rect, app: &App, modal_type: &ModalType) {
+fn draw_modal_form(f: &mut Frame, area: Rect, app: &App, modal_type: &ModalType, theme: &Theme) {
     let mut items = Vec::new();
 
     match modal_type {
         ModalType::ModelSelector => {
-            items.push(ListItem::new(Line::from(Span::styled(
+            let models = app.filtered_model_names();
+
+            // Three rows per model inside the border, after the title, filter
+            // and blank rows, before the footer, and one for the server header
+            let server_row = models.iter().any(|name| app.is_server_model(name)) as u16;
+            let rows = area.height.saturating_sub(2 + 3 + 1 + server_row);
+            app.model_viewport.set((rows / 3).max(1) as usize);
+            app.keep_model_visible();
+            let first = app.model_scroll.get().min(models.len());
+            let shown = &models[first..(first + app.model_viewport.get()).min(models.len())];
+
+            let mut title = vec![Span::styled(
                 "  Select Model",
-                Style::default().fg(ORANGE).add_modifier(Modifier::BOLD),
-            ))));
+                Style::default().fg(theme.accent).add_modifier(Modifier::BOLD),
+            )];
+            if shown.len() < models.len() {
+                title.push(Span::styled(
+                    format!("  {}-{} of {}", first + 1, first + shown.len(), models.len()),
+                    Style::default().fg(theme.dim),
+                ));
+            }
+            items.push(ListItem::new(Line::from(title)));
+            items.push(ListItem::new(Line::from(if app.modal_input.is_empty() {
+                Span::styled("  Type to filter by name", Style::default().fg(theme.dim).add_modifier(Modifier::ITALIC))
+            } else {
+                Span::styled(format!("  Filter: {}_", app.modal_input), Style::default().fg(theme.assistant))
+            })));
             items.push(ListItem::new(Line::from("")));
 
-            let mut models: Vec<_> = app.model_registry.iter().collect();
-            models.sort_by(|a, b| a.0.cmp(b.0));
-
             if models.is_empty() {
-                items.push(ListItem::new(Line::from(Span::styled(
-                    "  No models available",
-                    Style::default().fg(GRAY),
-                ))));
+                let message = if app.modal_input.is_empty() {
+                    "  No models available".to_string()
+                } else {
+                    format!("  No models match '{}'", app.modal_input.trim())
+                };
+                items.push(ListItem::new(Line::from(Span::styled(message, Style::default().fg(theme.dim)))));
             } else {
-                for (idx, (name, path)) in models.iter().enumerate() {
+                let mut shown_server_header = false;
+                for (idx, name) in shown.iter().enumerate().map(|(i, name)| (first + i, name)) {
+                    let is_server = app.is_server_model(name);
+                    if is_server && !shown_server_header {
+                        shown_server_header = true;
+                        items.push(ListItem::new(Line::from(Span::styled(
+                            "  Server models",
+                            Style::default().fg(theme.dim).add_modifier(Modifier::BOLD),
+                        ))));
+                    }
+                    let path = app
+                        .model_registry
+                        .get(name)
+                        .map(String::as_str)
+                        .unwrap_or("served by remote server");
                     let is_selected = idx == app.selected_model_idx;
                     let is_current = name.as_str() == app.current_model.as_str();
 
@@ -343,26 +693,26 @@ fn draw_modal_form(f: &mut Frame, area: Rect, app: &App, modal_type: &ModalType)
                     let name_line = if is_selected {
                         Line::from(vec![
                             Span::raw("  "),
-                            Span::styled("▶ ", Style::default().fg(ORANGE)),
+                            Span::styled("▶ ", Style::default().fg(theme.accent)),
                             Span::styled(
-                                name.as_str(),
-                                Style::default().fg(ORANGE).add_modifier(Modifier::BOLD),
+                                name.clone(),
+                                Style::default().fg(theme.accent).add_modifier(Modifier::BOLD),
                             ),
                             Span::styled(
                                 if is_current { "  ✓" } else { "" },
-                                Style::default().fg(ORANGE),
+                                Style::default().fg(theme.accent),
                             ),
                         ])
                     } else {
                         Line::from(vec![
                             Span::raw("    "),
                             Span::styled(
-                                name.as_str(),
-                                Style::default().fg(if is_current { ORANGE } else { EMERALD }),
+                                name.clone(),
+                                Style::default().fg(if is_current { theme.accent } else { theme.assistant }),
                             ),
                             Span::styled(
                                 if is_current { "  ✓" } else { "" },
-                                Style::default().fg(ORANGE),
+                                Style::default().fg(theme.accent),
                             ),
                         ])
                     };
@@ -371,7 +721,14 @@ fn draw_modal_form(f: &mut Frame, area: Rect, app: &App, modal_type: &ModalType)
                     // Full path in gray
                     let path_line = Line::from(vec![
                         Span::raw("     "),
-                        Span::styled(path.as_str(), Style::default().fg(GRAY)),
+                        Span::styled(
+                            path,
+                            if is_server {
+                                Style::default().fg(theme.dim).add_modifier(Modifier::ITALIC)
+                            } else {
+                                Style::default().fg(theme.dim)
+                            },
+                        ),
                     ]);
                     items.push(ListItem::new(path_line));
 
@@ -380,41 +737,42 @@ fn draw_modal_form(f: &mut Frame, area: Rect, app: &App, modal_type: &ModalType)
             }
 
             items.push(ListItem::new(Line::from(Span::styled(
-                "  ↑↓ navigate  •  enter select  •  esc cancel",
-                Style::default().fg(DARK_GRAY),
+                "  ↑↓ navigate  •  type to filter  •  enter select  •  ctrl-r rescan  •  esc cancel",
+                Style::default().fg(theme.border),
             ))));
         }
         ModalType::SetTemperature => {
             items.push(ListItem::new(Line::from(Span::styled(
                 "  Set Temperature",
-                Style::default().fg(ORANGE).add_modifier(Modifier::BOLD),
+                Style::default().fg(theme.accent).add_modifier(Modifier::BOLD),
             ))));
             items.push(ListItem::new(Line::from("")));
 
-            let current_text = format!("Current: {:.1}  (Range: 0.0 - 2.0)", app.temperature);
+            let (min, max) = app.temperature_range;
+            let current_text = format!("Current: {:.1}  (Range: {:.1} - {:.1})", app.temperature, min, max);
             items.push(ListItem::new(Line::from(vec![
                 Span::raw("  "),
-                Span::styled(current_text, Style::default().fg(GRAY)),
+                Span::styled(current_text, Style::default().fg(theme.dim)),
             ])));
             items.push(ListItem::new(Line::from("")));
 
             items.push(ListItem::new(Line::from(Span::styled(
                 "  0.1 - 0.5:  Focused, factual responses",
-                Style::default().fg(GRAY).add_modifier(Modifier::ITALIC),
+                Style::default().fg(theme.dim).add_modifier(Modifier::ITALIC),
             ))));
             items.push(ListItem::new(Line::from(Span::styled(
                 "  0.7 - 1.0:  Balanced (default 0.7)",
-                Style::default().fg(GRAY).add_modifier(Modifier::ITALIC),
+                Style::default().fg(theme.dim).add_modifier(Modifier::ITALIC),
             ))));
             items.push(ListItem::new(Line::from(Span::styled(
                 "  1.5 - 2.0:  Creative, varied responses",
-                Style::default().fg(GRAY).add_modifier(Modifier::ITALIC),
+                Style::default().fg(theme.dim).add_modifier(Modifier::ITALIC),
             ))));
             items.push(ListItem::new(Line::from("")));
 
             let input_line = Line::from(vec![
                 Span::raw("  > "),
-                Span::styled(&app.modal_input, Style::default().fg(EMERALD)),
+                Span::styled(&app.modal_input, Style::default().fg(theme.assistant)),
                 Span::raw("_"),
             ]);
             items.push(ListItem::new(input_line));
@@ -422,19 +780,19 @@ fn draw_modal_form(f: &mut Frame, area: Rect, app: &App, modal_type: &ModalType)
         ModalType::DeleteMessage => {
             items.push(ListItem::new(Line::from(Span::styled(
                 "  Delete Message",
-                Style::default().fg(ORANGE).add_modifier(Modifier::BOLD),
+                Style::default().fg(theme.accent).add_modifier(Modifier::BOLD),
             ))));
             items.push(ListItem::new(Line::from("")));
-            items.push(ListItem::new(Line::from("  Enter message ID or 'all' to clear history")));
+            items.push(ListItem::new(Line::from("  Enter message ID, range, list or 'all' to clear history")));
             items.push(ListItem::new(Line::from(Span::styled(
-                "  Example: 5 (delete message 5) or all (clear all)",
-                Style::default().fg(GRAY).add_modifier(Modifier::ITALIC),
+                "  Example: 5, 3-7, 3,5,8 or all (clear all)",
+                Style::default().fg(theme.dim).add_modifier(Modifier::ITALIC),
             ))));
             items.push(ListItem::new(Line::from("")));
 
             let input_line = Line::from(vec![
                 Span::raw("  > "),
-                Span::styled(&app.modal_input, Style::default().fg(EMERALD)),
+                Span::styled(&app.modal_input, Style::default().fg(theme.assistant)),
                 Span::raw("_"),
             ]);
             items.push(ListItem::new(input_line));
@@ -442,19 +800,19 @@ fn draw_modal_form(f: &mut Frame, area: Rect, app: &App, modal_type: &ModalType)
         ModalType::SaveResponse => {
             items.push(ListItem::new(Line::from(Span::styled(
                 "  Save Response",
-                Style::default().fg(ORANGE).add_modifier(Modifier::BOLD),
+                Style::default().fg(theme.accent).add_modifier(Modifier::BOLD),
             ))));
             items.push(ListItem::new(Line::from("")));
-            items.push(ListItem::new(Line::from("  Enter message ID to export to file")));
+            items.push(ListItem::new(Line::from("  Enter message ID to export to file, or all")));
             items.push(ListItem::new(Line::from(Span::styled(
-                "  Example: 3 (saves Chat/saved/msg_3.txt)",
-                Style::default().fg(GRAY).add_modifier(Modifier::ITALIC),
+                format!("  Example: 3 (saves {})", app.saved_message_path(3)),
+                Style::default().fg(theme.dim).add_modifier(Modifier::ITALIC),
             ))));
             items.push(ListItem::new(Line::from("")));
 
             let input_line = Line::from(vec![
                 Span::raw("  > "),
-                Span::styled(&app.modal_input, Style::default().fg(EMERALD)),
+                Span::styled(&app.modal_input, Style::default().fg(theme.assistant)),
                 Span::raw("_"),
             ]);
             items.push(ListItem::new(input_line));
@@ -462,104 +820,435 @@ fn draw_modal_form(f: &mut Frame, area: Rect, app: &App, modal_type: &ModalType)
         ModalType::RenameSession => {
             items.push(ListItem::new(Line::from(Span::styled(
                 "  Rename Session",
-                Style::default().fg(ORANGE).add_modifier(Modifier::BOLD),
+                Style::default().fg(theme.accent).add_modifier(Modifier::BOLD),
             ))));
             items.push(ListItem::new(Line::from("")));
             items.push(ListItem::new(Line::from("  Enter new label for this session")));
             items.push(ListItem::new(Line::from(Span::styled(
                 "  Example: 'Research Project' or 'Bug Fix Discussion'",
-                Style::default().fg(GRAY).add_modifier(Modifier::ITALIC),
+                Style::default().fg(theme.dim).add_modifier(Modifier::ITALIC),
             ))));
             items.push(ListItem::new(Line::from("")));
 
             let input_line = Line::from(vec![
                 Span::raw("  > "),
-                Span::styled(&app.modal_input, Style::default().fg(EMERALD)),
+                Span::styled(&app.modal_input, Style::default().fg(theme.assistant)),
                 Span::raw("_"),
             ]);
             items.push(ListItem::new(input_line));
         }
+        ModalType::ErrorDetails | ModalType::DebugRequest | ModalType::Output => {}
+        ModalType::Confirm => {
+            items.push(ListItem::new(Line::from(Span::styled(
+                "  Confirm",
+                Style::default().fg(theme.error).add_modifier(Modifier::BOLD),
+            ))));
+            items.push(ListItem::new(Line::from("")));
+            let question = app.pending_confirm.as_ref().map(|(q, _)| q.as_str()).unwrap_or("Are you sure?");
+            items.push(ListItem::new(Line::from(format!("  {}", question))));
+            items.push(ListItem::new(Line::from(Span::styled(
+                "  /undo can restore it afterwards",
+                Style::default().fg(theme.dim).add_modifier(Modifier::ITALIC),
+            ))));
+            items.push(ListItem::new(Line::from("")));
+            items.push(ListItem::new(Line::from(Span::styled(
+                "  y yes  •  n/esc no",
+                Style::default().fg(theme.border),
+            ))));
+        }
         ModalType::LoadPrompt => {
             items.push(ListItem::new(Line::from(Span::styled(
                 "  Load Prompt",
-                Style::default().fg(ORANGE).add_modifier(Modifier::BOLD),
+                Style::default().fg(theme.accent).add_modifier(Modifier::BOLD),
             ))));
             items.push(ListItem::new(Line::from("")));
-            items.push(ListItem::new(Line::from("  Enter prompt ID or 'list' to see available")));
+
+            if app.prompt_list.is_empty() {
+                items.push(ListItem::new(Line::from(Span::styled(
+                    "  No prompts available",
+                    Style::default().fg(theme.dim),
+                ))));
+            }
+            for (idx, (name, _)) in app.prompt_list.iter().enumerate() {
+                let line = if idx == app.selected_prompt_idx {
+                    Line::from(vec![
+                        Span::raw("  "),
+                        Span::styled("▶ ", Style::default().fg(theme.accent)),
+                        Span::styled(
+                            format!("[{}] {}", idx + 1, name),
+                            Style::default().fg(theme.accent).add_modifier(Modifier::BOLD),
+                        ),
+                    ])
+                } else {
+                    Line::from(vec![
+                        Span::raw("    "),
+                        Span::styled(format!("[{}] {}", idx + 1, name), Style::default().fg(theme.assistant)),
+                    ])
+                };
+                items.push(ListItem::new(line));
+            }
+
+            // Preview the highlighted prompt before it is sent
+            if let Some((_, content)) = app.prompt_list.get(app.selected_prompt_idx) {
+                items.push(ListItem::new(Line::from("")));
+                items.push(ListItem::new(Line::from(Span::styled(
+                    "  Preview",
+                    Style::default().fg(theme.dim).add_modifier(Modifier::BOLD),
+                ))));
+                const PREVIEW_LINES: usize = 6;
+                for line in content.lines().take(PREVIEW_LINES) {
+                    items.push(ListItem::new(Line::from(Span::styled(
+                        format!("  │ {}", line),
+                        Style::default().fg(theme.dim),
+                    ))));
+                }
+                let remaining = content.lines().count().saturating_sub(PREVIEW_LINES);
+                if remaining > 0 {
+                    items.push(ListItem::new(Line::from(Span::styled(
+                        format!("  │ … {} more lines", remaining),
+                        Style::default().fg(theme.dim).add_modifier(Modifier::ITALIC),
+                    ))));
+                }
+            }
+            items.push(ListItem::new(Line::from("")));
             items.push(ListItem::new(Line::from(Span::styled(
-                "  Example: coding (loads static/coding.txt) or list",
-                Style::default().fg(GRAY).add_modifier(Modifier::ITALIC),
+                "  ↑↓ navigate  •  enter load  •  or type an id / list  •  esc cancel",
+                Style::default().fg(theme.border),
             ))));
-            items.push(ListItem::new(Line::from("")));
 
             let input_line = Line::from(vec![
                 Span::raw("  > "),
-                Span::styled(&app.modal_input, Style::default().fg(EMERALD)),
+                Span::styled(&app.modal_input, Style::default().fg(theme.assistant)),
                 Span::raw("_"),
             ]);
             items.push(ListItem::new(input_line));
         }
+        ModalType::PickCandidate => {
+            items.push(ListItem::new(Line::from(Span::styled(
+                format!("  Pick a Reply ({} candidates)", app.candidates.len()),
+                Style::default().fg(theme.accent).add_modifier(Modifier::BOLD),
+            ))));
+            items.push(ListItem::new(Line::from("")));
+
+            for (idx, candidate) in app.candidates.iter().enumerate() {
+                // Preview the answer, not the reasoning in front of it
+                let answer = crate::session::split_thinking(candidate).1;
+                let first_line = answer.lines().find(|l| !l.trim().is_empty()).unwrap_or("(empty)");
+                let label = format!("[{}] {}", idx + 1, first_line);
+                let line = if idx == app.selected_candidate_idx {
+                    Line::from(vec![
+                        Span::raw("  "),
+                        Span::styled("▶ ", Style::default().fg(theme.accent)),
+                        Span::styled(label, Style::default().fg(theme.accent).add_modifier(Modifier::BOLD)),
+                    ])
+                } else {
+                    Line::from(vec![
+                        Span::raw("    "),
+                        Span::styled(label, Style::default().fg(theme.assistant)),
+                    ])
+                };
+                items.push(ListItem::new(line));
+            }
+
+            if let Some(candidate) = app.candidates.get(app.selected_candidate_idx) {
+                let answer = crate::session::split_thinking(candidate).1;
+                items.push(ListItem::new(Line::from("")));
+                const PREVIEW_LINES: usize = 8;
+                for line in answer.lines().take(PREVIEW_LINES) {
+                    items.push(ListItem::new(Line::from(Span::styled(
+                        format!("  │ {}", line),
+                        Style::default().fg(theme.dim),
+                    ))));
+                }
+                let remaining = answer.lines().count().saturating_sub(PREVIEW_LINES);
+                if remaining > 0 {
+                    items.push(ListItem::new(Line::from(Span::styled(
+                        format!("  │ … {} more lines", remaining),
+                        Style::default().fg(theme.dim).add_modifier(Modifier::ITALIC),
+                    ))));
+                }
+            }
+            items.push(ListItem::new(Line::from("")));
+            items.push(ListItem::new(Line::from(Span::styled(
+                "  ↑↓ navigate  •  1-9/enter accept  •  esc discard all",
+                Style::default().fg(theme.border),
+            ))));
+        }
+        ModalType::Settings => {
+            items.push(ListItem::new(Line::from(Span::styled(
+                "  Settings",
+                Style::default().fg(theme.accent).add_modifier(Modifier::BOLD),
+            ))));
+            items.push(ListItem::new(Line::from("")));
+
+            for (idx, row) in app.settings.iter().enumerate() {
+                let selected = idx == app.selected_setting_idx;
+                // The selected row shows what is being typed in place of its value
+                let value = if selected && !app.modal_input.is_empty() {
+                    format!("{}_", app.modal_input)
+                } else {
+                    row.value.clone()
+                };
+                let value_style = if !row.editable && row.name != "model" {
+                    Style::default().fg(theme.dim)
+                } else if selected {
+                    Style::default().fg(theme.accent).add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default().fg(theme.assistant)
+                };
+                items.push(ListItem::new(Line::from(vec![
+                    Span::raw("  "),
+                    Span::styled(if selected { "▶ " } else { "  " }, Style::default().fg(theme.accent)),
+                    Span::styled(format!("{:<12}", row.name), Style::default().fg(theme.user)),
+                    Span::styled(format!("{:<20}", value), value_style),
+                    Span::styled(row.range.clone(), Style::default().fg(theme.dim)),
+                ])));
+            }
+
+            items.push(ListItem::new(Line::from("")));
+            items.push(ListItem::new(Line::from(Span::styled(
+                "  ↑↓ navigate  •  type a value, enter apply  •  esc close",
+                Style::default().fg(theme.border),
+            ))));
+        }
     }
 
     let block = Block::default()
         .borders(Borders::ALL)
-        .border_style(Style::default().fg(ORANGE));
+        .border_style(Style::default().fg(theme.accent));
 
     let list = List::new(items).block(block);
     f.render_widget(list, area);
 }
 
-fn draw_status_bar(f: &mut Frame, area: Rect, app: &App) {
+// Full error text, wrapped; the status bar only has room for one line of it
+fn draw_error_details(f: &mut Frame, area: Rect, app: &App, theme: &Theme) {
+    let mut lines = vec![
+        Line::from(Span::styled(
+            "  Error Details",
+            Style::default().fg(theme.error).add_modifier(Modifier::BOLD),
+        )),
+        Line::from(""),
+    ];
+    for line in app.last_error.as_deref().unwrap_or("No error").lines() {
+        lines.push(Line::from(format!("  {}", line)));
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        "  enter/esc close",
+        Style::default().fg(theme.border),
+    )));
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(theme.error));
+
+    let paragraph = Paragraph::new(lines)
+        .block(block)
+        .wrap(Wrap { trim: false });
+    f.render_widget(paragraph, area);
+}
+
+// The request JSON from /debug request, scrolled with ↑/↓
+fn draw_debug_request(f: &mut Frame, area: Rect, app: &App, theme: &Theme) {
+    let lines: Vec<Line> = app
+        .debug_request
+        .lines()
+        .map(|line| Line::from(Span::styled(format!("  {}", line), Style::default().fg(theme.code))))
+        .collect();
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(theme.accent))
+        .title(Span::styled(
+            " Next request (not sent) ",
+            Style::default().fg(theme.accent).add_modifier(Modifier::BOLD),
+        ))
+        .title_bottom(Span::styled(" ↑↓ scroll  enter/esc close ", Style::default().fg(theme.border)));
+
+    let paragraph = Paragraph::new(lines)
+        .block(block)
+        .wrap(Wrap { trim: false })
+        .scroll((app.debug_scroll, 0));
+    f.render_widget(paragraph, area);
+}
+
+// Text returned by a command (/diff, /info, /search, ...), scrollable
+fn draw_output(f: &mut Frame, area: Rect, app: &App, theme: &Theme) {
+    let lines: Vec<Line> = app
+        .output
+        .lines
+        .iter()
+        .map(|spans| {
+            let mut line = vec![Span::raw("  ")];
+            line.extend(spans.iter().map(|(tone, text)| {
+                let style = match tone {
+                    Tone::Plain => Style::default(),
+                    Tone::Dim => Style::default().fg(theme.dim),
+                    Tone::Label => Style::default().fg(theme.assistant),
+                    Tone::Added => Style::default().fg(theme.assistant),
+                    Tone::Removed => Style::default().fg(theme.error),
+                    Tone::Highlight => Style::default().fg(Color::Black).bg(AMBER),
+                };
+                Span::styled(text.as_str(), style)
+            }));
+            Line::from(line)
+        })
+        .collect();
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(theme.accent))
+        .title(Span::styled(
+            format!(" {} ", app.output.title),
+            Style::default().fg(theme.accent).add_modifier(Modifier::BOLD),
+        ))
+        .title_bottom(Span::styled(" ↑↓ scroll  enter/esc close ", Style::default().fg(theme.border)));
+
+    let paragraph = Paragraph::new(lines)
+        .block(block)
+        .wrap(Wrap { trim: false })
+        .scroll((app.output_scroll, 0));
+    f.render_widget(paragraph, area);
+}
+
+// Cut `text` to at most `width` characters, ending with an ellipsis. Prefers
+// breaking at a word boundary so the status bar never ends mid-word.
+fn truncate_with_ellipsis(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        return text.to_string();
+    }
+    let keep: String = text.chars().take(width.saturating_sub(1)).collect();
+    let cut = match keep.rfind(' ') {
+        Some(idx) if idx > keep.len() / 2 => keep[..idx].trim_end(),
+        _ => keep.as_str(),
+    };
+    format!("{}…", cut)
+}
+
+// Server health indicator at the start of the status bar
+const HEALTH_DOT: &str = " ●";
+
+fn draw_status_bar(f: &mut Frame, area: Rect, app: &App, theme: &Theme) {
+    // Sampling changed from the config defaults; /info lists what differs
+    let custom = !app.custom_sampling().is_empty();
+    let custom_tag = if custom { " [custom]" } else { "" };
+    // The context gauge takes the right end of wide bars; errors get the whole width
+    let gauge_window = app
+        .context_window()
+        .filter(|&w| w > 0 && area.width >= GAUGE_MIN_WIDTH && app.error_message.is_none());
+    let gauge_width = if gauge_window.is_some() { GAUGE_WIDTH as usize } else { 0 };
+    // Room left for the text after the health dot
+    let width = (area.width as usize).saturating_sub(Span::raw(HEALTH_DOT).width() + gauge_width);
     let status_text = if let Some(error) = &app.error_message {
-        format!(" ✗ Error: {}", error)
+        let full = format!(" ✗ Error: {}", error.replace('\n', " "));
+        if full.chars().count() > width {
+            let hint = "  (? details)";
+            let body = truncate_with_ellipsis(&full, width.saturating_sub(hint.chars().count()));
+            format!("{}{}", body, hint)
+        } else {
+            full
+        }
+    } else if let Some(status) = &app.status_message {
+        truncate_with_ellipsis(&format!(" ✓ {}", status.replace('\n', " ")), width)
     } else if app.is_loading {
+        let countdown = app
+            .timeout_countdown()
+            .map(|secs| format!("  │  Timeout in {}s", secs))
+            .unwrap_or_default();
         format!(
-            " {} Generating  │  Temp: {:.1}",
+            " {} Generating  │  {} tok  │  {:.1} tok/s  │  Temp: {:.1}{}{}",
             app.get_loading_spinner(),
-            app.temperature
+            app.streaming_tokens,
+            app.live_tps,
+            app.generation_temperature(),
+            custom_tag,
+            countdown
         )
     } else {
         // Adapt status bar based on terminal width
         if area.width < 50 {
             // Compact mode for small terminals
             format!(
-                " ⚡ {:.1}tps │ {:.2}s │ {}°",
+                " ⚡ {:.1}tps │ {:.2}s │ {}°{}",
                 app.last_tps,
                 app.last_response_time,
-                app.temperature as i32
+                app.temperature as i32,
+                if custom { "*" } else { "" }
             )
         } else if area.width < 80 {
             // Medium mode
             format!(
-                " ⚡ {:.1} tok/s  │  {:.2}s  │  {:.1}°  │  {} msg",
+                " ⚡ {:.1} tok/s  │  {:.2}s  │  {:.1}°{}  │  {} msg",
                 app.last_tps,
                 app.last_response_time,
                 app.temperature,
+                custom_tag,
                 app.session.messages.len()
             )
         } else {
             // Full mode for wide terminals
             format!(
-                " ⚡ {:.1} tok/s  │  {:.2}s  │  🌡️  {:.1}  │  {} messages",
+                " ⚡ {:.1} tok/s  │  {:.2}s  │  🌡️  {:.1}{}  │  {} messages",
                 app.last_tps,
                 app.last_response_time,
                 app.temperature,
+                custom_tag,
                 app.session.messages.len()
             )
         }
     };
 
     let style = if app.error_message.is_some() {
-        Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)
+        Style::default().fg(theme.error).add_modifier(Modifier::BOLD)
+    } else if app.status_message.is_some() {
+        Style::default().fg(theme.assistant).add_modifier(Modifier::BOLD)
     } else if app.is_loading {
-        Style::default().fg(EMERALD)
+        Style::default().fg(theme.assistant)
     } else {
-        Style::default().fg(GRAY)
+        Style::default().fg(theme.dim)
     };
 
-    let paragraph = Paragraph::new(status_text)
-        .style(style)
-        .alignment(Alignment::Left);
+    // Server health from the periodic /health check; dim until the first result
+    let dot_color = match app.server_alive {
+        Some(true) => theme.assistant,
+        Some(false) => theme.error,
+        None => theme.dim,
+    };
+    let line = Line::from(vec![
+        Span::styled(HEALTH_DOT, Style::default().fg(dot_color)),
+        Span::styled(status_text, style),
+    ]);
 
-    f.render_widget(paragraph, area);
+    let paragraph = Paragraph::new(line).alignment(Alignment::Left);
+
+    let Some(window) = gauge_window else {
+        f.render_widget(paragraph, area);
+        return;
+    };
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Min(0), Constraint::Length(GAUGE_WIDTH)])
+        .split(area);
+    f.render_widget(paragraph, chunks[0]);
+    draw_context_gauge(f, chunks[1], app.context_used(), window, theme);
+}
+
+// used / window as a line bar, green until GAUGE_WARN, then amber, then red
+fn draw_context_gauge(f: &mut Frame, area: Rect, used: u32, window: u32, theme: &Theme) {
+    let ratio = used as f64 / window as f64;
+    let color = if ratio >= GAUGE_CRITICAL {
+        theme.error
+    } else if ratio >= GAUGE_WARN {
+        AMBER
+    } else {
+        theme.assistant
+    };
+    let gauge = LineGauge::default()
+        .ratio(ratio.min(1.0))
+        .label(Span::styled(format!("ctx {:>3.0}% ", ratio * 100.0), Style::default().fg(color)))
+        .filled_style(Style::default().fg(color))
+        .unfilled_style(Style::default().fg(theme.dim));
+
+    f.render_widget(gauge, area);
 }
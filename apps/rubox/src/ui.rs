@@ -1,6 +1,43 @@
-use std::io::{self, Write};
+use std::io::{self, IsTerminal, Write};
+use std::sync::OnceLock;
 use crate::config::RuboxConfig;
 
+pub const ORANGE: &str = "\x1b[38;5;208m";
+pub const EMERALD: &str = "\x1b[38;5;48m";
+pub const RESET: &str = "\x1b[0m";
+pub const CLEAR_LINE: &str = "\x1b[K";
+
+static COLOR_ENABLED: OnceLock<bool> = OnceLock::new();
+
+// Escape codes are only emitted when stdout is a terminal and NO_COLOR is unset,
+// so piped output (e.g. `rubox -p ... > out.txt`) stays clean text.
+pub fn color_enabled() -> bool {
+    *COLOR_ENABLED.get_or_init(|| {
+        let no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+        io::stdout().is_terminal() && !no_color
+    })
+}
+
+// Returns the escape code, or "" when colors are disabled
+pub fn ansi(code: &str) -> &str {
+    if color_enabled() { code } else { "" }
+}
+
+// Strip configured colors (and `colored` output) when the terminal can't use them
+pub fn init_color_support(config: &mut RuboxConfig) {
+    if color_enabled() {
+        return;
+    }
+
+    colored::control::set_override(false);
+    config.ui.color_orange.clear();
+    config.ui.color_red.clear();
+    config.ui.color_dark_orange.clear();
+    config.ui.color_bright_red.clear();
+    config.ui.color_white.clear();
+    config.ui.color_reset.clear();
+}
+
 #[allow(dead_code)]
 pub fn display_colored(message: &str, color: &str, reset: &str) {
     println!("{}{}{}", color, message, reset);
@@ -22,17 +59,22 @@ pub fn display_model_list(models: &[String], config: &RuboxConfig) {
         config.ui.color_orange, config.ui.color_reset
     );
     for (i, model) in models.iter().enumerate() {
-        println!("  {}[{}] {}{}",
-            config.ui.color_orange, i + 1, model, config.ui.color_reset);
+        let missing = if config.model_path_exists(model) { "" } else { " (file not found)" };
+        println!("  {}[{}] {}{}{}",
+            config.ui.color_orange, i + 1, model, missing, config.ui.color_reset);
     }
     println!();
 }
 
 #[allow(dead_code)]
 pub fn read_model_selection(models: &[String]) -> anyhow::Result<Vec<usize>> {
-    let orange = "\x1b[38;5;208m";
-    let reset = "\x1b[0m";
-    println!("{}Choose Model (Example: 1 for chat-mode and 1,2 for multiple responses):{}", orange, reset);
+    // Callers index the list with the result, so an empty one must never get this far
+    if models.is_empty() {
+        anyhow::bail!("No models to choose from");
+    }
+    let orange = ansi(ORANGE);
+    let reset = ansi(RESET);
+    println!("{}Choose Model (Example: 1 for chat-mode, 1,2 or qwen* for multiple responses, * for all):{}", orange, reset);
     print!("{}", orange);
     io::stdout().flush()?;
 
@@ -41,15 +83,44 @@ pub fn read_model_selection(models: &[String]) -> anyhow::Result<Vec<usize>> {
     println!("{}", reset);
     println!();
 
+    let selected = parse_model_selection(&input, models)?;
+
+    // Patterns can pull in more than the user expected, so list what will run
+    if selected.len() > 1 {
+        let names: Vec<&str> = selected.iter().map(|&i| models[i].as_str()).collect();
+        println!("{}Selected: {}{}", orange, names.join(", "), reset);
+        println!();
+    }
+
+    Ok(selected)
+}
+
+// Comma-separated entries: 1-based numbers, `*` for every model, or a prefix
+// pattern like `qwen*` (case-insensitive). Returns indices into `models` in the
+// order given, without duplicates. Out-of-range numbers are skipped as before,
+// but a pattern that matches nothing is an error so typos aren't silently dropped.
+pub fn parse_model_selection(input: &str, models: &[String]) -> anyhow::Result<Vec<usize>> {
     let mut selected = Vec::new();
-    for index_str in input.trim().split(',') {
-        if let Ok(index) = index_str.trim().parse::<usize>() {
+    for entry in input.trim().split(',').map(str::trim) {
+        if let Some(prefix) = entry.strip_suffix('*') {
+            let prefix = prefix.to_lowercase();
+            let matches: Vec<usize> = (0..models.len())
+                .filter(|&i| models[i].to_lowercase().starts_with(&prefix))
+                .collect();
+            if matches.is_empty() {
+                anyhow::bail!("No models match '{}'", entry);
+            }
+            selected.extend(matches);
+        } else if let Ok(index) = entry.parse::<usize>() {
             if index > 0 && index <= models.len() {
                 selected.push(index - 1);
             }
         }
     }
 
+    let mut seen = std::collections::HashSet::new();
+    selected.retain(|i| seen.insert(*i));
+
     if selected.is_empty() {
         anyhow::bail!("Invalid model selection");
     }
@@ -74,8 +145,8 @@ pub fn read_input() -> anyhow::Result<String> {
 
     // If line ends with backslash, enter multiline mode
     if first_line.ends_with('\\') {
-        println!("{}Multi-line mode (empty line to finish){}",
-            "  ".bright_green(), "");
+        println!("{}Multi-line mode (empty line to finish)",
+            "  ".bright_green());
         let trimmed = first_line.trim_end_matches('\\').to_string();
         let mut lines = vec![trimmed];
 
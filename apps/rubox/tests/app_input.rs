@@ -0,0 +1,56 @@
+// How App::submit_input treats what is typed in the chat box
+
+use rubox::tui::{App, UIMode};
+use rubox::{LlmClient, RuboxConfig};
+
+fn app_with_input(input: &str) -> App {
+    let config = RuboxConfig::default();
+    let mut app = App::new("qwen3-vl".to_string(), 0.7, config.models.registry.clone(), LlmClient::new(&config));
+    app.input_buffer = input.to_string();
+    app
+}
+
+#[test]
+fn whitespace_only_input_is_dropped_silently() {
+    let mut app = app_with_input("  \n\t ");
+
+    assert_eq!(app.submit_input(), None);
+    assert!(app.input_buffer.is_empty());
+    assert_eq!(app.error_message, None);
+    assert_eq!(app.mode, UIMode::Chat);
+}
+
+#[test]
+fn messages_and_commands_are_trimmed() {
+    assert_eq!(app_with_input("  hello world \n").submit_input().as_deref(), Some("hello world"));
+    assert_eq!(app_with_input(" /help ").submit_input().as_deref(), Some("/help"));
+}
+
+#[test]
+fn lone_slash_opens_the_palette() {
+    for input in ["/", "  /  ", "//"] {
+        let mut app = app_with_input(input);
+
+        assert_eq!(app.submit_input(), None, "{:?}", input);
+        assert_eq!(app.mode, UIMode::CommandPalette, "{:?}", input);
+        assert!(app.input_buffer.is_empty());
+    }
+}
+
+#[test]
+fn slash_without_a_command_name_is_kept_with_a_hint() {
+    let mut app = app_with_input("/ help");
+
+    assert_eq!(app.submit_input(), None);
+    assert_eq!(app.input_buffer, "/ help", "left in the box to fix");
+    assert_eq!(app.error_message.as_deref(), Some("No command name after '/' (did you mean /help?)"));
+}
+
+#[test]
+fn edits_keep_leading_slashes() {
+    let mut app = app_with_input("/");
+    app.editing_message_id = Some(1);
+
+    assert_eq!(app.submit_input().as_deref(), Some("/"));
+    assert_eq!(app.mode, UIMode::Chat);
+}
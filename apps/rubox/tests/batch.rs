@@ -0,0 +1,63 @@
+use rubox::batch::{batch_report, matrix_json, matrix_table, split_prompts, BatchResult};
+use rubox::Usage;
+use std::time::Duration;
+
+#[test]
+fn prompts_are_lines_unless_the_file_uses_separators() {
+    assert_eq!(split_prompts("What is Rust?\n\n  Explain borrowing  \n"), vec!["What is Rust?", "Explain borrowing"]);
+
+    let blocks = "Review this:\n\nfn main() {}\n---\nSecond prompt\n  ---  \n\n---\n";
+    assert_eq!(split_prompts(blocks), vec!["Review this:\n\nfn main() {}", "Second prompt"]);
+    assert!(split_prompts("\n---\n").is_empty());
+}
+
+#[test]
+fn report_lists_every_prompt_and_aggregates_the_answered_ones() {
+    let usage = Usage { prompt_tokens: 10, completion_tokens: 100, total_tokens: 110 };
+    let results = vec![
+        BatchResult {
+            prompt: "First\n\nline".to_string(),
+            reply: Ok(("Answer one".to_string(), Some(usage))),
+            elapsed: Duration::from_secs(2),
+        },
+        BatchResult {
+            prompt: "Second".to_string(),
+            reply: Err("connection refused".to_string()),
+            elapsed: Duration::from_secs(1),
+        },
+    ];
+
+    let report = batch_report("Qwen", &results, Duration::from_secs(3));
+    assert!(report.starts_with("# Batch: Qwen\n"));
+    assert!(report.contains("## Prompt 1\n\n> First\n>\n> line\n\nAnswer one\n\n_2.00s, 100 tok, 50.0 tok/s_\n"), "{}", report);
+    assert!(report.contains("## Prompt 2\n\n> Second\n\n_Error: connection refused_\n"), "{}", report);
+    assert!(report.contains("- 2 prompt(s): 1 answered, 1 failed\n"));
+    assert!(report.contains("- Total time: 3.00s\n"));
+    assert!(report.contains("- Completion tokens: 100 (50.0 tok/s)\n"));
+}
+
+#[test]
+fn matrix_grid_has_a_row_per_prompt_and_a_column_per_model() {
+    let usage = Usage { prompt_tokens: 5, completion_tokens: 40, total_tokens: 45 };
+    let prompts = vec!["Say | hi".to_string(), "Count".to_string()];
+    let ok = |secs| BatchResult {
+        prompt: String::new(),
+        reply: Ok(("reply".to_string(), Some(usage.clone()))),
+        elapsed: Duration::from_secs(secs),
+    };
+    let failed = BatchResult { prompt: String::new(), reply: Err("timed out".to_string()), elapsed: Duration::ZERO };
+    let runs = vec![("A".to_string(), vec![ok(1), ok(2)]), ("B".to_string(), vec![ok(4), failed])];
+
+    let table = matrix_table(&prompts, &runs);
+    let lines: Vec<&str> = table.lines().collect();
+    assert_eq!(lines[0], "| # | Prompt | A | B |");
+    assert_eq!(lines[2], "| 1 | Say \\| hi | 1.00s, 40 tok, 40.0 tok/s | 4.00s, 40 tok, 10.0 tok/s |");
+    assert_eq!(lines[3], "| 2 | Count | 2.00s, 40 tok, 20.0 tok/s | ✗ timed out |");
+    assert_eq!(lines[4], "| | **Total** | 2/2 answered, 3.00s, 80 tok, 26.7 tok/s | 1/2 answered, 4.00s, 40 tok, 10.0 tok/s |");
+
+    let json = matrix_json(&prompts, &runs, "t");
+    assert_eq!(json["models"][1]["model"], "B");
+    assert_eq!(json["models"][1]["results"][1]["error"], "timed out");
+    assert_eq!(json["models"][0]["results"][1]["completion_tokens"], 40);
+    assert_eq!(json["models"][0]["results"][0]["response"], "reply");
+}
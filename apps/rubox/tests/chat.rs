@@ -0,0 +1,67 @@
+// Chat loop actions that don't need a terminal
+
+use rubox::chat::{switch_model, switch_with_context};
+use rubox::tui::App;
+use rubox::{LlmClient, RuboxConfig, ServerManager};
+
+fn loading_app(config: &RuboxConfig) -> App {
+    let mut app = App::new("qwen3-vl".to_string(), 0.7, config.models.registry.clone(), LlmClient::new(config));
+    app.session.add_message("user".to_string(), "Hi".to_string(), None);
+    // A reply that never finishes, as if the server were still generating
+    app.start_generation(0.7);
+    app.generation_task = Some(tokio::spawn(std::future::pending()));
+    app.prefill = Some("Partial".to_string());
+    app.on_stream_delta("Part");
+    app
+}
+
+#[tokio::test]
+async fn switching_models_while_loading_drops_the_reply_first() {
+    let config = RuboxConfig::default();
+    let mut app = loading_app(&config);
+    app.server_models = vec!["remote-model".to_string()];
+    let mut server = ServerManager::new();
+
+    switch_model(&mut app, &mut server, &config, "remote-model".to_string(), Some("New session".to_string())).await;
+
+    assert_eq!(app.current_model, "remote-model");
+    assert_eq!(app.status_message.as_deref(), Some("New session"), "the command's confirmation shows once switched");
+    assert!(app.generation_task.is_none());
+    assert!(!app.is_loading);
+    assert_eq!(app.prefill, None);
+    assert_eq!(app.streaming_tokens, 0);
+    assert_eq!(app.session.messages.len(), 1, "no partial answer is kept");
+}
+
+#[tokio::test]
+async fn a_failed_switch_while_loading_leaves_a_clean_state() {
+    let mut config = RuboxConfig::default();
+    // rubox can't restart a server it doesn't manage, so the switch fails
+    config.llm.manage_server = false;
+    let mut app = loading_app(&config);
+    let mut server = ServerManager::new();
+
+    switch_model(&mut app, &mut server, &config, "gemma".to_string(), None).await;
+
+    assert_eq!(app.current_model, "qwen3-vl");
+    assert!(app.generation_task.is_none());
+    assert!(!app.is_loading);
+    assert!(app.error_message.as_deref().is_some_and(|e| e.starts_with("Failed to switch model")));
+    assert_eq!(app.session.messages.len(), 1);
+}
+
+#[tokio::test]
+async fn a_failed_switch_keeps_the_previous_context_size() {
+    let mut config = RuboxConfig::default();
+    config.llm.manage_server = false;
+    let mut app = loading_app(&config);
+    let mut server = ServerManager::new();
+    server.context_override = Some(4096);
+
+    assert!(!switch_with_context(&mut app, &mut server, &config, "gemma".to_string(), None, None).await);
+    assert_eq!(server.context_override, Some(4096));
+
+    app.server_models = vec!["remote-model".to_string()];
+    assert!(switch_with_context(&mut app, &mut server, &config, "remote-model".to_string(), Some(2048), None).await);
+    assert_eq!(server.context_override, Some(2048));
+}
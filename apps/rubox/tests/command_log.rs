@@ -0,0 +1,41 @@
+use rubox::command_log::CommandLog;
+use std::fs;
+
+fn scratch_dir(name: &str) -> String {
+    let dir = std::env::temp_dir().join(format!("rubox-command-log-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    dir.to_string_lossy().into_owned()
+}
+
+#[test]
+fn recent_returns_latest_entries_in_order() {
+    let dir = scratch_dir("recent");
+    let log = CommandLog::new(&dir, 256);
+    for command in ["/info", "/temp 0.2", "/history"] {
+        log.append("20250101_120000", command).unwrap();
+    }
+
+    let entries = log.recent(2);
+
+    let commands: Vec<&str> = entries.iter().map(|e| e.command.as_str()).collect();
+    assert_eq!(commands, vec!["/temp 0.2", "/history"]);
+    assert_eq!(entries[0].session_id, "20250101_120000");
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn append_rotates_past_size_limit() {
+    let dir = scratch_dir("rotate");
+    let log = CommandLog::new(&dir, 1);
+    let long_command = format!("/prompt {}", "x".repeat(600));
+    for _ in 0..3 {
+        log.append("s1", &long_command).unwrap();
+    }
+    log.append("s1", "/info").unwrap();
+
+    let current = fs::read_to_string(format!("{}/command_log.tsv", dir)).unwrap();
+    assert!(current.len() < 1024 + long_command.len());
+    assert!(fs::metadata(format!("{}/command_log.tsv.1", dir)).is_ok());
+    assert_eq!(log.recent(1)[0].command, "/info");
+    let _ = fs::remove_dir_all(&dir);
+}
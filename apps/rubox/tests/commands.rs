@@ -0,0 +1,496 @@
+// Slash commands run against an App's state, as the chat loop does
+
+use rubox::commands::{ChatState, CommandResult, Tone};
+use rubox::tui::App;
+use rubox::{CommandRegistry, LlmClient, OutputFormat, RuboxConfig, ServerManager};
+use std::fs;
+use std::path::PathBuf;
+
+fn app(config: &RuboxConfig) -> App {
+    App::new("qwen3-vl".to_string(), 0.7, config.models.registry.clone(), LlmClient::new(config))
+}
+
+// An app and a config whose directories all live in one scratch directory,
+// removed again when the test ends
+struct Fixture {
+    app: App,
+    config: RuboxConfig,
+    server: ServerManager,
+    dir: PathBuf,
+}
+
+impl Fixture {
+    fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("rubox-commands-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let mut config = RuboxConfig::default();
+        let sub = |name: &str| dir.join(name).to_string_lossy().into_owned();
+        config.directories.output = sub("output");
+        config.directories.chat = sub("chat");
+        config.directories.sessions = sub("sessions");
+        config.directories.static_prompts = sub("static_prompts");
+        config.directories.saved_responses = sub("saved");
+        Fixture { app: app(&config), config, server: ServerManager::new(), dir }
+    }
+
+    fn run(&mut self, command: &str) -> CommandResult {
+        let app = &mut self.app;
+        let mut state = ChatState {
+            session: &mut app.session,
+            current_model: &mut app.current_model,
+            verbose: &mut app.verbose,
+            temperature: &mut app.temperature,
+            undo_snapshot: &mut app.undo_snapshot,
+            expanded_thinking: &mut app.expanded_thinking,
+            output_format: &mut app.output_format,
+            candidate_count: &mut app.candidate_count,
+            logit_bias: &mut app.logit_bias,
+            think: &mut app.think,
+            top_p: &mut app.top_p,
+            max_tokens: &mut app.max_tokens,
+            model_registry: &mut app.model_registry,
+            server: &mut self.server,
+            client: &app.client,
+            config: &self.config,
+            is_loading: false,
+            focused: None,
+        };
+        CommandRegistry::new().handle(command, &mut state).unwrap()
+    }
+}
+
+impl Drop for Fixture {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+#[test]
+fn set_and_settings_list_the_same_rows() {
+    let mut f = Fixture::new("settings");
+
+    assert!(matches!(f.run("/set top_p 0.9"), CommandResult::Status(msg) if msg == "top_p set to 0.9"));
+    assert_eq!(f.app.top_p, Some(0.9));
+    assert_eq!(f.app.session.metadata.top_p, Some(0.9), "kept with the session");
+
+    let CommandResult::Settings(rows) = f.run("/settings") else {
+        panic!("/settings should return the panel rows");
+    };
+    let CommandResult::Settings(listed) = f.run("/set") else {
+        panic!("/set alone should open the same panel");
+    };
+    assert_eq!(listed.len(), rows.len());
+    assert!(rows.iter().any(|row| row.name == "top_p" && row.value == "0.9"));
+}
+
+#[test]
+fn load_restores_the_sampling_settings_of_a_session() {
+    let mut f = Fixture::new("load");
+    f.app.session.add_message("user".to_string(), "Hi".to_string(), None);
+    for command in ["/set temp 0.2", "/set top_p 0.8", "/set max_tokens 256", "/set n 2", "/set format json", "/bias 42 -5"] {
+        f.run(command);
+    }
+    f.app.session.save(&f.config.directories.sessions).unwrap();
+    let saved_id = f.app.session.metadata.id.clone();
+
+    f.app = app(&f.config);
+    let loaded = format!("Loaded {} (1 messages): ", saved_id);
+    assert!(matches!(f.run("/load 1"), CommandResult::Status(s) if s.starts_with(&loaded)));
+
+    assert_eq!(f.app.session.metadata.id, saved_id);
+    assert_eq!(f.app.temperature, 0.2);
+    assert_eq!(f.app.top_p, Some(0.8));
+    assert_eq!(f.app.max_tokens, 256);
+    assert_eq!(f.app.candidate_count, 2);
+    assert_eq!(f.app.output_format, OutputFormat::Json);
+    assert_eq!(f.app.logit_bias.get(&42), Some(&-5.0));
+}
+
+#[test]
+fn load_asks_for_the_context_size_of_the_session() {
+    let mut f = Fixture::new("load-context");
+    let save = |id: &str, context_window| {
+        let mut app = app(&f.config);
+        app.session.add_message("user".to_string(), "Hi".to_string(), None);
+        app.session.metadata.id = id.to_string();
+        app.session.metadata.context_window = context_window;
+        app.session.save(&f.config.directories.sessions).unwrap();
+    };
+    save("sized", Some(2048));
+    save("unsized", None);
+
+    // The override is the chat loop's to apply, once the restart succeeds
+    assert!(matches!(f.run("/load sized"), CommandResult::SwitchSession(m, Some(2048), _) if m == "qwen3-vl"));
+    assert_eq!(f.server.context_override, None);
+
+    // A session without a size drops an earlier /set ctx
+    f.server.context_override = Some(4096);
+    assert!(matches!(f.run("/load unsized"), CommandResult::SwitchSession(_, None, _)));
+    f.server.context_override = None;
+    assert!(matches!(f.run("/load unsized"), CommandResult::Status(_)), "nothing to restart");
+}
+
+#[test]
+fn new_saves_the_chat_and_starts_an_empty_session() {
+    let mut f = Fixture::new("new");
+    f.app.session.add_message("user".to_string(), "Hi".to_string(), None);
+    f.run("/set top_p 0.5");
+    let old_id = f.app.session.metadata.id.clone();
+
+    let saved = format!("Saved {}; new session ", old_id);
+    assert!(matches!(f.run("/new"), CommandResult::Status(s) if s.starts_with(&saved)));
+
+    assert!(f.app.session.messages.is_empty());
+    assert_ne!(f.app.session.metadata.id, old_id, "ids stay unique within the same second");
+    assert_eq!(f.app.session.metadata.top_p, Some(0.5), "settings carry over");
+    assert!(PathBuf::from(&f.config.directories.sessions).join(&old_id).join("messages.json").is_file());
+
+    let model = f.app.model_registry.keys().find(|m| **m != f.app.current_model).cloned().unwrap();
+    assert!(matches!(f.run(&format!("/new {}", model)), CommandResult::SwitchModel(m, Some(s)) if m == model && s.starts_with("New session ")));
+}
+
+#[test]
+fn garbage_arguments_are_reported_instead_of_failing() {
+    let mut f = Fixture::new("garbage");
+    f.app.session.add_message("user".to_string(), "Hi".to_string(), None);
+
+    for command in ["/delete x", "/save x", "/set temp abc", "/prompt x"] {
+        assert!(
+            matches!(f.run(command), CommandResult::Error(_)),
+            "{} should be a CommandResult::Error",
+            command
+        );
+    }
+    assert_eq!(f.app.session.messages.len(), 1);
+}
+
+#[test]
+fn non_numeric_set_temp_keeps_the_temperature() {
+    let mut f = Fixture::new("set-temp");
+    let before = f.app.temperature;
+
+    match f.run("/set temp abc") {
+        CommandResult::Error(msg) => assert_eq!(msg, "Invalid argument: temperature value 'abc'"),
+        _ => panic!("expected an error for a non-numeric temperature"),
+    }
+    assert_eq!(f.app.temperature, before);
+
+    // The chat keeps accepting commands afterwards
+    assert!(matches!(f.run("/set temp 0.3"), CommandResult::Status(_)));
+    assert_eq!(f.app.temperature, 0.3);
+}
+
+#[test]
+fn set_think_reports_in_the_status_bar() {
+    let mut f = Fixture::new("set-think");
+
+    assert!(matches!(f.run("/set think low"), CommandResult::Status(s) if s == "Reasoning effort set to low"));
+    assert!(matches!(f.run("/set think default"), CommandResult::Status(s) if s == "Reasoning left to the model"));
+}
+
+#[test]
+fn oversized_context_warns_in_the_status_bar() {
+    let mut f = Fixture::new("set-context");
+
+    assert!(matches!(
+        f.run("/set context 1024"),
+        CommandResult::SetContext(1024, s) if s == "Context size set to 1024"
+    ));
+    assert_eq!(f.app.session.metadata.context_window, None, "kept only once the server restarts with it");
+    assert!(matches!(
+        f.run("/set context 1000000"),
+        CommandResult::SetContext(1_000_000, s) if s.starts_with("Context size set to 1000000 · ⚠ far above")
+    ));
+}
+
+#[test]
+fn undo_reports_in_the_status_bar() {
+    let mut f = Fixture::new("undo");
+    assert!(matches!(f.run("/undo"), CommandResult::Error(msg) if msg == "Nothing to undo"));
+
+    f.app.session.add_message("user".to_string(), "Hi".to_string(), None);
+    f.run("/delete all");
+    assert!(matches!(f.run("/undo"), CommandResult::Status(msg) if msg == "Restored 1 message(s)"));
+    assert_eq!(f.app.session.messages.len(), 1);
+}
+
+#[test]
+fn delete_ranges_only_touch_existing_messages() {
+    let mut f = Fixture::new("delete");
+    for text in ["one", "two", "three", "four"] {
+        f.app.session.add_message("user".to_string(), text.to_string(), None);
+    }
+    let ids: Vec<usize> = f.app.session.messages.iter().map(|m| m.id).collect();
+
+    // A huge range is intersected with the session, not expanded
+    let huge = format!("/delete {}-{}", ids[2], usize::MAX);
+    assert!(matches!(f.run(&huge), CommandResult::Status(msg) if msg == "2 message(s) deleted"));
+    assert_eq!(f.app.session.messages.iter().map(|m| m.id).collect::<Vec<_>>(), ids[..2]);
+
+    assert!(matches!(f.run("/delete 5-3"), CommandResult::Error(_)));
+    assert_eq!(f.app.session.messages.len(), 2);
+
+    // Deleting nothing is an error and keeps the undo point of the real delete
+    assert!(matches!(f.run("/delete 99"), CommandResult::Error(msg) if msg.starts_with("Not found")));
+    f.run("/undo");
+    assert_eq!(f.app.session.messages.len(), 4);
+}
+
+#[test]
+fn models_and_connect_report_without_printing() {
+    let mut f = Fixture::new("models");
+
+    assert!(matches!(f.run("/models refresh"), CommandResult::Status(msg) if msg.starts_with("Rescanned models/")));
+    assert!(matches!(f.run("/models"), CommandResult::Error(_)));
+    assert!(matches!(
+        f.run("/connect http://192.168.1.20:8080"),
+        CommandResult::Connect(url) if url == "http://192.168.1.20:8080/v1"
+    ));
+    assert!(matches!(f.run("/connect ftp://host"), CommandResult::Error(_)));
+}
+
+#[test]
+fn bias_confirms_in_the_status_bar_and_lists_in_the_output_modal() {
+    let mut f = Fixture::new("bias");
+
+    assert!(matches!(f.run("/bias"), CommandResult::Status(msg) if msg == "No token biases set"));
+    assert!(matches!(f.run("/bias 42 -5"), CommandResult::Status(msg) if msg == "Token 42 biased by -5.0"));
+    f.run("/bias 7 2");
+    let CommandResult::Output(output) = f.run("/bias") else {
+        panic!("expected the bias listing");
+    };
+    assert_eq!(output.text(), "       7  +2.0\n      42  -5.0");
+    assert!(matches!(f.run("/bias 1 2 3"), CommandResult::Error(_)));
+    assert!(matches!(f.run("/bias clear"), CommandResult::Status(_)));
+    assert!(f.app.logit_bias.is_empty());
+}
+
+#[test]
+fn reload_reports_changes_in_the_output_modal() {
+    let mut f = Fixture::new("reload");
+    fs::create_dir_all(&f.dir).unwrap();
+    let path = f.dir.join("rubox_config.json");
+    let mut edited = RuboxConfig::default();
+    fs::write(&path, serde_json::to_string(&edited).unwrap()).unwrap();
+    f.config = RuboxConfig::load(Some(&path)).unwrap();
+
+    let CommandResult::Reload(_, report) = f.run("/reload") else {
+        panic!("expected a reload");
+    };
+    assert_eq!(report.text(), "Nothing changed");
+
+    edited.theme.user = "#112233".to_string();
+    fs::write(&path, serde_json::to_string(&edited).unwrap()).unwrap();
+    let CommandResult::Reload(_, report) = f.run("/reload") else {
+        panic!("expected a reload");
+    };
+    assert_eq!(report.title, "Config Reloaded");
+    assert_eq!(report.text(), "Applied: theme.user");
+}
+
+#[test]
+fn cache_reports_in_the_status_bar() {
+    let mut f = Fixture::new("cache");
+
+    assert!(matches!(
+        f.run("/cache"),
+        CommandResult::Status(msg) if msg == "Response cache: off (0 entries, temperature 0 only)"
+    ));
+    assert!(matches!(f.run("/cache clear"), CommandResult::Status(msg) if msg == "Cleared 0 cached response(s)"));
+    assert!(matches!(f.run("/cache purge"), CommandResult::Error(_)));
+}
+
+#[test]
+fn copy_reports_in_the_status_bar() {
+    let mut f = Fixture::new("copy");
+    f.app.session.add_message("user".to_string(), "Hi".to_string(), None);
+    f.app.session.add_message("assistant".to_string(), "Use:\n\n```sh\nls\n```\n\n```sh\npwd\n```".to_string(), None);
+
+    assert!(matches!(f.run("/copy 1"), CommandResult::Status(msg) if msg == "Copied message 1 (2 chars)"));
+    assert!(matches!(
+        f.run("/copy code"),
+        CommandResult::Status(msg) if msg == "Copied code from message 2 (block 2 of 2; /copy code 2 <n> for another)"
+    ));
+    assert!(matches!(f.run("/copy code 1"), CommandResult::Error(msg) if msg.contains("code block in message 1")));
+}
+
+#[test]
+fn save_meta_records_where_a_reply_came_from() {
+    let mut f = Fixture::new("save-meta");
+    f.app.session.add_message("user".to_string(), "Hi".to_string(), None);
+    f.app.session.add_message("assistant".to_string(), "Hello!".to_string(), Some(3));
+    let session = f.app.session.metadata.id.clone();
+    let dir = PathBuf::from(&f.config.directories.saved_responses);
+    let files = |suffix: &str| -> Vec<PathBuf> {
+        let mut files: Vec<_> = fs::read_dir(&dir).unwrap().map(|e| e.unwrap().path()).collect();
+        files.retain(|p| p.to_string_lossy().ends_with(suffix));
+        files
+    };
+
+    f.run("/save 2 --meta");
+    let text = fs::read_to_string(&files(".txt")[0]).unwrap();
+    assert!(text.starts_with(&format!("---\nsession: \"{}\"\nmessage: 2\nrole: assistant\nmodel: \"qwen3-vl\"\ntemperature: 0.7\n", session)), "{}", text);
+    assert!(text.ends_with("tokens: 3\n---\n\nHello!"), "{}", text);
+    fs::remove_dir_all(&dir).unwrap();
+
+    let CommandResult::Status(status) = f.run("/save --meta json 2") else {
+        panic!("expected the saved path in the status bar");
+    };
+    assert!(status.starts_with("Saved to: ") && status.ends_with(".meta.json"), "{}", status);
+    assert_eq!(fs::read_to_string(&files(".txt")[0]).unwrap(), "Hello!", "the reply file stays plain");
+    let meta: serde_json::Value = serde_json::from_str(&fs::read_to_string(&files(".meta.json")[0]).unwrap()).unwrap();
+    assert_eq!(meta["session"], session.as_str());
+    assert_eq!(meta["model"], "qwen3-vl");
+    assert_eq!(meta["tokens"], 3);
+    fs::remove_dir_all(&dir).unwrap();
+
+    // Without the flag the file holds only the reply
+    f.run("/save 2");
+    assert_eq!(fs::read_to_string(&files(".txt")[0]).unwrap(), "Hello!");
+
+    // The other save targets report their paths the same way
+    for (command, prefix) in [("/save all", "Saved 1 message(s) to: "), ("/export html", "Exported session to: "), ("/export jsonl", "Exported 1 record(s) to: ")] {
+        assert!(matches!(f.run(command), CommandResult::Status(msg) if msg.starts_with(prefix)), "{}", command);
+    }
+}
+
+#[test]
+fn diff_returns_changed_lines_for_the_output_modal() {
+    let mut f = Fixture::new("diff");
+    f.app.session.add_message("assistant".to_string(), "same\nold line".to_string(), None);
+    f.app.session.add_message("assistant".to_string(), "same\nnew line".to_string(), None);
+
+    let CommandResult::Output(output) = f.run("/diff 1 2") else {
+        panic!("/diff should return its lines for the modal");
+    };
+    assert_eq!(output.title, "Diff #1 → #2");
+    assert_eq!(output.lines, vec![vec![(Tone::Removed, "- old line".to_string())], vec![(Tone::Added, "+ new line".to_string())]]);
+}
+
+#[test]
+fn search_results_highlight_the_match() {
+    let mut f = Fixture::new("search");
+    f.app.session.add_message("user".to_string(), "How do lifetimes work?".to_string(), None);
+    f.app.session.add_message("assistant".to_string(), "Borrowing rules apply.".to_string(), None);
+
+    let CommandResult::Output(output) = f.run("/search LIFETIMES") else {
+        panic!("/search should return its results for the modal");
+    };
+    assert_eq!(output.text(), "[1] user: How do lifetimes work?");
+    assert!(output.lines[0].contains(&(Tone::Highlight, "lifetimes".to_string())));
+
+    let CommandResult::Output(output) = f.run("/search --assistant lifetimes") else {
+        panic!("/search should return its results for the modal");
+    };
+    assert_eq!(output.text(), "No matches in 2 message(s)");
+}
+
+#[test]
+fn command_history_lists_logged_commands_in_the_output_modal() {
+    let mut f = Fixture::new("history");
+    f.config.session.command_log = true;
+
+    let CommandResult::Output(output) = f.run("/history commands") else {
+        panic!("/history commands should return its entries for the modal");
+    };
+    assert_eq!(output.text(), "No commands logged yet");
+
+    rubox::command_log::CommandLog::new(&f.config.directories.chat, 256).append("session_1", "/info").unwrap();
+    let CommandResult::Output(output) = f.run("/history commands") else {
+        panic!("/history commands should return its entries for the modal");
+    };
+    assert_eq!(output.title, "Command History");
+    assert!(output.text().ends_with("session_1 /info"), "{}", output.text());
+}
+
+#[tokio::test]
+async fn info_shows_the_model_and_sampling_in_the_output_modal() {
+    let mut f = Fixture::new("info");
+    // Nothing listens here, so the server's props come back unavailable
+    f.config.llm.api_url = "http://127.0.0.1:9/v1".to_string();
+    f.app.client = LlmClient::new(&f.config);
+    f.run("/set top_p 0.9");
+
+    let CommandResult::Info(mut output) = f.run("/info") else {
+        panic!("/info should return its rows for the modal");
+    };
+    let text = output.text();
+    assert!(text.starts_with("model           qwen3-vl"), "{}", text);
+    assert!(text.contains("server model    checking..."), "the server is asked off the event loop: {}", text);
+    assert!(text.contains("custom          top_p 0.9"), "{}", text);
+
+    let model = rubox::commands::server_model(&f.app.client).await;
+    rubox::commands::fill_server_model(&mut output, &model);
+    assert!(output.text().contains("server model    unavailable"), "{}", output.text());
+}
+
+#[test]
+fn benchmark_runs_in_the_background() {
+    let mut f = Fixture::new("benchmark");
+
+    assert!(matches!(f.run("/benchmark 2"), CommandResult::Benchmark(2)));
+    assert!(matches!(f.run("/benchmark 0"), CommandResult::Error(_)));
+}
+
+#[test]
+fn prompt_show_previews_and_prompt_n_sends() {
+    let mut f = Fixture::new("prompts");
+    let prompts = PathBuf::from(&f.config.directories.static_prompts);
+    fs::create_dir_all(&prompts).unwrap();
+    fs::write(prompts.join("review.txt"), "Review this code.\nBe brief.").unwrap();
+
+    let CommandResult::Output(output) = f.run("/prompt show 1") else {
+        panic!("/prompt show should open the output modal");
+    };
+    assert_eq!(output.title, "Prompt: review");
+    assert_eq!(output.text(), "Review this code.\nBe brief.");
+    assert!(f.app.session.messages.is_empty(), "a preview adds nothing to the session");
+
+    let CommandResult::Output(list) = f.run("/prompt list") else {
+        panic!("/prompt list should open the output modal");
+    };
+    assert!(list.text().starts_with("[1] review"));
+    assert!(matches!(f.run("/prompt 1"), CommandResult::Status(s) if s == "Loaded prompt: review"));
+    assert_eq!(f.app.session.messages[0].content, "Review this code.\nBe brief.");
+}
+
+#[test]
+fn rate_reports_in_the_status_bar() {
+    let mut f = Fixture::new("rate");
+    f.app.session.add_message("user".to_string(), "Hi".to_string(), None);
+    f.app.session.add_message("assistant".to_string(), "Hello".to_string(), None);
+
+    assert!(matches!(f.run("/rate 2 good"), CommandResult::Status(s) if s == "Message 2 rated good"));
+    assert_eq!(f.app.session.messages[1].rating, Some(1));
+}
+
+#[test]
+fn rating_keys_only_act_on_the_focused_answer() {
+    let mut f = Fixture::new("rating-keys");
+    let app = &mut f.app;
+    app.session.add_message("user".to_string(), "Hi".to_string(), None);
+    app.session.add_message("assistant".to_string(), "Hello".to_string(), None);
+
+    app.rate_focused(1);
+    assert_eq!(app.session.messages[1].rating, None, "the latest answer isn't rated without focus");
+
+    app.focused_message_id = Some(app.session.messages[1].id);
+    app.rate_focused(1);
+    assert_eq!(app.session.messages[1].rating, Some(1));
+    app.rate_focused(1);
+    assert_eq!(app.session.messages[1].rating, None, "the same key clears it");
+}
+
+#[test]
+fn rename_reports_in_the_status_bar() {
+    let mut f = Fixture::new("rename");
+
+    assert!(matches!(
+        f.run("/rename"),
+        CommandResult::Error(msg) if msg == "Invalid argument: usage: /rename <label>"
+    ));
+    let CommandResult::Status(status) = f.run("/rename notes") else {
+        panic!("expected a status after /rename");
+    };
+    assert_eq!(status, format!("Session renamed to: {}", f.app.session.metadata.id));
+}
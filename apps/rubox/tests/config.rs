@@ -0,0 +1,117 @@
+use rubox::config::{config_changes, render_filename, validate_filename_template, FilenamesConfig, FILENAME_TOKENS};
+use rubox::RuboxConfig;
+use std::fs;
+
+#[test]
+fn default_filename_templates_reproduce_historical_names() {
+    let filenames = FilenamesConfig::default();
+    filenames.validate().unwrap();
+
+    let results = render_filename(&filenames.results, &[("timestamp", "01_01_2025_12_00_00")]);
+    let saved = render_filename(&filenames.saved_message, &[("session", "session_01012025_120000"), ("id", "7")]);
+
+    assert_eq!(results, "Results_01_01_2025_12_00_00");
+    assert_eq!(saved, "session_01012025_120000_7");
+}
+
+#[test]
+fn filename_templates_reject_bad_tokens_and_characters() {
+    assert!(validate_filename_template("{session}", &FILENAME_TOKENS, &["session", "id"]).is_err());
+    assert!(validate_filename_template("out/{session}_{id}", &FILENAME_TOKENS, &["session", "id"]).is_err());
+    assert!(validate_filename_template("{session}_{id}_{user}", &FILENAME_TOKENS, &["session", "id"]).is_err());
+    assert!(validate_filename_template("{model}_{timestamp}", &["timestamp"], &["timestamp"]).is_err());
+    assert!(validate_filename_template("{model} - {session}_{id}", &FILENAME_TOKENS, &["session", "id"]).is_ok());
+}
+
+#[test]
+fn rendered_values_cannot_escape_the_directory() {
+    let name = render_filename("{model}_{timestamp}", &[("model", "org/model:q4"), ("timestamp", "t")]);
+
+    assert_eq!(name, "org_model_q4_t");
+}
+
+#[test]
+fn empty_registry_without_gguf_files_is_reported_not_indexed() {
+    let dir = std::env::temp_dir().join(format!("rubox_test_models_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let mut config = RuboxConfig::default();
+    config.models.registry.clear();
+
+    let err = config.require_models(&dir).unwrap_err().to_string();
+    assert!(err.contains("contains no .gguf files"), "{}", err);
+    assert!(err.contains("models.registry"), "{}", err);
+    assert!(config.require_models(&dir.join("missing")).unwrap_err().to_string().contains("does not exist"));
+
+    fs::write(dir.join("tiny.gguf"), b"").unwrap();
+    assert_eq!(config.require_models(&dir).unwrap(), vec!["tiny"]);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn display_name_falls_back_to_the_registry_key() {
+    let mut config = RuboxConfig::default();
+    let key = config.model_profiles.keys().next().unwrap().clone();
+    assert_eq!(config.display_name(&key), key);
+    assert_eq!(config.display_name("unknown-model"), "unknown-model");
+
+    config.model_profiles.get_mut(&key).unwrap().display_name = Some("Friendly Model".to_string());
+    assert_eq!(config.display_name(&key), "Friendly Model");
+}
+
+#[test]
+fn reload_reads_the_file_again_and_rejects_invalid_values() {
+    let dir = std::env::temp_dir().join(format!("rubox_test_reload_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("rubox_config.json");
+    let mut edited = RuboxConfig::default();
+    fs::write(&path, serde_json::to_string(&edited).unwrap()).unwrap();
+    let mut config = RuboxConfig::load(Some(&path)).unwrap();
+    config.param_overrides.gpu_layers = Some(12);
+
+    edited.theme.user = "#112233".to_string();
+    edited.model_profiles.values_mut().for_each(|p| p.gpu_layers = 1);
+    fs::write(&path, serde_json::to_string(&edited).unwrap()).unwrap();
+    let reloaded = config.reload().unwrap();
+    assert_eq!(reloaded.theme.user, "#112233");
+    assert_eq!(reloaded.param_overrides.gpu_layers, Some(12), "command-line overrides survive");
+
+    let (applied, restart) = config_changes(&config, &reloaded);
+    assert_eq!(applied, vec!["theme.user"]);
+    assert_eq!(restart, vec!["model_profiles"]);
+
+    edited.temperature.default = 3.0;
+    fs::write(&path, serde_json::to_string(&edited).unwrap()).unwrap();
+    assert!(config.reload().unwrap_err().to_string().contains("temperature"));
+    edited.temperature.default = 0.7;
+    edited.ui.chat_percent = 95;
+    fs::write(&path, serde_json::to_string(&edited).unwrap()).unwrap();
+    assert!(config.reload().unwrap_err().to_string().contains("ui.chat_percent"));
+
+    // A broken file is an error rather than the built-in defaults, at startup too
+    fs::write(&path, "{ not json").unwrap();
+    assert!(config.reload().is_err());
+    let err = RuboxConfig::load(Some(&path)).unwrap_err().to_string();
+    assert!(err.starts_with("Invalid config "), "{}", err);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn relative_model_paths_resolve_against_the_config_directory() {
+    let dir = std::env::temp_dir().join(format!("rubox_test_paths_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("rubox_config.json");
+    let mut written = RuboxConfig::default();
+    written.models.models_root = None;
+    written.models.registry.insert("local".to_string(), "models/local.gguf".to_string());
+    fs::write(&path, serde_json::to_string(&written).unwrap()).unwrap();
+
+    // Tests run from the crate directory, not the config's
+    assert_ne!(std::env::current_dir().unwrap(), dir);
+    let config = RuboxConfig::load(Some(&path)).unwrap();
+
+    let resolved = std::path::Path::new(&config.models.registry["local"]);
+    assert_eq!(resolved, dir.join("models").join("local.gguf"));
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,45 @@
+use rubox::export::{session_to_jsonl, JsonlLayout};
+use rubox::session::Session;
+
+fn conversation() -> Session {
+    let mut session = Session::new("qwen3-vl".to_string(), 0.7);
+    session.add_message("system".to_string(), "Answer briefly.".to_string(), None);
+    session.add_message("user".to_string(), "2 + 2?".to_string(), None);
+    session.add_message("assistant".to_string(), "<think>Add.</think>\n4".to_string(), None);
+    session.add_message("user".to_string(), "3 + 3?".to_string(), None);
+    session.add_message("assistant".to_string(), "7".to_string(), None);
+    session.add_message("user".to_string(), "Unanswered".to_string(), None);
+    session
+}
+
+fn records(jsonl: &str) -> Vec<serde_json::Value> {
+    jsonl.lines().map(|line| serde_json::from_str(line).unwrap()).collect()
+}
+
+#[test]
+fn jsonl_pairs_lead_with_system_prompt_and_skip_incomplete_turns() {
+    let jsonl = session_to_jsonl(&conversation(), JsonlLayout::Pairs, true, |_| true);
+
+    let records = records(&jsonl);
+    assert_eq!(records.len(), 2);
+    let first = records[0]["messages"].as_array().unwrap();
+    let roles: Vec<&str> = first.iter().map(|m| m["role"].as_str().unwrap()).collect();
+    assert_eq!(roles, ["system", "user", "assistant"]);
+    assert_eq!(first[2]["content"], "4");
+}
+
+#[test]
+fn jsonl_exclusion_drops_the_whole_exchange() {
+    // Message 5 is the wrong answer; its question goes with it
+    let jsonl = session_to_jsonl(&conversation(), JsonlLayout::Conversation, false, |m| m.id != 5);
+
+    let records = records(&jsonl);
+    assert_eq!(records.len(), 1);
+    let contents: Vec<&str> = records[0]["messages"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|m| m["content"].as_str().unwrap())
+        .collect();
+    assert_eq!(contents, ["Answer briefly.", "2 + 2?", "<think>Add.</think>\n4"]);
+}
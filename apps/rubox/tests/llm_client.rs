@@ -0,0 +1,566 @@
+// LlmClient against a canned HTTP responder, so the request/response handling
+// can be checked without a GPU or a running llama-server.
+
+use rubox::llm_client::{ChatMessage, LlmClient, ThinkLevel};
+use rubox::response_cache::ResponseCache;
+use rubox::{Rubox, RuboxConfig};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+struct MockResponse {
+    status: u16,
+    content_type: &'static str,
+    body: String,
+    delay: Duration,
+}
+
+impl MockResponse {
+    fn json(status: u16, body: &str) -> Self {
+        MockResponse {
+            status,
+            content_type: "application/json",
+            body: body.to_string(),
+            delay: Duration::ZERO,
+        }
+    }
+}
+
+// Serve `routes` (path -> response) on an ephemeral port; unknown paths get a
+// 404. Returns the API base URL in the form the config expects.
+async fn spawn_mock(routes: Vec<(&'static str, MockResponse)>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let routes = std::sync::Arc::new(routes);
+
+    tokio::spawn(async move {
+        loop {
+            let Ok((mut socket, _)) = listener.accept().await else {
+                break;
+            };
+            let routes = routes.clone();
+            tokio::spawn(async move {
+                let path = read_request_path(&mut socket).await;
+                let not_found = MockResponse::json(404, r#"{"error":"not found"}"#);
+                let response = routes
+                    .iter()
+                    .find(|(route, _)| *route == path)
+                    .map(|(_, response)| response)
+                    .unwrap_or(&not_found);
+
+                tokio::time::sleep(response.delay).await;
+                let reply = format!(
+                    "HTTP/1.1 {} Mock\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    response.status,
+                    response.content_type,
+                    response.body.len(),
+                    response.body
+                );
+                let _ = socket.write_all(reply.as_bytes()).await;
+            });
+        }
+    });
+
+    format!("http://127.0.0.1:{}/v1", port)
+}
+
+// Read headers and body (per Content-Length) and return the request path
+async fn read_request_path(socket: &mut tokio::net::TcpStream) -> String {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 4096];
+    loop {
+        let n = socket.read(&mut chunk).await.unwrap_or(0);
+        if n == 0 {
+            break;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+
+        let text = String::from_utf8_lossy(&buf);
+        if let Some(header_end) = text.find("\r\n\r\n") {
+            let content_length = text[..header_end]
+                .lines()
+                .find_map(|line| {
+                    let (name, value) = line.split_once(':')?;
+                    name.eq_ignore_ascii_case("content-length").then(|| value.trim().parse::<usize>().ok())?
+                })
+                .unwrap_or(0);
+            if buf.len() >= header_end + 4 + content_length {
+                break;
+            }
+        }
+    }
+
+    let text = String::from_utf8_lossy(&buf);
+    text.split_whitespace().nth(1).unwrap_or("/").to_string()
+}
+
+fn client_for(api_url: String, timeout_secs: u64) -> LlmClient {
+    let mut config = RuboxConfig::default();
+    config.llm.api_url = api_url;
+    config.llm.request_timeout_secs = timeout_secs;
+    LlmClient::new(&config)
+}
+
+fn user_message(content: &str) -> Vec<ChatMessage> {
+    vec![ChatMessage {
+        role: "user".to_string(),
+        content: content.to_string(),
+    }]
+}
+
+#[tokio::test]
+async fn completion_returns_content_and_usage() {
+    let api_url = spawn_mock(vec![(
+        "/v1/chat/completions",
+        MockResponse::json(
+            200,
+            r#"{"choices":[{"message":{"role":"assistant","content":"Hello there"}}],
+                "usage":{"prompt_tokens":5,"completion_tokens":2,"total_tokens":7}}"#,
+        ),
+    )])
+    .await;
+    let client = client_for(api_url, 5);
+
+    let (content, usage) = client.chat_completion_with_usage(user_message("hi"), 0.7).await.unwrap();
+
+    assert_eq!(content, "Hello there");
+    let usage = usage.expect("usage should be parsed");
+    assert_eq!(usage.completion_tokens, 2);
+    assert_eq!(usage.total_tokens, 7);
+}
+
+#[tokio::test]
+async fn response_cache_serves_repeated_deterministic_requests() {
+    let reply = |content: &str| {
+        MockResponse::json(
+            200,
+            &format!(r#"{{"choices":[{{"message":{{"role":"assistant","content":"{}"}}}}]}}"#, content),
+        )
+    };
+    let first_url = spawn_mock(vec![("/v1/chat/completions", reply("first"))]).await;
+    let second_url = spawn_mock(vec![("/v1/chat/completions", reply("second"))]).await;
+    let cache_dir = std::env::temp_dir().join(format!("rubox-response-cache-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&cache_dir);
+
+    let mut client = client_for(first_url, 5);
+    client.cache = Some(ResponseCache::new(&cache_dir));
+    client.chat_completion_with_usage(user_message("hi"), 0.0).await.unwrap();
+
+    // Same request against a server that would answer differently
+    let mut client = client_for(second_url, 5);
+    client.cache = Some(ResponseCache::new(&cache_dir));
+    let (cached, _) = client.chat_completion_with_usage(user_message("hi"), 0.0).await.unwrap();
+    let (sampled, _) = client.chat_completion_with_usage(user_message("hi"), 0.7).await.unwrap();
+    let (other, _) = client.chat_completion_with_usage(user_message("hello"), 0.0).await.unwrap();
+
+    assert_eq!(cached, "first");
+    assert_eq!(sampled, "second");
+    assert_eq!(other, "second");
+    assert_eq!(ResponseCache::new(&cache_dir).clear().unwrap(), 2);
+}
+
+#[tokio::test]
+async fn switching_models_misses_the_response_cache() {
+    let reply = |content: &str| {
+        MockResponse::json(
+            200,
+            &format!(r#"{{"choices":[{{"message":{{"role":"assistant","content":"{}"}}}}]}}"#, content),
+        )
+    };
+    let first_url = spawn_mock(vec![("/v1/chat/completions", reply("first"))]).await;
+    let second_url = spawn_mock(vec![("/v1/chat/completions", reply("second"))]).await;
+    let output_dir = std::env::temp_dir().join(format!("rubox-cache-switch-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&output_dir);
+    let rubox_at = |api_url: String| {
+        let mut config = RuboxConfig::default();
+        config.llm.api_url = api_url;
+        config.llm.manage_server = false;
+        config.llm.response_cache = true;
+        config.directories.output = output_dir.to_string_lossy().to_string();
+        let mut rubox = Rubox::new(config);
+        rubox.set_temperature(0.0);
+        rubox
+    };
+
+    rubox_at(first_url).send("hi").await.unwrap();
+
+    // The same conversation on the same model is a hit, on another a miss
+    let mut rubox = rubox_at(second_url.clone());
+    assert_eq!(rubox.send("hi").await.unwrap().content, "first");
+    let mut rubox = rubox_at(second_url);
+    rubox.switch_model("other-model").await.unwrap();
+    assert_eq!(rubox.send("hi").await.unwrap().content, "second");
+
+    std::fs::remove_dir_all(&output_dir).unwrap();
+}
+
+#[tokio::test]
+async fn completion_without_choices_is_an_error() {
+    let api_url = spawn_mock(vec![(
+        "/v1/chat/completions",
+        MockResponse::json(200, r#"{"choices":[],"usage":null}"#),
+    )])
+    .await;
+    let client = client_for(api_url, 5);
+
+    let err = client.chat_completion_with_usage(user_message("hi"), 0.7).await.unwrap_err();
+
+    assert!(err.to_string().contains("no choices"), "unexpected error: {}", err);
+}
+
+#[tokio::test]
+async fn completion_choices_returns_every_candidate() {
+    let api_url = spawn_mock(vec![(
+        "/v1/chat/completions",
+        MockResponse::json(
+            200,
+            r#"{"choices":[{"index":0,"message":{"content":"First"}},{"index":1,"message":{"content":"Second"}}],
+                "usage":{"prompt_tokens":5,"completion_tokens":4,"total_tokens":9}}"#,
+        ),
+    )])
+    .await;
+    let client = client_for(api_url, 5);
+
+    let (choices, usage) = client.chat_completion_choices(user_message("hi"), 0.7, 2).await.unwrap();
+
+    assert_eq!(choices, vec!["First", "Second"]);
+    assert_eq!(usage.unwrap().completion_tokens, 4);
+}
+
+#[tokio::test]
+async fn completion_error_status_includes_body() {
+    let api_url = spawn_mock(vec![(
+        "/v1/chat/completions",
+        MockResponse::json(500, r#"{"error":{"message":"model is still loading"}}"#),
+    )])
+    .await;
+    let client = client_for(api_url, 5);
+
+    let err = client.chat_completion_with_usage(user_message("hi"), 0.7).await.unwrap_err();
+
+    let message = err.to_string();
+    assert!(message.contains("500"), "unexpected error: {}", message);
+    assert!(message.contains("model is still loading"), "unexpected error: {}", message);
+}
+
+#[tokio::test]
+async fn completion_times_out() {
+    let mut slow = MockResponse::json(200, r#"{"choices":[{"message":{"content":"late"}}]}"#);
+    slow.delay = Duration::from_secs(3);
+    let api_url = spawn_mock(vec![("/v1/chat/completions", slow)]).await;
+    let client = client_for(api_url, 1);
+
+    let err = client.chat_completion_with_usage(user_message("hi"), 0.7).await.unwrap_err();
+
+    let reqwest_err = err.downcast_ref::<reqwest::Error>().expect("timeout should be a reqwest error");
+    assert!(reqwest_err.is_timeout());
+}
+
+#[tokio::test]
+async fn stream_collects_deltas_and_usage() {
+    let body = [
+        r#"data: {"choices":[{"delta":{"content":"Hel"}}]}"#,
+        r#"data: {"choices":[{"delta":{"content":"lo"}}]}"#,
+        r#"data: {"choices":[],"usage":{"prompt_tokens":4,"completion_tokens":2,"total_tokens":6}}"#,
+        "data: [DONE]",
+    ]
+    .join("\n\n")
+        + "\n\n";
+    let api_url = spawn_mock(vec![(
+        "/v1/chat/completions",
+        MockResponse {
+            status: 200,
+            content_type: "text/event-stream",
+            body,
+            delay: Duration::ZERO,
+        },
+    )])
+    .await;
+    let client = client_for(api_url, 5);
+
+    let mut deltas = Vec::new();
+    let (content, usage) = client
+        .chat_completion_stream(user_message("hi"), 0.7, |delta| deltas.push(delta.to_string()))
+        .await
+        .unwrap();
+
+    assert_eq!(deltas, vec!["Hel", "lo"]);
+    assert_eq!(content, "Hello");
+    assert_eq!(usage.unwrap().total_tokens, 6);
+}
+
+#[tokio::test]
+async fn list_server_models_reads_ids() {
+    let api_url = spawn_mock(vec![(
+        "/v1/models",
+        MockResponse::json(200, r#"{"object":"list","data":[{"id":"qwen3"},{"id":"gemma"}]}"#),
+    )])
+    .await;
+    let client = client_for(api_url, 5);
+
+    assert_eq!(client.list_server_models().await, vec!["qwen3", "gemma"]);
+}
+
+#[tokio::test]
+async fn list_server_models_is_empty_without_endpoint() {
+    let api_url = spawn_mock(vec![]).await;
+    let client = client_for(api_url, 5);
+
+    assert!(client.list_server_models().await.is_empty());
+}
+
+#[tokio::test]
+async fn health_reflects_status() {
+    let ready = spawn_mock(vec![("/health", MockResponse::json(200, r#"{"status":"ok"}"#))]).await;
+    let loading = spawn_mock(vec![(
+        "/health",
+        MockResponse::json(503, r#"{"error":{"message":"Loading model"}}"#),
+    )])
+    .await;
+
+    assert!(client_for(ready, 5).health().await);
+    assert!(!client_for(loading, 5).health().await);
+    assert!(!client_for("http://127.0.0.1:9/v1".to_string(), 5).health().await);
+}
+
+#[tokio::test]
+async fn server_props_hits_server_root() {
+    let api_url = spawn_mock(vec![(
+        "/props",
+        MockResponse::json(200, r#"{"model_path":"/models/qwen3.gguf"}"#),
+    )])
+    .await;
+    let client = client_for(api_url, 5);
+
+    let props = client.server_props().await.unwrap();
+
+    assert_eq!(props["model_path"], "/models/qwen3.gguf");
+}
+
+// Stream one SSE delta every `interval` until the client hangs up; `closed` is
+// set once a write fails, i.e. the server noticed the disconnect
+async fn spawn_endless_stream(interval: Duration, closed: std::sync::Arc<std::sync::atomic::AtomicBool>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    tokio::spawn(async move {
+        let Ok((mut socket, _)) = listener.accept().await else {
+            return;
+        };
+        read_request_path(&mut socket).await;
+        let headers = "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nTransfer-Encoding: chunked\r\n\r\n";
+        if socket.write_all(headers.as_bytes()).await.is_err() {
+            return;
+        }
+        loop {
+            let event = "data: {\"choices\":[{\"delta\":{\"content\":\"tok\"}}]}\n\n";
+            let chunk = format!("{:x}\r\n{}\r\n", event.len(), event);
+            if socket.write_all(chunk.as_bytes()).await.is_err() {
+                closed.store(true, std::sync::atomic::Ordering::SeqCst);
+                return;
+            }
+            tokio::time::sleep(interval).await;
+        }
+    });
+
+    format!("http://127.0.0.1:{}/v1", port)
+}
+
+#[tokio::test]
+async fn cancelled_stream_closes_connection_and_stops_deltas() {
+    let closed = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let api_url = spawn_endless_stream(Duration::from_millis(20), closed.clone()).await;
+    let client = client_for(api_url, 30);
+    let (delta_tx, mut delta_rx) = tokio::sync::mpsc::unbounded_channel();
+
+    let task = tokio::spawn(async move {
+        client
+            .chat_completion_stream(user_message("hi"), 0.7, |delta| {
+                let _ = delta_tx.send(delta.to_string());
+            })
+            .await
+    });
+    assert_eq!(delta_rx.recv().await.as_deref(), Some("tok"));
+
+    task.abort();
+    assert!(task.await.unwrap_err().is_cancelled());
+    while delta_rx.try_recv().is_ok() {}
+
+    // The server sees the hang-up on its next write; nothing arrives after it
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(2);
+    while !closed.load(std::sync::atomic::Ordering::SeqCst) && tokio::time::Instant::now() < deadline {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert!(closed.load(std::sync::atomic::Ordering::SeqCst), "server never saw the connection close");
+    assert!(delta_rx.recv().await.is_none(), "delta delivered after cancel");
+}
+
+#[test]
+fn request_preview_matches_what_a_generation_sends() {
+    let mut client = client_for("http://127.0.0.1:9/v1".to_string(), 5);
+    client.top_p = Some(0.9);
+    client.stop = vec!["</s>".to_string()];
+
+    let streamed: serde_json::Value =
+        serde_json::from_str(&client.request_preview(user_message("hi"), 0.3, 1).unwrap()).unwrap();
+    assert_eq!(streamed["messages"][0]["content"], "hi");
+    assert_eq!(streamed["stream"], true);
+    assert_eq!(streamed["top_p"].as_f64().map(|p| (p * 10.0).round()), Some(9.0));
+    assert_eq!(streamed["stop"][0], "</s>");
+    assert!(streamed.get("n").is_none());
+
+    // Candidates go out in one non-streamed request
+    let candidates: serde_json::Value =
+        serde_json::from_str(&client.request_preview(user_message("hi"), 0.3, 3).unwrap()).unwrap();
+    assert_eq!(candidates["n"], 3);
+    assert!(candidates.get("stream").is_none());
+}
+
+#[test]
+fn think_level_is_sent_and_spelled_out_on_the_last_user_message() {
+    let mut client = client_for("http://127.0.0.1:9/v1".to_string(), 5);
+    let preview = |client: &LlmClient| -> serde_json::Value {
+        serde_json::from_str(&client.request_preview(user_message("hi"), 0.3, 1).unwrap()).unwrap()
+    };
+
+    let plain = preview(&client);
+    assert_eq!(plain["messages"][0]["content"], "hi");
+    assert!(plain.get("reasoning_effort").is_none() && plain.get("chat_template_kwargs").is_none());
+
+    client.think = ThinkLevel::Off;
+    let off = preview(&client);
+    assert_eq!(off["messages"][0]["content"], "hi\n\n/no_think");
+    assert_eq!(off["chat_template_kwargs"]["enable_thinking"], false);
+
+    client.think = ThinkLevel::Low;
+    let low = preview(&client);
+    assert_eq!(low["reasoning_effort"], "low");
+    assert!(low["messages"][0]["content"].as_str().unwrap().starts_with("hi\n\nThink briefly"));
+
+    client.think = ThinkLevel::Medium;
+    assert_eq!(preview(&client)["messages"][0]["content"], "hi");
+}
+
+#[tokio::test]
+async fn benchmark_runs_report_progress_and_average() {
+    let api_url = spawn_mock(vec![(
+        "/v1/chat/completions",
+        MockResponse::json(
+            200,
+            r#"{"choices":[{"message":{"role":"assistant","content":"fn fib"}}],
+                "usage":{"prompt_tokens":20,"completion_tokens":40,"total_tokens":60}}"#,
+        ),
+    )])
+    .await;
+    let client = client_for(api_url, 5);
+    let progress = std::sync::Mutex::new(Vec::new());
+
+    let results = rubox::commands::run_benchmark(&client, 0.7, 3, |done| progress.lock().unwrap().push(done))
+        .await
+        .unwrap();
+
+    assert_eq!(*progress.lock().unwrap(), vec![1, 2, 3]);
+    assert_eq!(results.len(), 3);
+    assert!(results.iter().all(|(_, tokens, _)| *tokens == 40));
+    let report = rubox::commands::benchmark_report("qwen3-vl", &results).text();
+    assert_eq!(report.lines().count(), 5, "header, three runs, average:\n{}", report);
+    assert!(report.lines().last().unwrap().starts_with("average"));
+}
+
+// Send each of `writes` as its own HTTP chunk, pausing `interval` before each
+// one, then end the body
+async fn spawn_paced_stream(writes: Vec<Vec<u8>>, interval: Duration) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    tokio::spawn(async move {
+        let Ok((mut socket, _)) = listener.accept().await else {
+            return;
+        };
+        read_request_path(&mut socket).await;
+        let headers = "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nTransfer-Encoding: chunked\r\n\r\n";
+        let _ = socket.write_all(headers.as_bytes()).await;
+        for write in writes {
+            tokio::time::sleep(interval).await;
+            let mut chunk = format!("{:x}\r\n", write.len()).into_bytes();
+            chunk.extend_from_slice(&write);
+            chunk.extend_from_slice(b"\r\n");
+            if socket.write_all(&chunk).await.is_err() {
+                return;
+            }
+        }
+        let _ = socket.write_all(b"0\r\n\r\n").await;
+    });
+
+    format!("http://127.0.0.1:{}/v1", port)
+}
+
+fn delta_event(content: &str) -> Vec<u8> {
+    format!("data: {{\"choices\":[{{\"delta\":{{\"content\":\"{}\"}}}}]}}\n\n", content).into_bytes()
+}
+
+#[tokio::test]
+async fn stream_keeps_characters_split_across_chunks() {
+    let event = delta_event("café 世界");
+    // Cut inside the three bytes of 世
+    let split = event.windows(3).position(|w| w == "世".as_bytes()).unwrap() + 1;
+    let writes = vec![event[..split].to_vec(), event[split..].to_vec(), b"data: [DONE]\n\n".to_vec()];
+    let api_url = spawn_paced_stream(writes, Duration::from_millis(50)).await;
+    let client = client_for(api_url, 5);
+
+    let (content, _) = client.chat_completion_stream(user_message("hi"), 0.7, |_| {}).await.unwrap();
+
+    assert_eq!(content, "café 世界");
+}
+
+#[tokio::test]
+async fn stream_outlives_the_request_timeout_while_data_keeps_coming() {
+    let mut writes: Vec<Vec<u8>> = (0..5).map(|_| delta_event("tok")).collect();
+    writes.push(b"data: [DONE]\n\n".to_vec());
+    let api_url = spawn_paced_stream(writes, Duration::from_millis(400)).await;
+    let client = client_for(api_url, 1);
+
+    let (content, _) = client.chat_completion_stream(user_message("hi"), 0.7, |_| {}).await.unwrap();
+    assert_eq!(content, "toktoktoktoktok");
+
+    // A server that goes quiet for longer than the timeout still fails
+    let api_url = spawn_paced_stream(vec![delta_event("tok"), delta_event("late")], Duration::from_secs(2)).await;
+    let client = client_for(api_url, 1);
+    let err = client.chat_completion_stream(user_message("hi"), 0.7, |_| {}).await.unwrap_err();
+    assert!(err.is::<tokio::time::error::Elapsed>(), "unexpected error: {}", err);
+}
+
+#[tokio::test]
+async fn zero_request_timeout_waits_for_the_reply() {
+    let mut slow = MockResponse::json(200, r#"{"choices":[{"message":{"content":"late"}}]}"#);
+    slow.delay = Duration::from_millis(300);
+    let api_url = spawn_mock(vec![("/v1/chat/completions", slow)]).await;
+    let client = client_for(api_url, 0);
+
+    let (content, _) = client.chat_completion_with_usage(user_message("hi"), 0.7).await.unwrap();
+    assert_eq!(content, "late");
+
+    let api_url = spawn_paced_stream(vec![delta_event("tok"), b"data: [DONE]\n\n".to_vec()], Duration::from_millis(300)).await;
+    let client = client_for(api_url, 0);
+    let (content, _) = client.chat_completion_stream(user_message("hi"), 0.7, |_| {}).await.unwrap();
+    assert_eq!(content, "tok");
+}
+
+#[tokio::test]
+async fn failed_send_leaves_the_session_unchanged() {
+    let api_url = spawn_mock(vec![(
+        "/v1/chat/completions",
+        MockResponse::json(500, r#"{"error":"boom"}"#),
+    )])
+    .await;
+    let mut config = RuboxConfig::default();
+    config.llm.api_url = api_url;
+    let mut rubox = Rubox::new(config);
+    rubox.session_mut().add_message("user".to_string(), "Earlier".to_string(), None);
+
+    assert!(rubox.send("Hello").await.is_err());
+
+    let contents: Vec<_> = rubox.session().messages.iter().map(|m| m.content.as_str()).collect();
+    assert_eq!(contents, vec!["Earlier"], "the unanswered prompt is taken back out");
+    assert_eq!(rubox.session().metadata.message_count, 1);
+}
@@ -0,0 +1,91 @@
+// Markdown rendering as plain text, one string per rendered line
+
+use rubox::config::ThemeConfig;
+use rubox::tui::{code_blocks, parse_markdown_to_lines, Theme};
+
+fn render(markdown: &str) -> Vec<String> {
+    let (theme, _) = Theme::from_config(&ThemeConfig::default());
+    parse_markdown_to_lines(markdown, &theme)
+        .iter()
+        .map(|line| line.spans.iter().map(|span| span.content.as_ref()).collect())
+        .collect()
+}
+
+#[test]
+fn paragraphs_and_headings_are_separated_by_one_blank_line() {
+    let markdown = "# Title\n\nFirst paragraph\nwraps here.\n\n\n\nSecond paragraph.\n\n## Next\nText";
+
+    assert_eq!(render(markdown), vec![
+        "Title",
+        "",
+        "First paragraph",
+        "wraps here.",
+        "",
+        "Second paragraph.",
+        "",
+        "Next",
+        "",
+        "Text",
+    ]);
+}
+
+#[test]
+fn lists_nest_and_stay_apart_from_surrounding_text() {
+    let markdown = "Steps:\n\n1. Install\n2. Configure\n   - port\n   - model\n3. Run\n\nDone.";
+
+    assert_eq!(render(markdown), vec![
+        "Steps:",
+        "",
+        "  1. Install",
+        "  2. Configure",
+        "    • port",
+        "    • model",
+        "  3. Run",
+        "",
+        "Done.",
+    ]);
+}
+
+#[test]
+fn code_inside_a_list_item_is_indented_to_the_item_text() {
+    let markdown = "- Build it:\n\n  ```sh\n  cargo build\n  cargo test\n  ```\n\n  Then run it.\n- Done\n\n```\nplain\n```";
+
+    assert_eq!(render(markdown), vec![
+        "  • Build it:",
+        "    ┌─ Code ─",
+        "    │ cargo build",
+        "    │ cargo test",
+        "    └─────────",
+        "    Then run it.",
+        "  • Done",
+        "",
+        "┌─ Code ─",
+        "│ plain",
+        "└─────────",
+    ]);
+}
+
+#[test]
+fn block_quotes_get_a_gutter_per_nesting_level() {
+    let markdown = "You wrote:\n\n> First line\n> second line\n>\n> > Nested quote\n>\n> Back out\n\nMy answer.";
+
+    assert_eq!(render(markdown), vec![
+        "You wrote:",
+        "",
+        "│ First line",
+        "│ second line",
+        "│ ",
+        "│ │ Nested quote",
+        "│ ",
+        "│ Back out",
+        "",
+        "My answer.",
+    ]);
+}
+
+#[test]
+fn code_blocks_are_extracted_without_fences_or_language() {
+    let reply = "Install it:\n\n```bash\ncargo add serde\n```\n\nThen:\n\n- step\n\n  ~~~rust\n  fn main() {}\n  ~~~\n\n    indented, not fenced\n\nInline `code` is ignored.";
+    assert_eq!(code_blocks(reply), vec!["cargo add serde", "fn main() {}"]);
+    assert!(code_blocks("No code here").is_empty());
+}
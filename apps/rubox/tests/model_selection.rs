@@ -0,0 +1,25 @@
+use rubox::ui::parse_model_selection;
+
+fn models() -> Vec<String> {
+    ["qwen3-coder", "Qwen2.5-7b", "llama-3.1", "gemma-2"].iter().map(|s| s.to_string()).collect()
+}
+
+#[test]
+fn selection_expands_patterns_alongside_numbers() {
+    let models = models();
+
+    assert_eq!(parse_model_selection("*", &models).unwrap(), vec![0, 1, 2, 3]);
+    assert_eq!(parse_model_selection("qwen*", &models).unwrap(), vec![0, 1]);
+    // Numbers and patterns mix; repeats are dropped, first position wins
+    assert_eq!(parse_model_selection("3, qwen*, 1", &models).unwrap(), vec![2, 0, 1]);
+    assert_eq!(parse_model_selection("2,9", &models).unwrap(), vec![1]);
+}
+
+#[test]
+fn selection_rejects_patterns_that_match_nothing() {
+    let models = models();
+
+    let err = parse_model_selection("1,mistral*", &models).unwrap_err();
+    assert_eq!(err.to_string(), "No models match 'mistral*'");
+    assert!(parse_model_selection("0,abc", &models).is_err());
+}
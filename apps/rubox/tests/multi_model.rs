@@ -0,0 +1,183 @@
+// Multi-model comparisons against canned endpoints, one per model, so the
+// rounds, ordering and failure reporting can be checked without llama-server.
+
+use rubox::multi_model::run_multi_model;
+use rubox::{RuboxConfig, ServerManager};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+// Answer every completion with "<name> turn <n>", n being the user turns in
+// the request, after `delay`; the first `failures` requests get a 500 instead.
+// Returns the API base URL.
+async fn spawn_model(name: &'static str, delay: Duration, failures: usize) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let requests = Arc::new(AtomicUsize::new(0));
+    tokio::spawn(async move {
+        while let Ok((mut socket, _)) = listener.accept().await {
+            let requests = requests.clone();
+            tokio::spawn(async move {
+                let request = read_request(&mut socket).await;
+                let turns = request.matches(r#""role":"user""#).count();
+                tokio::time::sleep(delay).await;
+                let (status, body) = if requests.fetch_add(1, Ordering::SeqCst) < failures {
+                    ("500 Internal Server Error", r#"{"error":"boom"}"#.to_string())
+                } else {
+                    ("200 OK", format!(
+                        r#"{{"choices":[{{"message":{{"role":"assistant","content":"{} turn {}"}}}}]}}"#,
+                        name, turns
+                    ))
+                };
+                let reply = format!(
+                    "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                let _ = socket.write_all(reply.as_bytes()).await;
+            });
+        }
+    });
+    format!("http://127.0.0.1:{}/v1", port)
+}
+
+// Headers and body, per Content-Length
+async fn read_request(socket: &mut tokio::net::TcpStream) -> String {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 4096];
+    loop {
+        let n = socket.read(&mut chunk).await.unwrap_or(0);
+        if n == 0 {
+            break;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+        let text = String::from_utf8_lossy(&buf);
+        if let Some(header_end) = text.find("\r\n\r\n") {
+            let content_length = text[..header_end]
+                .lines()
+                .find_map(|line| {
+                    let (name, value) = line.split_once(':')?;
+                    name.eq_ignore_ascii_case("content-length").then(|| value.trim().parse::<usize>().ok())?
+                })
+                .unwrap_or(0);
+            if buf.len() >= header_end + 4 + content_length {
+                break;
+            }
+        }
+    }
+    String::from_utf8_lossy(&buf).to_string()
+}
+
+// Output directories under a fresh temp dir and every model on its own
+// endpoint, as with llm.manage_server off
+struct Fixture {
+    config: RuboxConfig,
+    dir: PathBuf,
+}
+
+impl Fixture {
+    fn new(name: &str, endpoints: Vec<(&str, String)>) -> Self {
+        let dir = std::env::temp_dir().join(format!("rubox-multi-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut config = RuboxConfig::default();
+        config.llm.manage_server = false;
+        for (model, url) in endpoints {
+            config.llm.endpoints.insert(model.to_string(), url);
+        }
+        let path = |sub: &str| dir.join(sub).to_string_lossy().to_string();
+        config.directories.output = path("output");
+        config.directories.tmp_md = path("tmp_md");
+        config.directories.prompts = path("prompts");
+        Fixture { config, dir }
+    }
+
+    // Run the comparison with `follow_ups` typed between rounds and return
+    // the results file
+    async fn run(&self, models: &[&str], follow_ups: &[&str]) -> String {
+        let targets: Vec<(String, String)> = models.iter().map(|m| (m.to_string(), m.to_string())).collect();
+        let mut follow_ups = follow_ups.iter().map(|f| f.to_string());
+        run_multi_model(&targets, "Explain ownership".to_string(), &self.config, &mut ServerManager::new(), || {
+            Ok(follow_ups.next().unwrap_or_default())
+        })
+        .await
+        .unwrap();
+
+        let results: Vec<PathBuf> = std::fs::read_dir(self.dir.join("output"))
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "md"))
+            .collect();
+        assert_eq!(results.len(), 1, "one results file per run");
+        std::fs::read_to_string(&results[0]).unwrap()
+    }
+}
+
+impl Drop for Fixture {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+#[tokio::test]
+async fn follow_ups_continue_every_model_conversation() {
+    let mut fixture = Fixture::new(
+        "follow-ups",
+        vec![("a", spawn_model("a", Duration::ZERO, 0).await), ("b", spawn_model("b", Duration::ZERO, 0).await)],
+    );
+    // One model after another, the way a local server is used
+    fixture.config.llm.multi_model_concurrency = 1;
+
+    let results = fixture.run(&["a", "b"], &["And in C++?", "/exit"]).await;
+
+    // Each round is sent with the model's whole thread, so turn 2 follows turn 1
+    let expected = "# a\n\na turn 1\n\n---\n\n# b\n\nb turn 1\n\n---\n\n## Follow-up 2\n\nAnd in C++?\n\n---\n\n# a\n\na turn 2\n\n---\n\n# b\n\nb turn 2\n\n---\n\n## Summary (2 round(s))\n\n- Succeeded: a, b\n";
+    assert_eq!(results, expected);
+    assert_eq!(std::fs::read_dir(fixture.dir.join("prompts")).unwrap().count(), 2, "one prompt file per round");
+}
+
+#[tokio::test]
+async fn concurrent_replies_keep_the_selection_order() {
+    // The first model answers last; with two at a time the third starts
+    // as soon as the second is done
+    let mut fixture = Fixture::new(
+        "concurrent",
+        vec![
+            ("slow", spawn_model("slow", Duration::from_millis(400), 0).await),
+            ("b", spawn_model("b", Duration::ZERO, 0).await),
+            ("c", spawn_model("c", Duration::ZERO, 0).await),
+        ],
+    );
+    fixture.config.llm.multi_model_concurrency = 2;
+
+    let results = fixture.run(&["slow", "b", "c"], &[]).await;
+
+    assert_eq!(results, "# slow\n\nslow turn 1\n\n---\n\n# b\n\nb turn 1\n\n---\n\n# c\n\nc turn 1\n\n---\n\n## Summary (1 round(s))\n\n- Succeeded: slow, b, c\n");
+}
+
+#[tokio::test]
+async fn failed_models_are_retried_and_summarised() {
+    let mut fixture = Fixture::new(
+        "failures",
+        vec![
+            ("flaky", spawn_model("flaky", Duration::ZERO, 1).await),
+            ("down", spawn_model("down", Duration::ZERO, usize::MAX).await),
+        ],
+    );
+    fixture.config.llm.multi_model_retries = 1;
+
+    let results = fixture.run(&["flaky", "down"], &[]).await;
+
+    // One retry is enough for the first; the second keeps failing and says so
+    let error = r#"server returned 500 Internal Server Error: {"error":"boom"}"#;
+    assert_eq!(
+        results,
+        format!(
+            "# flaky\n\nflaky turn 1\n\n---\n\n# down\n\n_Error: {}_\n\n---\n\n## Summary (1 round(s))\n\n- Succeeded: flaky\n- down failed in round 1: {}\n",
+            error, error
+        )
+    );
+}
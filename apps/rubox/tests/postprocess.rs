@@ -0,0 +1,42 @@
+// Reply cleanup steps, each on its own and combined through the config switches
+
+use rubox::config::ResponseConfig;
+use rubox::postprocess::{clean_response, close_code_fences, collapse_blank_lines, trim_trailing_whitespace};
+
+#[test]
+fn trailing_whitespace_is_trimmed_per_line_and_at_the_end() {
+    assert_eq!(trim_trailing_whitespace("Hello  \nworld\t\n\n  \n"), "Hello\nworld");
+    assert_eq!(trim_trailing_whitespace("  indented stays"), "  indented stays");
+}
+
+#[test]
+fn unclosed_code_fences_are_closed_with_the_same_marker() {
+    assert_eq!(close_code_fences("```rust\nfn main() {}"), "```rust\nfn main() {}\n```");
+    assert_eq!(close_code_fences("~~~~\ncode\n"), "~~~~\ncode\n~~~~");
+    // A shorter or different fence inside the block doesn't close it
+    assert_eq!(close_code_fences("````md\n```\n~~~"), "````md\n```\n~~~\n````");
+
+    let balanced = "```\na\n```\ntext ``` inline";
+    assert_eq!(close_code_fences(balanced), balanced);
+}
+
+#[test]
+fn blank_line_runs_collapse_outside_code_blocks() {
+    assert_eq!(collapse_blank_lines("a\n\n\n\nb\n"), "a\n\nb\n");
+    let code = "```\nx\n\n\ny\n```";
+    assert_eq!(collapse_blank_lines(code), code);
+}
+
+#[test]
+fn config_switches_select_the_steps() {
+    let text = "Intro  \n\n\n\n```py\nprint(1)  ";
+
+    let defaults = ResponseConfig::default();
+    assert_eq!(clean_response(text, &defaults), "Intro\n\n\n\n```py\nprint(1)\n```");
+
+    let all = ResponseConfig { collapse_blank_lines: true, ..ResponseConfig::default() };
+    assert_eq!(clean_response(text, &all), "Intro\n\n```py\nprint(1)\n```");
+
+    let raw = ResponseConfig { trim_trailing_whitespace: false, close_code_fences: false, collapse_blank_lines: false };
+    assert_eq!(clean_response(text, &raw), text);
+}
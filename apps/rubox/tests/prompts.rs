@@ -0,0 +1,44 @@
+use rubox::prompts::{initial_prompt, PromptManager};
+use std::fs;
+
+#[test]
+fn binary_prompt_file_reports_invalid_utf8() {
+    let dir = std::env::temp_dir().join(format!("rubox-prompts-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    // Start of a GGUF header followed by bytes that aren't UTF-8
+    fs::write(dir.join("model.txt"), b"GGUF\x03\x00\x00\x00\xff\xfe\x80").unwrap();
+    fs::write(dir.join("review.txt"), "Review this code").unwrap();
+    let pm = PromptManager::new(dir.to_string_lossy().into_owned());
+
+    let err = pm.load_prompt("model").unwrap_err();
+
+    assert!(err.to_string().starts_with("Prompt file is not valid UTF-8 text"), "{}", err);
+    assert_eq!(pm.load_prompt("review").unwrap(), "Review this code");
+}
+
+#[test]
+fn initial_prompt_follows_the_precedence_chain() {
+    let dir = std::env::temp_dir().join(format!("rubox-initial-prompt-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("review.md");
+    let input = dir.join("prompt_input.txt");
+    fs::write(&file, "  From the file\n").unwrap();
+    fs::write(&input, "From prompt_input.txt\n").unwrap();
+
+    assert_eq!(initial_prompt(Some("Inline"), Some(&file), &input).unwrap().as_deref(), Some("Inline"));
+    assert_eq!(initial_prompt(None, Some(&file), &input).unwrap().as_deref(), Some("From the file"));
+    assert_eq!(initial_prompt(None, None, &input).unwrap().as_deref(), Some("From prompt_input.txt"));
+
+    // A missing --prompt-file is an error, not a fall-through to the next source
+    let err = initial_prompt(None, Some(&dir.join("missing.md")), &input).unwrap_err();
+    assert!(err.to_string().starts_with("Prompt file not found"), "{}", err);
+
+    // An empty prompt_input.txt leaves asking to the caller
+    fs::write(&input, "\n").unwrap();
+    assert_eq!(initial_prompt(None, None, &input).unwrap(), None);
+    assert_eq!(initial_prompt(None, None, &dir.join("absent.txt")).unwrap(), None);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,57 @@
+// --model auto: which model the default router picks, and the fallback when it has no opinion
+
+use rubox::router::{choose_model, default_router, ModelInfo, Route};
+
+fn model(name: &str, vision: bool, size_gb: u64, context_window: u32) -> ModelInfo {
+    ModelInfo { name: name.to_string(), vision, size_bytes: Some(size_gb << 30), context_window }
+}
+
+fn registry() -> Vec<ModelInfo> {
+    vec![
+        model("gemma3-4b", false, 3, 8192),
+        model("qwen3-128k", false, 18, 131072),
+        model("qwen3-vl", true, 6, 32768),
+    ]
+}
+
+fn picked(prompt: &str, models: &[ModelInfo]) -> String {
+    default_router(prompt, models).expect("a route").model
+}
+
+#[test]
+fn prompts_route_by_images_length_and_complexity() {
+    let models = registry();
+    assert_eq!(picked("What's in screenshot.PNG?", &models), "qwen3-vl");
+    assert_eq!(picked("Capital of France?", &models), "gemma3-4b");
+    assert_eq!(picked("Explain step by step how TLS works", &models), "qwen3-128k");
+    assert_eq!(picked("Why does this panic?\n```rust\nlet v: Vec<u8> = vec![]; v[0];\n```", &models), "qwen3-128k");
+    assert_eq!(picked(&"word ".repeat(300), &models), "qwen3-128k");
+
+    let route = default_router("Capital of France?", &models).unwrap();
+    assert!(route.reason.contains("short question"), "{}", route.reason);
+}
+
+#[test]
+fn without_name_hints_size_and_context_decide() {
+    let models = vec![model("big", false, 20, 8192), model("tiny-ctx", false, 2, 4096), model("long", false, 9, 65536)];
+    assert_eq!(picked("Hi there", &models), "tiny-ctx");
+    assert_eq!(picked("Please design a cache", &models), "long");
+}
+
+#[test]
+fn no_signal_or_no_suitable_model_falls_back_to_the_default() {
+    let models = registry();
+    // Multi-line but neither long nor complex
+    let middling = "Summarize these notes:\nmeeting moved to friday\nbring the slides";
+    assert_eq!(default_router(middling, &models), None);
+    let route = choose_model(middling, &models, default_router, "qwen3-vl");
+    assert_eq!(route.model, "qwen3-vl");
+
+    // No vision model to take the image
+    let text_only = vec![model("gemma3-4b", false, 3, 8192)];
+    assert_eq!(choose_model("describe photo.jpg", &text_only, default_router, "gemma3-4b").model, "gemma3-4b");
+
+    // Any function with the router signature plugs in
+    let always = |_: &str, models: &[ModelInfo]| Some(Route { model: models[1].name.clone(), reason: "fixed".into() });
+    assert_eq!(choose_model("anything", &models, always, "qwen3-vl").model, "qwen3-128k");
+}
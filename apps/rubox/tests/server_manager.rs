@@ -0,0 +1,69 @@
+use rubox::server_manager::{api_port, is_llama_server, pid_file_path, stop_kept_server, ServerRecord};
+use rubox::RuboxConfig;
+use std::fs;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+#[tokio::test]
+async fn stop_server_only_kills_a_recorded_llama_server() {
+    let dir = std::env::temp_dir().join(format!("rubox_test_pid_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let mut config = RuboxConfig::default();
+    config.directories.output = dir.to_string_lossy().to_string();
+    // No llama-server was started on this port, so the fallback finds nothing
+    config.llm.api_url = "http://127.0.0.1:1/v1".to_string();
+
+    assert_eq!(stop_kept_server(&config).await.unwrap(), None, "nothing recorded");
+
+    // A stale pid now belonging to another program (this test) is left alone
+    let record = ServerRecord { pid: std::process::id(), port: 1, model: "model.gguf".to_string() };
+    record.write(&config.directories.output).unwrap();
+    assert_eq!(ServerRecord::read(&config.directories.output), Some(record.clone()));
+    assert!(!record.is_alive(), "this test is not a llama-server");
+    let pid_file = pid_file_path(&config.directories.output);
+    assert_eq!(stop_kept_server(&config).await.unwrap(), None);
+    assert!(!pid_file.exists(), "the stale record is dropped");
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn api_port_falls_back_to_the_llama_server_default() {
+    assert_eq!(api_port("http://127.0.0.1:8090/v1"), 8090);
+    assert_eq!(api_port("http://localhost/v1"), 8081);
+}
+
+// Answer every request on an ephemeral port with the same status and body
+async fn spawn_responder(status: u16, content_type: &'static str, body: &'static str) -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        while let Ok((mut socket, _)) = listener.accept().await {
+            let mut buf = [0u8; 4096];
+            let _ = socket.read(&mut buf).await;
+            let reply = format!(
+                "HTTP/1.1 {} Mock\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                content_type,
+                body.len(),
+                body
+            );
+            let _ = socket.write_all(reply.as_bytes()).await;
+        }
+    });
+    port
+}
+
+#[tokio::test]
+async fn only_llama_server_answers_are_taken_for_a_running_server() {
+    let ready = spawn_responder(200, "application/json", r#"{"status":"ok"}"#).await;
+    assert!(is_llama_server(ready).await);
+    let loading = spawn_responder(503, "application/json", r#"{"error":{"code":503,"message":"Loading model","type":"unavailable_error"}}"#).await;
+    assert!(is_llama_server(loading).await);
+
+    // A dev server serving its page on every path is someone else's
+    let web = spawn_responder(200, "text/html", "<!doctype html><html></html>").await;
+    assert!(!is_llama_server(web).await);
+    let not_found = spawn_responder(404, "application/json", r#"{"error":"not found"}"#).await;
+    assert!(!is_llama_server(not_found).await);
+}
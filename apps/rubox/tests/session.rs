@@ -0,0 +1,194 @@
+use rubox::session::{split_thinking, Session, RATING_BAD, RATING_GOOD};
+use std::fs;
+
+fn scratch_dir(name: &str) -> String {
+    let dir = std::env::temp_dir().join(format!("rubox-session-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    dir.to_string_lossy().into_owned()
+}
+
+fn session_dirs(base: &str) -> Vec<String> {
+    let mut dirs: Vec<String> = fs::read_dir(base)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    dirs.sort();
+    dirs
+}
+
+#[test]
+fn split_thinking_separates_reasoning_from_answer() {
+    let (thinking, answer) = split_thinking("<think>\nFirst, add them.\n</think>\n\n2 + 2 = 4");
+
+    assert_eq!(thinking.as_deref(), Some("First, add them."));
+    assert_eq!(answer, "2 + 2 = 4");
+}
+
+#[test]
+fn split_thinking_leaves_plain_content_alone() {
+    let (thinking, answer) = split_thinking("No reasoning here");
+
+    assert!(thinking.is_none());
+    assert_eq!(answer, "No reasoning here");
+}
+
+#[test]
+fn split_thinking_drops_empty_blocks() {
+    let (thinking, answer) = split_thinking("<think>\n\n</think>\nShort answer");
+
+    assert!(thinking.is_none());
+    assert_eq!(answer, "Short answer");
+}
+
+#[test]
+fn split_thinking_handles_unterminated_block() {
+    let (thinking, answer) = split_thinking("<think>still going when the tokens ran out");
+
+    assert_eq!(thinking.as_deref(), Some("still going when the tokens ran out"));
+    assert_eq!(answer, "");
+}
+
+#[test]
+fn rename_moves_the_saved_directory() {
+    let base = scratch_dir("rename");
+    let mut session = Session::new("qwen3".to_string(), 0.7);
+    session.add_message("user".to_string(), "hello".to_string(), None);
+    session.save(&base).unwrap();
+
+    session.rename("refactor".to_string());
+    session.save(&base).unwrap();
+
+    assert_eq!(session_dirs(&base), vec![session.metadata.id.clone()]);
+    let reloaded = Session::load(&base, &session.metadata.id).unwrap();
+    assert_eq!(reloaded.messages.len(), 1);
+    let _ = fs::remove_dir_all(&base);
+}
+
+#[test]
+fn rename_onto_existing_session_is_refused() {
+    let base = scratch_dir("rename-clash");
+    // Two sessions started in the same second
+    let mut first = Session::new("qwen3".to_string(), 0.7);
+    first.metadata.id = "session_01012025_120000".to_string();
+    first.rename("taken".to_string());
+    first.save(&base).unwrap();
+
+    let mut second = Session::new("qwen3".to_string(), 0.7);
+    second.metadata.id = "session_01012025_120000".to_string();
+    second.rename("other".to_string());
+    second.save(&base).unwrap();
+    second.rename("taken".to_string());
+
+    assert!(second.save(&base).is_err());
+    assert_eq!(session_dirs(&base).len(), 2);
+    let _ = fs::remove_dir_all(&base);
+}
+
+#[test]
+fn rename_keeps_the_full_timestamp() {
+    let mut session = Session::new("qwen3".to_string(), 0.7);
+    session.metadata.id = "session_01012025_120000".to_string();
+
+    session.rename("first".to_string());
+    assert_eq!(session.metadata.id, "session_01012025_120000_first");
+
+    session.rename("second".to_string());
+    assert_eq!(session.metadata.id, "session_01012025_120000_second");
+    assert_eq!(session.metadata.label.as_deref(), Some("second"));
+}
+
+#[test]
+fn rename_with_underscored_labels_is_idempotent() {
+    let mut session = Session::new("qwen3".to_string(), 0.7);
+    session.metadata.id = "session_01012025_120000".to_string();
+
+    session.rename("api_design_notes".to_string());
+    session.rename("api_design_notes".to_string());
+    assert_eq!(session.metadata.id, "session_01012025_120000_api_design_notes");
+
+    session.rename("v2".to_string());
+    assert_eq!(session.metadata.id, "session_01012025_120000_v2");
+}
+
+#[test]
+fn archive_overflow_keeps_the_newest_messages() {
+    let base = scratch_dir("archive");
+    let mut session = Session::new("qwen3".to_string(), 0.7);
+    for n in 1..=5 {
+        session.add_message("user".to_string(), format!("message {}", n), None);
+    }
+
+    assert_eq!(session.archive_overflow(&base, 0).unwrap(), 0);
+    assert_eq!(session.archive_overflow(&base, 2).unwrap(), 3);
+
+    let kept: Vec<usize> = session.messages.iter().map(|m| m.id).collect();
+    assert_eq!(kept, vec![4, 5]);
+    let archived: Vec<usize> = session.load_archive(&base).iter().map(|m| m.id).collect();
+    assert_eq!(archived, vec![1, 2, 3]);
+
+    // New ids continue past the archived ones
+    session.add_message("user".to_string(), "message 6".to_string(), None);
+    assert_eq!(session.messages.last().unwrap().id, 6);
+    let _ = fs::remove_dir_all(&base);
+}
+
+#[test]
+fn regenerated_answers_are_kept_as_variants() {
+    let base = scratch_dir("variants");
+    let mut session = Session::new("qwen3-vl".to_string(), 0.7);
+    session.add_message("user".to_string(), "Name a color".to_string(), None);
+    session.add_message("assistant".to_string(), "Red".to_string(), None);
+
+    assert!(session.begin_regenerate());
+    assert_eq!(session.context_messages().count(), 1, "the old answer isn't sent again");
+    session.add_response("Blue".to_string(), None);
+
+    let answer = session.messages.last().unwrap();
+    assert_eq!(session.messages.len(), 2);
+    assert_eq!(answer.variants, vec!["Red", "Blue"]);
+    assert_eq!(answer.content, "Blue");
+
+    let id = answer.id;
+    assert_eq!(session.cycle_variant(id, true), Some(1));
+    assert_eq!(session.messages.last().unwrap().content, "Red");
+
+    session.save(&base).unwrap();
+    let loaded = Session::load(&base, &session.metadata.id).unwrap();
+    let answer = loaded.messages.last().unwrap();
+    assert_eq!(answer.variants.len(), 2);
+    assert_eq!((answer.selected, answer.content.as_str()), (0, "Red"));
+}
+
+#[test]
+fn ratings_persist_and_only_apply_to_answers() {
+    let base = scratch_dir("ratings");
+    let mut session = Session::new("qwen3-vl".to_string(), 0.7);
+    session.add_message("user".to_string(), "Name a color".to_string(), None);
+    session.add_message("assistant".to_string(), "Red".to_string(), None);
+
+    assert!(session.rate(1, Some(RATING_GOOD)).is_err(), "questions can't be rated");
+    session.rate(2, Some(RATING_BAD)).unwrap();
+
+    session.save(&base).unwrap();
+    let loaded = Session::load(&base, &session.metadata.id).unwrap();
+    assert_eq!(loaded.messages[0].rating, None);
+    assert_eq!(loaded.messages[1].rating, Some(RATING_BAD));
+}
+
+#[test]
+fn deleting_a_middle_message_keeps_the_other_ids() {
+    let mut session = Session::new("qwen3".to_string(), 0.7);
+    for text in ["first", "second", "third"] {
+        session.add_message("user".to_string(), text.to_string(), None);
+    }
+    let ids: Vec<usize> = session.messages.iter().map(|m| m.id).collect();
+
+    session.delete_message(ids[1]).unwrap();
+
+    assert_eq!(session.messages.iter().map(|m| m.id).collect::<Vec<_>>(), [ids[0], ids[2]]);
+    assert_eq!(session.get_message(ids[2]).unwrap().content, "third");
+
+    session.add_message("user".to_string(), "fourth".to_string(), None);
+    let next = session.messages.last().unwrap().id;
+    assert!(!ids.contains(&next), "id {} was reused", next);
+}
@@ -0,0 +1,385 @@
+// Rendering checks against ratatui's in-memory backend
+
+use ratatui::backend::TestBackend;
+use ratatui::layout::Position;
+use ratatui::Terminal;
+use rubox::config::ThemeConfig;
+use rubox::commands::{CommandOutput, SettingRow, Tone};
+use rubox::tui::{draw, render_transcript, App, LayoutMode, ModalType, Theme};
+use rubox::{LlmClient, RuboxConfig};
+
+fn app() -> App {
+    let config = RuboxConfig::default();
+    App::new("qwen3-vl".to_string(), 0.7, config.models.registry.clone(), LlmClient::new(&config))
+}
+
+fn render(app: &App, width: u16, height: u16) -> Terminal<TestBackend> {
+    let (theme, _) = Theme::from_config(&ThemeConfig::default());
+    let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+    terminal.draw(|f| draw(f, app, &theme)).unwrap();
+    terminal
+}
+
+#[test]
+fn short_terminal_uses_compact_layout_with_cursor_on_input_line() {
+    let mut app = app();
+    app.input_buffer = "hello".to_string();
+
+    let mut terminal = render(&app, 60, 12);
+
+    assert!(app.compact.get());
+    // Input sits right above the status bar, after the two-column prompt marker
+    assert_eq!(terminal.get_cursor_position().unwrap(), Position::new(2 + 5, 10));
+}
+
+#[test]
+fn tiny_terminal_renders_without_panicking() {
+    let mut app = app();
+    app.session.add_message("user".to_string(), "Hello there".to_string(), None);
+    app.input_buffer = "typing".to_string();
+
+    for (width, height) in [(10, 5), (1, 1), (0, 0)] {
+        render(&app, width, height);
+    }
+    app.open_modal(ModalType::ModelSelector);
+    let terminal = render(&app, 10, 5);
+
+    let screen: String = terminal.backend().buffer().content().iter().map(|c| c.symbol()).collect();
+    assert!(screen.contains("Terminal"), "expected the too-small notice, got {:?}", screen);
+}
+
+#[test]
+fn long_errors_keep_the_details_hint_visible() {
+    let mut app = app();
+    app.set_error("the server said something very long about why this failed ".repeat(3));
+
+    let terminal = render(&app, 60, 20);
+    let buffer = terminal.backend().buffer();
+    let status: String = (0..60).map(|x| buffer[(x, 19)].symbol().to_string()).collect();
+    assert!(status.contains("✗ Error"), "not the status bar: {:?}", status);
+    assert!(status.trim_end().ends_with("(? details)"), "hint clipped: {:?}", status);
+}
+
+#[test]
+fn toggle_overrides_automatic_layout() {
+    let mut app = app();
+    render(&app, 60, 40);
+    assert!(!app.compact.get());
+
+    app.toggle_layout();
+    render(&app, 60, 40);
+
+    assert_eq!(app.layout, LayoutMode::Compact);
+    assert!(app.compact.get());
+}
+
+#[test]
+fn full_layout_split_follows_the_ui_settings() {
+    let mut app = app();
+    app.layout = LayoutMode::Full;
+    let input_row = |app: &App| render(app, 60, 40).get_cursor_position().unwrap().y;
+    // The input box starts below the chat's share of the screen, cursor inside its border
+    assert_eq!(input_row(&app), 25);
+
+    app.chat_percent = 30;
+    assert_eq!(input_row(&app), 13);
+
+    // A taller input minimum wins over the chat share
+    app.chat_percent = 60;
+    app.input_min_rows = 30;
+    assert_eq!(input_row(&app), 10);
+}
+
+#[test]
+fn model_selector_scrolls_to_the_selection_and_filters_by_name() {
+    let mut app = app();
+    app.model_registry.clear();
+    for i in 1..=20 {
+        app.model_registry.insert(format!("model-{:02}", i), format!("/models/model-{:02}.gguf", i));
+    }
+    app.current_model = "model-01".to_string();
+    app.open_modal(ModalType::ModelSelector);
+    let screen = |app: &App| -> String {
+        let terminal = render(app, 80, 40);
+        terminal.backend().buffer().content().iter().map(|c| c.symbol()).collect()
+    };
+
+    let first = screen(&app);
+    let fits = app.model_viewport.get();
+    assert!(fits < 20, "the modal can't hold all 20 models");
+    assert!(first.contains(&format!("1-{} of 20", fits)));
+    assert!(!first.contains("model-20"));
+
+    for _ in 0..19 {
+        app.scroll_down();
+    }
+    assert_eq!(app.selected_model_idx, 19);
+    assert_eq!(app.model_scroll.get(), 20 - fits, "the window follows the selection");
+    assert!(screen(&app).contains("▶ model-20"));
+
+    for _ in 0..19 {
+        app.scroll_up();
+    }
+    assert_eq!(app.model_scroll.get(), 0);
+    assert!(screen(&app).contains("▶ model-01"));
+
+    // Typing narrows the list and starts over at the first match
+    app.scroll_down();
+    for c in "MODEL-1".chars() {
+        app.handle_input_char(c);
+    }
+    assert_eq!(app.selected_model_idx, 0);
+    assert_eq!(app.filtered_model_names().len(), 10);
+    assert!(screen(&app).contains("▶ model-10"));
+    app.handle_input_char('x');
+    assert!(screen(&app).contains("No models match 'MODEL-1x'"));
+}
+
+#[test]
+fn focusing_an_old_message_scrolls_it_into_view() {
+    let mut app = app();
+    for i in 1..=20 {
+        app.session.add_message("user".to_string(), format!("question {}", i), None);
+    }
+    render(&app, 60, 40);
+    assert_eq!(app.scroll_offset.get(), 0);
+
+    // Tab starts at the newest message, which is already on screen
+    app.move_focus(true);
+    assert_eq!(app.focused_message_id, Some(20));
+    assert_eq!(app.scroll_offset.get(), 0);
+
+    for _ in 0..19 {
+        app.move_focus(true);
+    }
+    assert_eq!(app.focused_message_id, Some(1));
+    assert_eq!(app.scroll_offset.get(), app.max_scroll(), "the first message is at the top");
+    assert!(!app.follow);
+
+    // Shift-Tab past the newest message drops the focus
+    for _ in 0..20 {
+        app.move_focus(false);
+    }
+    assert_eq!(app.focused_message_id, None);
+}
+
+#[test]
+fn new_messages_do_not_move_a_scrolled_up_view() {
+    let mut app = app();
+    for i in 1..=20 {
+        app.session.add_message("user".to_string(), format!("question {}", i), None);
+    }
+    render(&app, 60, 20);
+    app.scroll_up();
+    assert!(!app.follow);
+    let top = app.max_scroll() - app.scroll_offset.get();
+
+    app.session.add_message("user".to_string(), "a long follow-up ".repeat(10), None);
+    app.add_assistant_message("answer\n\nwith\n\nseveral lines".to_string(), None);
+    render(&app, 60, 20);
+    app.finish_reveal();
+    render(&app, 60, 20);
+
+    assert_eq!(app.max_scroll() - app.scroll_offset.get(), top);
+    assert!(app.has_new_message);
+
+    // Back at the bottom, new messages scroll into view again
+    app.scroll_to_bottom();
+    render(&app, 60, 20);
+    app.session.add_message("user".to_string(), "one more".to_string(), None);
+    render(&app, 60, 20);
+    assert_eq!(app.scroll_offset.get(), 0);
+}
+
+#[test]
+fn settings_panel_lists_names_and_values() {
+    let mut app = app();
+    let row = |name, value: &str| SettingRow { name, value: value.to_string(), range: String::new(), editable: true };
+    app.open_modal(ModalType::Settings);
+    app.settings = vec![row("top_p", "0.9"), row("max_tokens", "2048")];
+    let terminal = render(&app, 80, 40);
+
+    let screen: String = terminal.backend().buffer().content().iter().map(|c| c.symbol()).collect();
+    assert!(screen.contains("top_p"));
+    assert!(screen.contains("0.9"));
+    assert!(screen.contains("max_tokens"));
+}
+
+#[test]
+fn temperature_modal_shows_the_configured_range() {
+    let mut app = app();
+    app.temperature_range = (0.2, 1.5);
+    app.open_modal(ModalType::SetTemperature);
+    let terminal = render(&app, 80, 40);
+
+    let screen: String = terminal.backend().buffer().content().iter().map(|c| c.symbol()).collect();
+    assert!(screen.contains("Range: 0.2 - 1.5"));
+}
+
+#[test]
+fn save_modal_example_follows_the_filename_template() {
+    let mut app = app();
+    app.session.metadata.id = "chat1".to_string();
+    app.saved_message = "out/{session}-reply{id}".to_string();
+    app.open_modal(ModalType::SaveResponse);
+    let terminal = render(&app, 80, 40);
+
+    let screen: String = terminal.backend().buffer().content().iter().map(|c| c.symbol()).collect();
+    assert!(screen.contains("saves out/chat1-reply3.txt"));
+}
+
+#[test]
+fn context_gauge_shows_estimated_usage_on_wide_terminals() {
+    let mut app = app();
+    app.session.metadata.context_window = Some(100);
+    // 76 characters + template overhead = 23 estimated tokens
+    app.session.add_message("user".to_string(), "x".repeat(76), None);
+
+    let status = |terminal: &Terminal<TestBackend>| {
+        let buffer = terminal.backend().buffer();
+        let y = buffer.area.height - 1;
+        (0..buffer.area.width).map(|x| buffer[(x, y)].symbol()).collect::<String>()
+    };
+    assert!(status(&render(&app, 100, 30)).contains("ctx  23%"));
+    assert!(!status(&render(&app, 60, 30)).contains("ctx"), "no room on narrow terminals");
+}
+
+#[test]
+fn long_status_messages_stop_short_of_the_context_gauge() {
+    let mut app = app();
+    app.session.metadata.context_window = Some(100);
+    app.set_status("y".repeat(200));
+
+    let terminal = render(&app, 100, 30);
+    let buffer = terminal.backend().buffer();
+    let y = buffer.area.height - 1;
+    let status = (0..buffer.area.width).map(|x| buffer[(x, y)].symbol()).collect::<String>();
+    assert!(status.contains("…"), "status is shortened: {status}");
+    assert!(status.contains("ctx"), "gauge still drawn: {status}");
+}
+
+#[test]
+fn status_bar_tags_sampling_changed_from_the_defaults() {
+    let mut app = app();
+    let status = |app: &App| {
+        let terminal = render(app, 100, 30);
+        let buffer = terminal.backend().buffer();
+        let y = buffer.area.height - 1;
+        (0..buffer.area.width).map(|x| buffer[(x, y)].symbol()).collect::<String>()
+    };
+    assert!(!status(&app).contains("[custom]"));
+
+    app.temperature = 1.2;
+    assert_eq!(app.custom_sampling(), vec!["temp 1.2 (default 0.7)"]);
+    assert!(status(&app).contains("[custom]"));
+
+    app.temperature = 0.7;
+    app.candidate_count = 2;
+    assert_eq!(app.custom_sampling(), vec!["n 2 (default 1)"]);
+    app.candidate_count = 1;
+    assert!(!status(&app).contains("[custom]"));
+}
+
+#[test]
+fn suggestions_show_under_the_latest_reply_until_the_next_message() {
+    let mut app = app();
+    app.session.add_message("user".to_string(), "What is Rust?".to_string(), None);
+    app.session.add_message("assistant".to_string(), "A systems language.".to_string(), None);
+
+    app.set_suggestions(2, "1. How does borrowing work?\n- \"Is it fast?\"\n\n* What about async?\n4. Extra");
+    assert_eq!(app.suggestions, vec!["How does borrowing work?", "Is it fast?", "What about async?"]);
+    assert_eq!(app.suggestion('2').as_deref(), Some("Is it fast?"));
+    assert_eq!(app.suggestion('4'), None);
+
+    let terminal = render(&app, 60, 40);
+    let screen: String = terminal.backend().buffer().content().iter().map(|c| c.symbol()).collect();
+    assert!(screen.contains("[1] How does borrowing work?"));
+    assert!(screen.contains("press Alt+1-3 to send"), "plain digits stay typeable");
+
+    // Suggestions for an older message never show up
+    app.session.add_message("user".to_string(), "Next".to_string(), None);
+    assert!(app.visible_suggestions().is_empty());
+    app.set_suggestions(2, "Stale?");
+    assert!(app.suggestions.len() == 3 && app.visible_suggestions().is_empty());
+
+    app.start_generation(0.7);
+    assert!(app.suggestions.is_empty());
+}
+
+#[test]
+fn typewriter_reveals_a_reply_over_ticks_until_skipped() {
+    let mut app = app();
+    app.typewriter_cps = 40;
+    app.tick_ms = 250;  // 10 characters per tick
+    app.session.add_message("user".to_string(), "Count".to_string(), None);
+
+    app.add_assistant_message("one two three four five".to_string(), None);
+    let id = app.session.messages.last().unwrap().id;
+    assert_eq!(app.revealed_chars(id), Some(0));
+    app.tick();
+    assert_eq!(app.revealed_chars(id), Some(10));
+    app.tick();
+    app.tick();
+    assert_eq!(app.revealed_chars(id), None, "done once everything is shown");
+
+    app.add_assistant_message("again".to_string(), None);
+    assert!(app.is_revealing());
+    app.finish_reveal();
+    assert!(!app.is_revealing());
+
+    app.typewriter_cps = 0;
+    app.add_assistant_message("off".to_string(), None);
+    assert!(!app.is_revealing());
+}
+
+#[test]
+fn output_modal_shows_the_title_and_lines() {
+    let mut app = app();
+    let mut output = CommandOutput::new("Diff #1 → #2");
+    output.line(Tone::Removed, "- old line");
+    output.line(Tone::Added, "+ new line");
+
+    app.show_output(output);
+    let terminal = render(&app, 80, 40);
+    let screen: String = terminal.backend().buffer().content().iter().map(|c| c.symbol()).collect();
+    assert!(screen.contains("Diff #1 → #2"));
+    assert!(screen.contains("+ new line"));
+}
+
+#[test]
+fn benchmark_progress_shows_in_the_status_bar() {
+    let mut app = app();
+    app.benchmark_progress = Some((1, 2));
+    app.is_loading = true;
+    let terminal = render(&app, 80, 40);
+    let screen: String = terminal.backend().buffer().content().iter().map(|c| c.symbol()).collect();
+    assert!(screen.contains("Benchmark run 2/2... (esc to stop)"));
+}
+
+#[test]
+fn transcript_prints_headers_and_rendered_markdown() {
+    let config = RuboxConfig::default();
+    let (theme, _) = Theme::from_config(&ThemeConfig::default());
+    let mut app = app();
+    app.session.add_message("user".to_string(), "List two".to_string(), None);
+    app.session.add_message("assistant".to_string(), "<think>easy</think>**Sure:**\n\n- one\n- two".to_string(), None);
+
+    let plain = render_transcript(&app, &theme, &config, false);
+    assert_eq!(plain, "\n┌─ You ─\nList two\n\n┌─ qwen3-vl ─\nSure:\n\n  • one\n  • two\n");
+
+    let colored = render_transcript(&app, &theme, &config, true);
+    assert!(colored.contains("\x1b[1m"), "bold survives as an escape code");
+}
+
+#[test]
+fn status_bar_shows_the_temperature_a_generation_started_with() {
+    let mut app = app();
+    app.session.add_message("user".to_string(), "Hi".to_string(), None);
+    app.start_generation(0.3);
+    app.temperature = 1.5;
+
+    let terminal = render(&app, 120, 40);
+    let screen: String = terminal.backend().buffer().content().iter().map(|c| c.symbol()).collect();
+    assert!(screen.contains("Temp: 0.3"), "the in-flight reply still uses 0.3");
+    assert_eq!(app.temperature, 1.5);
+}